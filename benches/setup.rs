@@ -0,0 +1,115 @@
+//! Measures the CPU cost of bringing up the renderer and of drawing frames, to catch
+//! regressions in setup time. Needs a display to open a window on and a Vulkan device;
+//! run with `cargo bench`, which saves a baseline under `target/criterion` for the next
+//! run to be compared against.
+
+use std::{ffi::CStr, time::Duration};
+
+use anyhow::{anyhow, Result};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rusty_games::{
+    GraphicsPipeline, Instance, LogicalDevice, PhysicalDeviceSurface, PipelineCache,
+    PipelineConfig, Renderer, Shared, Surface, WindowConfig,
+};
+use winit::{event_loop::EventLoop, raw_window_handle::HasDisplayHandle, window::Window};
+
+fn pick_physical_device(
+    instance: &Shared<Instance>,
+    surface: &Shared<Surface>,
+) -> Result<PhysicalDeviceSurface> {
+    for physical_device in unsafe { instance.enumerate_physical_devices()? } {
+        let physical_device_surface =
+            PhysicalDeviceSurface::new(instance, surface, physical_device)?;
+        if physical_device_surface.is_suitable()?.is_ok() {
+            return Ok(physical_device_surface);
+        }
+    }
+    Err(anyhow!("Could not find a suitable physical device!"))
+}
+
+/// Times creating the instance and the device on their own, and the whole renderer
+fn bench_setup(c: &mut Criterion, window: &Shared<Window>) {
+    let required_extensions =
+        ash_window::enumerate_required_extensions(window.display_handle().unwrap().as_raw())
+            .unwrap()
+            .iter()
+            .map(|extension| unsafe { CStr::from_ptr(*extension) }.to_str().unwrap())
+            .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("setup");
+    // each run takes long enough that criterion's minimum sample count is plenty
+    group.sample_size(10);
+    group.bench_function("instance creation", |b| {
+        b.iter_with_large_drop(|| Instance::new(required_extensions.clone()).unwrap())
+    });
+
+    let instance = Shared::new(Instance::new(required_extensions.clone()).unwrap());
+    let surface = Shared::new(Surface::new(&instance, window).unwrap());
+    group.bench_function("device creation", |b| {
+        b.iter_batched(
+            || pick_physical_device(&instance, &surface).unwrap(),
+            |physical_device_surface| LogicalDevice::try_from(physical_device_surface).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+    drop(surface);
+    drop(instance);
+
+    // covers the instance, device, swapchain, pipeline and command pool
+    group.bench_function("renderer creation", |b| {
+        b.iter_with_large_drop(|| Renderer::new(window).unwrap())
+    });
+    group.finish();
+}
+
+/// Times creating the built-in graphics pipeline with and without a pipeline cache
+fn bench_pipelines(c: &mut Criterion, renderer: &Renderer) {
+    let logical_device = renderer.get_logical_device().unwrap();
+    let swapchain = renderer.get_swapchain().unwrap();
+
+    let mut group = c.benchmark_group("graphics pipeline creation");
+    // without a cache every pipeline is compiled from scratch
+    group.bench_function("uncached", |b| {
+        b.iter_with_large_drop(|| {
+            GraphicsPipeline::new::<()>(logical_device, swapchain, PipelineConfig::default())
+                .unwrap()
+        })
+    });
+    // criterion's warm up fills the cache, so the measured pipelines are all cache hits
+    let pipeline_cache = PipelineCache::new(logical_device).unwrap();
+    let cached_config = PipelineConfig::default().with_pipeline_cache(&pipeline_cache);
+    group.bench_function("cached", |b| {
+        b.iter_with_large_drop(|| {
+            GraphicsPipeline::new::<()>(logical_device, swapchain, cached_config).unwrap()
+        })
+    });
+    group.finish();
+}
+
+/// Times recording and submitting a frame
+fn bench_frames(c: &mut Criterion, renderer: &mut Renderer) {
+    // includes waiting on the frame in flight, so with a vsynced present mode this is
+    // bounded by the refresh rate once the GPU falls behind
+    c.bench_function("render frame", |b| {
+        b.iter(|| renderer.render_frame().unwrap())
+    });
+    renderer.wait_idle().unwrap();
+}
+
+fn benches(c: &mut Criterion) {
+    // the event loop can only be created once, so every benchmark shares its window
+    let event_loop = EventLoop::new().unwrap();
+    let window = Shared::new(WindowConfig::default().build(&event_loop).unwrap());
+
+    bench_setup(c, &window);
+    let mut renderer = Renderer::new(&window).unwrap();
+    bench_pipelines(c, &renderer);
+    bench_frames(c, &mut renderer);
+}
+
+criterion_group! {
+    name = setup;
+    config = Criterion::default().warm_up_time(Duration::from_secs(1));
+    targets = benches
+}
+criterion_main!(setup);
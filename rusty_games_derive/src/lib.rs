@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, LitInt, Result};
+
+/// Derives `rusty_games::Vertex` for a struct with named fields. Every field must be
+/// marked with `#[location(n)]`, the shader location it's read from; the format is
+/// picked from the field's type and the offset from its position in the struct.
+#[proc_macro_derive(Vertex, attributes(location))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_vertex(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_vertex(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "Vertex can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "Vertex can only be derived for structs",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "Vertex can't be derived for generic structs",
+        ));
+    }
+
+    let mut attribute_descriptions = Vec::with_capacity(fields.len());
+    let mut locations = HashSet::new();
+    for field in fields {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_ty = &field.ty;
+        let location_attr = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("location"))
+            .ok_or_else(|| Error::new(field.span(), "Missing #[location(n)] attribute"))?;
+        let location = location_attr
+            .parse_args::<LitInt>()?
+            .base10_parse::<u32>()?;
+        if !locations.insert(location) {
+            return Err(Error::new(
+                location_attr.span(),
+                format!("Location {location} is already used by another field"),
+            ));
+        }
+
+        attribute_descriptions.push(quote! {
+            ::rusty_games::__private::attribute_description_for::<#field_ty>(
+                #location,
+                ::std::mem::offset_of!(#name, #field_name),
+            )
+        });
+    }
+
+    Ok(quote! {
+        impl ::rusty_games::Vertex for #name {
+            fn binding_description() -> ::rusty_games::__private::VertexInputBindingDescription {
+                ::rusty_games::__private::binding_description_for::<#name>()
+            }
+
+            fn attribute_descriptions(
+            ) -> ::std::vec::Vec<::rusty_games::__private::VertexInputAttributeDescription> {
+                ::std::vec![#(#attribute_descriptions),*]
+            }
+        }
+    })
+}
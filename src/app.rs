@@ -0,0 +1,290 @@
+use anyhow::Result;
+use tracing::{error, info, warn};
+use winit::{
+    event::{ElementState, Event, KeyEvent, Modifiers, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::Window,
+};
+
+use crate::{
+    set_window_mode, FrameLimiter, Renderer, RustyGamesError, Shared, WindowConfig, WindowMode,
+};
+
+/// Hook for responding to window events (keyboard, resize, focus, etc) without
+/// having to rewrite the event loop. Closures taking a `&WindowEvent` implement
+/// this as well.
+pub trait EventHandler {
+    /// Called for every window event, except for close requests and redraws
+    /// which the [`App`] handles itself.
+    fn on_window_event(&mut self, event: &WindowEvent);
+}
+
+impl<F> EventHandler for F
+where
+    F: FnMut(&WindowEvent),
+{
+    fn on_window_event(&mut self, event: &WindowEvent) {
+        self(event)
+    }
+}
+
+/// Runs the event loop for a single window, rendering to it with a [`Renderer`].
+/// Handles closing, redrawing, resizing (including on DPI changes) and toggling
+/// fullscreen with Alt+Enter, and forwards window events on to the [`EventHandler`].
+pub struct App<H: EventHandler> {
+    /// Handles all of the vulkan resources needed to draw
+    /// to the window
+    renderer: Renderer,
+    window: Shared<Window>,
+    window_config: WindowConfig,
+    /// The mode the window is currently in, which may differ from
+    /// the configured one after toggling fullscreen
+    window_mode: WindowMode,
+    /// Modifier keys currently held down
+    modifiers: Modifiers,
+    /// If true, a new frame is drawn as soon as the previous one is done.
+    /// Otherwise frames are only drawn when the window needs redrawing.
+    continuous_redraw: bool,
+    /// Caps the frame rate, and measures it
+    frame_limiter: FrameLimiter,
+    event_handler: H,
+}
+
+impl<H: EventHandler> App<H> {
+    pub fn new(
+        event_loop: &EventLoop<()>,
+        window_config: WindowConfig,
+        event_handler: H,
+    ) -> Result<Self> {
+        let window = Shared::new(window_config.build(event_loop)?);
+        let renderer = Renderer::new(&window)?;
+
+        Ok(Self {
+            renderer,
+            window,
+            window_mode: window_config.mode,
+            window_config,
+            modifiers: Modifiers::default(),
+            continuous_redraw: false,
+            frame_limiter: FrameLimiter::new(None),
+            event_handler,
+        })
+    }
+
+    pub fn run(&mut self, event_loop: EventLoop<()>) -> Result<()> {
+        event_loop.run(move |event, elwp| match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                window_id: _,
+            } => {
+                elwp.exit();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::RedrawRequested,
+                window_id: _,
+            } => {
+                self.frame_limiter.wait();
+                let result = self.renderer.render_frame();
+                if !self.handle_error(result) {
+                    elwp.exit();
+                }
+            }
+            Event::WindowEvent {
+                event,
+                window_id: _,
+            } => {
+                let result = self.handle_window_event(&event);
+                if !self.handle_error(result) {
+                    elwp.exit();
+                }
+                self.event_handler.on_window_event(&event);
+            }
+            Event::AboutToWait => {
+                if self.continuous_redraw {
+                    self.window.request_redraw();
+                    elwp.set_control_flow(ControlFlow::Poll);
+                } else {
+                    // sleep until the next event, instead of spinning on the CPU
+                    elwp.set_control_flow(ControlFlow::Wait);
+                }
+            }
+            Event::LoopExiting => {
+                // wait for vulkan to finish up before exiting. errors here mean the device
+                // was lost, in which case there's nothing left to wait for
+                let _ = self.renderer.wait_idle();
+            }
+            _ => {}
+        })?;
+        Ok(())
+    }
+
+    /// Enables or disables continuously drawing frames. Games that animate every frame
+    /// should enable this; static scenes can leave it disabled (the default) to only
+    /// draw when the window needs it, which uses far less power.
+    pub fn request_continuous_redraw(&mut self, continuous_redraw: bool) {
+        self.continuous_redraw = continuous_redraw;
+        self.window.request_redraw();
+    }
+
+    /// Requests that a single frame be drawn, for when the scene has changed
+    /// while continuous redraws are disabled.
+    pub fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    /// Caps the frame rate to the target, on top of whatever the present mode allows, or
+    /// stops capping it if `None` (the default)
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) {
+        self.frame_limiter.set_target_fps(target_fps);
+    }
+
+    /// Returns the frame rate actually being drawn at, including any cap, once at least
+    /// two frames have been drawn
+    pub fn get_fps(&self) -> Option<f64> {
+        self.frame_limiter.get_fps()
+    }
+
+    pub fn get_renderer(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+
+    pub fn get_window(&self) -> &Shared<Window> {
+        &self.window
+    }
+
+    /// Handles the window events the app responds to itself, before they're forwarded
+    /// to the event handler
+    fn handle_window_event(&mut self, event: &WindowEvent) -> Result<()> {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Enter),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if self.modifiers.state().alt_key() => {
+                self.toggle_fullscreen();
+            }
+            // minimized windows have no area to draw to, so skip those
+            WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                self.renderer.resize()?;
+                self.window.request_redraw();
+            }
+            // moving to a monitor with a different DPI changes how many physical pixels
+            // the window covers, so the swapchain has to match the new size
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                info!(scale_factor, "Scale factor changed");
+                let size = self.window.inner_size();
+                if size.width > 0 && size.height > 0 {
+                    self.renderer.resize()?;
+                    self.window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Recovers from the errors the renderer can recover from, like the device being lost
+    /// or the driver changing, rather than crashing. Returns false if the error can't be
+    /// recovered from, or recovering failed, in which case it's logged and the app should
+    /// exit.
+    fn handle_error(&mut self, result: Result<()>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(error) if RustyGamesError::is_swapchain_out_of_date(&error) => {
+                // minimized windows have no area to draw to, they're resized when shown
+                let size = self.window.inner_size();
+                if size.width == 0 || size.height == 0 {
+                    return true;
+                }
+                let result = self.renderer.resize();
+                if result.is_ok() {
+                    self.window.request_redraw();
+                }
+                self.handle_error(result)
+            }
+            Err(error) if RustyGamesError::is_device_lost(&error) => {
+                warn!("Device lost, recreating the renderer");
+                match self.renderer.recover() {
+                    Ok(()) => {
+                        self.window.request_redraw();
+                        true
+                    }
+                    Err(error) => {
+                        error!(?error, "Failed to recover from device loss");
+                        false
+                    }
+                }
+            }
+            Err(error) if RustyGamesError::is_surface_lost(&error) => {
+                warn!("Surface lost, recreating it");
+                match self.renderer.recreate_surface() {
+                    Ok(()) => {
+                        self.window.request_redraw();
+                        true
+                    }
+                    Err(error) => {
+                        error!(?error, "Failed to recreate the surface");
+                        false
+                    }
+                }
+            }
+            Err(error) if RustyGamesError::is_initialization_failed(&error) => {
+                warn!("The driver changed, reinitializing the renderer");
+                match self.renderer.reinitialize() {
+                    Ok(()) => {
+                        self.window.request_redraw();
+                        true
+                    }
+                    Err(error) => {
+                        error!(?error, "Failed to reinitialize the renderer");
+                        false
+                    }
+                }
+            }
+            Err(error) => {
+                error!(?error, "Unrecoverable error, shutting down");
+                false
+            }
+        }
+    }
+
+    /// Switches between windowed and fullscreen. If the window was configured as windowed,
+    /// borderless fullscreen is used.
+    fn toggle_fullscreen(&mut self) {
+        self.window_mode = match (self.window_mode, self.window_config.mode) {
+            (WindowMode::Windowed, WindowMode::Windowed) => WindowMode::Borderless,
+            (WindowMode::Windowed, configured_mode) => configured_mode,
+            _ => WindowMode::Windowed,
+        };
+        info!(mode = ?self.window_mode, "Switching window mode");
+        set_window_mode(
+            &self.window,
+            self.window_mode,
+            self.window_config.get_size(),
+        );
+    }
+}
+
+impl<H: EventHandler> Drop for App<H> {
+    fn drop(&mut self) {
+        info!("Window closed, shutting down");
+
+        if let Some(debug_messenger) = self.renderer.get_debug_messenger() {
+            let data = debug_messenger.get_data();
+            info!(
+                errors = data.get_error_count(),
+                warnings = data.get_warning_count(),
+                "Validation messages received"
+            );
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::ops::Deref;
+
+use anyhow::{ensure, Result};
+use ash::vk::{
+    self, BufferCreateInfo, BufferUsageFlags, DeviceMemory, DeviceSize, MemoryAllocateInfo,
+    MemoryMapFlags, MemoryPropertyFlags, SharingMode,
+};
+
+use crate::{LogicalDevice, Shared};
+
+/// A buffer along with the memory backing it
+pub struct Buffer {
+    logical_device: Shared<LogicalDevice>,
+    buffer: vk::Buffer,
+    memory: DeviceMemory,
+    size: DeviceSize,
+}
+
+impl Buffer {
+    /// Creates a buffer of `size` bytes, in memory with all of the `properties`. Use
+    /// `HOST_VISIBLE | HOST_COHERENT` for buffers written with [`Buffer::write`], like
+    /// staging and uniform buffers.
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        size: DeviceSize,
+        usage: BufferUsageFlags,
+        properties: MemoryPropertyFlags,
+    ) -> Result<Self> {
+        let buffer_create_info = BufferCreateInfo::default()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(SharingMode::EXCLUSIVE);
+        let buffer = unsafe { logical_device.create_buffer(&buffer_create_info, None)? };
+
+        let memory = match allocate_memory(logical_device, buffer, properties) {
+            Ok(memory) => memory,
+            Err(err) => {
+                unsafe { logical_device.destroy_buffer(buffer, None) };
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            buffer,
+            memory,
+            size,
+        })
+    }
+
+    /// Copies `data` into the buffer, starting `offset` bytes in. The buffer's memory must
+    /// be host visible and coherent, and the GPU must not be using the range.
+    pub fn write(&self, offset: DeviceSize, data: &[u8]) -> Result<()> {
+        self.write_slice(offset, data)
+    }
+
+    /// Like [`Buffer::write`], but copies the raw bytes of a slice of values, such as
+    /// vertices or draw commands
+    pub fn write_slice<T: Copy>(&self, offset: DeviceSize, data: &[T]) -> Result<()> {
+        let len = std::mem::size_of_val(data) as DeviceSize;
+        ensure!(
+            offset + len <= self.size,
+            "Writing {} bytes at offset {} overflows the {} byte buffer!",
+            len,
+            offset,
+            self.size
+        );
+        unsafe {
+            let mapped = self.logical_device.map_memory(
+                self.memory,
+                offset,
+                len,
+                MemoryMapFlags::empty(),
+            )?;
+            // copied as raw memory, so any padding between the values is fine
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr().cast::<u8>(),
+                mapped.cast::<u8>(),
+                len as usize,
+            );
+            self.logical_device.unmap_memory(self.memory);
+        }
+        Ok(())
+    }
+
+    /// Copies `data.len()` bytes out of the buffer, starting `offset` bytes in. The
+    /// buffer's memory must be host visible and coherent, and the GPU must be done
+    /// writing the range.
+    pub fn read(&self, offset: DeviceSize, data: &mut [u8]) -> Result<()> {
+        let len = data.len() as DeviceSize;
+        ensure!(
+            offset + len <= self.size,
+            "Reading {} bytes at offset {} overflows the {} byte buffer!",
+            len,
+            offset,
+            self.size
+        );
+        unsafe {
+            let mapped = self.logical_device.map_memory(
+                self.memory,
+                offset,
+                len,
+                MemoryMapFlags::empty(),
+            )?;
+            std::ptr::copy_nonoverlapping(mapped.cast::<u8>(), data.as_mut_ptr(), data.len());
+            self.logical_device.unmap_memory(self.memory);
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the buffer in bytes
+    pub fn get_size(&self) -> DeviceSize {
+        self.size
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device.destroy_buffer(self.buffer, None);
+            self.logical_device.free_memory(self.memory, None);
+        }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = vk::Buffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+/// Allocates memory with the properties for the buffer, and binds it
+fn allocate_memory(
+    logical_device: &LogicalDevice,
+    buffer: vk::Buffer,
+    properties: MemoryPropertyFlags,
+) -> Result<DeviceMemory> {
+    let memory_requirements = unsafe { logical_device.get_buffer_memory_requirements(buffer) };
+    let memory_type_index =
+        logical_device.find_memory_type(memory_requirements.memory_type_bits, properties)?;
+    let memory_allocate_info = MemoryAllocateInfo::default()
+        .allocation_size(memory_requirements.size)
+        .memory_type_index(memory_type_index);
+    let memory = unsafe { logical_device.allocate_memory(&memory_allocate_info, None)? };
+    if let Err(err) = unsafe { logical_device.bind_buffer_memory(buffer, memory, 0) } {
+        unsafe { logical_device.free_memory(memory, None) };
+        return Err(err.into());
+    }
+    Ok(memory)
+}
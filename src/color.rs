@@ -0,0 +1,74 @@
+/// Converts a linear color channel in [0, 1] to its sRGB encoded value, e.g. to pick the
+/// value to write to a `*_UNORM` target so it displays like the same value written to an
+/// `*_SRGB` one
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts an sRGB encoded color channel in [0, 1], like those from a color picker, to
+/// linear space, which is what shaders and clear values for `*_SRGB` targets expect
+pub fn srgb_to_linear(srgb: f32) -> f32 {
+    if srgb <= 0.040_45 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Returns the fully saturated, full brightness color of the hue, given in turns (0.0 is
+/// red, 1/3 green, 2/3 blue), as linear RGB
+pub(crate) fn hue_to_linear_rgb(hue: f32) -> [f32; 3] {
+    let hue = hue.rem_euclid(1.0) * 6.0;
+    // distance from the hue to each channel's peak, on the color wheel split in six
+    let channel = |offset: f32| {
+        let k = (offset + hue) % 6.0;
+        let srgb = 1.0 - k.min(4.0 - k).clamp(0.0, 1.0);
+        srgb_to_linear(srgb)
+    };
+    [channel(5.0), channel(3.0), channel(1.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{actual} isn't close to {expected}"
+        );
+    }
+
+    #[test]
+    fn endpoints_are_unchanged() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert_close(linear_to_srgb(1.0), 1.0);
+        assert_close(srgb_to_linear(1.0), 1.0);
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        for step in 0..=20 {
+            let value = step as f32 / 20.0;
+            assert_close(srgb_to_linear(linear_to_srgb(value)), value);
+            assert_close(linear_to_srgb(srgb_to_linear(value)), value);
+        }
+    }
+
+    #[test]
+    fn mid_gray_is_darker_in_linear_space() {
+        assert_close(srgb_to_linear(0.5), 0.214_041);
+    }
+
+    #[test]
+    fn hues_wrap_around() {
+        assert_eq!(hue_to_linear_rgb(0.0), [1.0, 0.0, 0.0]);
+        assert_eq!(hue_to_linear_rgb(1.0), hue_to_linear_rgb(0.0));
+        assert_eq!(hue_to_linear_rgb(1.0 / 3.0), [0.0, 1.0, 0.0]);
+    }
+}
@@ -1,25 +1,43 @@
-use std::rc::Rc;
-
-use crate::{frame::Frame, GraphicsPipeline, LogicalDevice};
+use crate::{
+    error::VkResultExt,
+    frame::{Frame, DEFAULT_FRAME_TIMEOUT},
+    transition_image_layout, ComputePipeline, DrawCommand, Fence, GraphicsPipeline, IndirectDraw,
+    LogicalDevice, Queue, StaticCommands, Submission, SubmitBatch, Swapchain, TimelineSemaphore,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, ensure, Result};
 use ash::vk::{
-    self, CommandBufferAllocateInfo, CommandBufferLevel, CommandPoolCreateFlags,
-    CommandPoolCreateInfo,
+    self, AccessFlags, Buffer, BufferCopy, BufferImageCopy, BufferMemoryBarrier, BufferUsageFlags,
+    CommandBuffer, CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferLevel,
+    CommandBufferUsageFlags, CommandPoolCreateFlags, CommandPoolCreateInfo, CommandPoolResetFlags,
+    CommandPoolTrimFlags, DependencyFlags, DescriptorSet, DeviceSize, Extent3D, Format, Image,
+    ImageAspectFlags, ImageLayout, ImageSubresourceLayers, ImageSubresourceRange,
+    MemoryPropertyFlags, Offset3D, PipelineBindPoint, PipelineStageFlags, QueueFlags, WHOLE_SIZE,
 };
 
+use crate::Shared;
+
 pub struct CommandPool {
     frame_idx: usize,
     frames: Vec<Frame>,
+    graphics_pipeline: Shared<GraphicsPipeline>,
     command_pool: vk::CommandPool,
-    logical_device: Rc<LogicalDevice>,
+    /// Allocated via [`CommandPool::allocate`] on top of the frames' own, and not freed
+    /// yet
+    allocated_command_buffers: Vec<CommandBuffer>,
+    /// For the short lived command buffers of [`CommandPool::run_one_time_commands`]
+    transient_command_pool: vk::CommandPool,
+    /// For uploads on the dedicated transfer queue, if the device has one
+    transfer_command_pool: Option<vk::CommandPool>,
+    logical_device: Shared<LogicalDevice>,
 }
 
-const FRAMES_IN_FLIGHT: u32 = 2;
+/// How many frames can be recorded and in flight on the GPU at once
+pub const FRAMES_IN_FLIGHT: u32 = 2;
 
 impl CommandPool {
     pub fn new(
-        logical_device: &Rc<LogicalDevice>,
+        logical_device: &Shared<LogicalDevice>,
         graphics_pipeline: GraphicsPipeline,
     ) -> Result<Self> {
         let queue_family_indicies = logical_device.get_queue_family_indicies();
@@ -29,6 +47,15 @@ impl CommandPool {
             .queue_family_index(queue_family_indicies.graphics_family.unwrap() as u32);
         let command_pool =
             unsafe { logical_device.create_command_pool(&create_command_pool, None)? };
+        logical_device.set_object_name(command_pool, "command pool")?;
+        // hints to the driver that its command buffers are freed soon after recording, so
+        // it can allocate them differently than the frames' reused ones
+        let create_transient_command_pool = CommandPoolCreateInfo::default()
+            .flags(CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family_indicies.graphics_family.unwrap() as u32);
+        let transient_command_pool =
+            unsafe { logical_device.create_command_pool(&create_transient_command_pool, None)? };
+        logical_device.set_object_name(transient_command_pool, "transient command pool")?;
 
         let allocate_info = CommandBufferAllocateInfo::default()
             .command_pool(command_pool)
@@ -36,19 +63,121 @@ impl CommandPool {
             .command_buffer_count(FRAMES_IN_FLIGHT);
 
         let command_buffers = unsafe { logical_device.allocate_command_buffers(&allocate_info)? };
-        let graphics_pipeline = Rc::new(graphics_pipeline);
+        let graphics_pipeline = Shared::new(graphics_pipeline);
+
+        // prefer a timeline semaphore for synchronizing the frames in flight, falling
+        // back to a fence per frame if the device doesn't support them
+        let timeline_semaphore = if logical_device.is_timeline_semaphore_enabled() {
+            let timeline_semaphore = TimelineSemaphore::new(logical_device)?;
+            logical_device.set_object_name(*timeline_semaphore, "frame timeline semaphore")?;
+            Some(Shared::new(timeline_semaphore))
+        } else {
+            None
+        };
 
         let frames = command_buffers
             .into_iter()
-            .map(|command_buffer| Frame::new(logical_device, command_buffer, &graphics_pipeline))
+            .enumerate()
+            .map(|(idx, command_buffer)| {
+                Frame::new(
+                    logical_device,
+                    idx,
+                    command_buffer,
+                    &graphics_pipeline,
+                    timeline_semaphore.as_ref(),
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
+        for frame in &frames {
+            frame.set_object_names()?;
+        }
 
-        Ok(Self {
+        let mut this = Self {
             frame_idx: 0,
             frames,
+            graphics_pipeline,
             command_pool,
-            logical_device: Rc::clone(logical_device),
-        })
+            allocated_command_buffers: vec![],
+            transient_command_pool,
+            transfer_command_pool: None,
+            logical_device: Shared::clone(logical_device),
+        };
+        if let Some(transfer_queue) = logical_device.transfer_queue() {
+            let create_transfer_command_pool = CommandPoolCreateInfo::default()
+                .flags(CommandPoolCreateFlags::TRANSIENT)
+                .queue_family_index(transfer_queue.get_family_index());
+            let transfer_command_pool =
+                unsafe { logical_device.create_command_pool(&create_transfer_command_pool, None)? };
+            this.transfer_command_pool = Some(transfer_command_pool);
+            logical_device.set_object_name(transfer_command_pool, "transfer command pool")?;
+        }
+        Ok(this)
+    }
+
+    /// Allocates `count` more command buffers of the given level from the pool, on top of
+    /// the one per frame in flight, for instance secondary buffers to record parts of a
+    /// frame into, or primary ones for uploads. They're freed along with the pool, or
+    /// earlier with [`CommandPool::free`]. They can be reset individually, and are
+    /// submittable to the graphics queue family.
+    pub fn allocate(
+        &mut self,
+        count: u32,
+        level: CommandBufferLevel,
+    ) -> Result<Vec<CommandBuffer>> {
+        ensure!(count > 0, "Can't allocate zero command buffers!");
+        let allocate_info = CommandBufferAllocateInfo::default()
+            .command_pool(self.command_pool)
+            .level(level)
+            .command_buffer_count(count);
+        let command_buffers = unsafe {
+            self.logical_device
+                .allocate_command_buffers(&allocate_info)?
+        };
+        self.allocated_command_buffers
+            .extend_from_slice(&command_buffers);
+        Ok(command_buffers)
+    }
+
+    /// Frees command buffers allocated with [`CommandPool::allocate`], which must not be
+    /// pending execution. Errors, without freeing any, if one wasn't allocated that way.
+    pub fn free(&mut self, command_buffers: &[CommandBuffer]) -> Result<()> {
+        ensure!(
+            command_buffers
+                .iter()
+                .all(|command_buffer| self.allocated_command_buffers.contains(command_buffer)),
+            "Can only free command buffers allocated with CommandPool::allocate!"
+        );
+        self.allocated_command_buffers
+            .retain(|command_buffer| !command_buffers.contains(command_buffer));
+        unsafe {
+            self.logical_device
+                .free_command_buffers(self.command_pool, command_buffers)
+        };
+        Ok(())
+    }
+
+    /// Records `record`'s draws once per swapchain image, inside the main render pass
+    /// with the pipeline bound, into command buffers that can then be submitted every
+    /// frame with [`Frame::render_static`] instead of re-recording the frame. The clear
+    /// color is baked in as it is now. They stay valid as long as this pool's pipeline
+    /// does, so have to be recorded again after the swapchain is recreated.
+    pub fn record_static<F>(&self, swapchain: &Swapchain, mut record: F) -> Result<StaticCommands>
+    where
+        F: FnMut(CommandBuffer) -> Result<()>,
+    {
+        let static_commands = StaticCommands::new(
+            &self.logical_device,
+            &self.graphics_pipeline,
+            swapchain.get_images().len() as u32,
+        )?;
+        // every frame records the main pass the same way
+        let frame = &self.frames[0];
+        for (image_index, command_buffer) in
+            static_commands.get_command_buffers().iter().enumerate()
+        {
+            frame.record_static_command_buffer(*command_buffer, image_index, &mut record)?;
+        }
+        Ok(static_commands)
     }
 
     pub fn get_next_frame(&mut self) -> &Frame {
@@ -56,13 +185,405 @@ impl CommandPool {
         self.frame_idx = (self.frame_idx + 1) % self.frames.len();
         frame
     }
+
+    /// Returns the pipeline every frame draws with
+    pub fn get_graphics_pipeline(&self) -> &GraphicsPipeline {
+        &self.graphics_pipeline
+    }
+
+    /// Returns what each frame draws
+    pub fn get_draw_command(&self) -> DrawCommand {
+        self.frames[0].get_draw_command()
+    }
+
+    /// Sets what each frame draws, starting with the next frame rendered
+    pub fn set_draw_command(&self, draw_command: DrawCommand) {
+        for frame in &self.frames {
+            frame.set_draw_command(draw_command);
+        }
+    }
+
+    /// Returns the indirect draw each frame records, if any
+    pub fn get_indirect_draw(&self) -> Option<IndirectDraw> {
+        self.frames[0].get_indirect_draw()
+    }
+
+    /// Sets an indirect draw for each frame to record in place of the draw command,
+    /// starting with the next frame rendered
+    pub fn set_indirect_draw(&self, indirect_draw: Option<IndirectDraw>) {
+        for frame in &self.frames {
+            frame.set_indirect_draw(indirect_draw);
+        }
+    }
+
+    /// Returns the color each frame is cleared to
+    pub fn get_clear_color(&self) -> [f32; 4] {
+        self.frames[0].get_clear_color()
+    }
+
+    /// Sets the color each frame is cleared to. The color is in linear space, and encoded
+    /// by the hardware for `*_SRGB` swapchains.
+    pub fn set_clear_color(&self, clear_color: [f32; 4]) {
+        for frame in &self.frames {
+            frame.set_clear_color(clear_color);
+        }
+    }
+
+    /// Resets the pool, returning the memory of all its command buffers to the system.
+    /// Useful after a scene that recorded far more commands than usual, since command
+    /// buffers otherwise hold on to the memory they've grown to. Waits for every frame's
+    /// draw to complete first, since command buffers can't be reset while in flight.
+    pub fn reset(&self) -> Result<()> {
+        for frame in &self.frames {
+            frame.wait_until_idle(DEFAULT_FRAME_TIMEOUT)?;
+        }
+        unsafe {
+            self.logical_device
+                .reset_command_pool(self.command_pool, CommandPoolResetFlags::RELEASE_RESOURCES)
+                .map_device_lost()?
+        };
+        Ok(())
+    }
+
+    /// Returns memory the pool holds on to but isn't using, such as from freed command
+    /// buffers, to the system. Unlike [`CommandPool::reset`] this leaves the command
+    /// buffers alone, so it's cheap and safe to call while frames are in flight, for
+    /// instance when switching scenes.
+    pub fn trim(&self) {
+        unsafe {
+            self.logical_device
+                .trim_command_pool(self.command_pool, CommandPoolTrimFlags::empty());
+            self.logical_device
+                .trim_command_pool(self.transient_command_pool, CommandPoolTrimFlags::empty())
+        }
+    }
+
+    /// Records and submits a dispatch of the compute pipeline, blocking until the GPU
+    /// has finished running it. Submits on the graphics queue, since queue families
+    /// supporting graphics are required to support compute as well.
+    pub fn dispatch_compute(
+        &self,
+        compute_pipeline: &ComputePipeline,
+        descriptor_sets: &[DescriptorSet],
+        group_count_x: u32,
+        group_count_y: u32,
+        group_count_z: u32,
+    ) -> Result<()> {
+        let graphics_queue = self.logical_device.graphics_queue();
+        graphics_queue.ensure_supports(QueueFlags::COMPUTE)?;
+        self.run_one_time_commands(graphics_queue, |command_buffer| unsafe {
+            self.logical_device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::COMPUTE,
+                **compute_pipeline,
+            );
+            if !descriptor_sets.is_empty() {
+                self.logical_device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    PipelineBindPoint::COMPUTE,
+                    **compute_pipeline.get_pipeline_layout(),
+                    0,
+                    descriptor_sets,
+                    &[],
+                );
+            }
+            self.logical_device.cmd_dispatch(
+                command_buffer,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            );
+            Ok(())
+        })
+    }
+
+    /// Copies `size` bytes from the start of one buffer to the start of another, for
+    /// instance from a staging buffer into device local memory. Blocks until the copy
+    /// has finished.
+    ///
+    /// If the device has a dedicated transfer queue the copy runs on it, leaving the
+    /// graphics queue free, after which ownership of `dst_buffer` is transferred over to
+    /// the graphics family, as exclusively shared buffers require.
+    pub fn copy_buffer(
+        &self,
+        src_buffer: Buffer,
+        dst_buffer: Buffer,
+        size: DeviceSize,
+    ) -> Result<()> {
+        let record_copy = |command_buffer| {
+            let regions = [BufferCopy::default().size(size)];
+            unsafe {
+                self.logical_device.cmd_copy_buffer(
+                    command_buffer,
+                    src_buffer,
+                    dst_buffer,
+                    &regions,
+                )
+            };
+        };
+
+        let graphics_queue = self.logical_device.graphics_queue();
+        let (Some(transfer_command_pool), Some(transfer_queue)) = (
+            self.transfer_command_pool,
+            self.logical_device.transfer_queue(),
+        ) else {
+            return self.run_one_time_commands(graphics_queue, |command_buffer| {
+                record_copy(command_buffer);
+                Ok(())
+            });
+        };
+
+        // the transfer family releases the buffer once it's written, then the graphics
+        // family acquires it. both barriers have to name the same families
+        let ownership_barrier = BufferMemoryBarrier::default()
+            .src_queue_family_index(transfer_queue.get_family_index())
+            .dst_queue_family_index(graphics_queue.get_family_index())
+            .buffer(dst_buffer)
+            .size(WHOLE_SIZE);
+        self.run_in_pool(transfer_command_pool, transfer_queue, |command_buffer| {
+            record_copy(command_buffer);
+            let release_barrier = ownership_barrier.src_access_mask(AccessFlags::TRANSFER_WRITE);
+            unsafe {
+                self.logical_device.cmd_pipeline_barrier(
+                    command_buffer,
+                    PipelineStageFlags::TRANSFER,
+                    PipelineStageFlags::BOTTOM_OF_PIPE,
+                    DependencyFlags::empty(),
+                    &[],
+                    &[release_barrier],
+                    &[],
+                )
+            };
+            Ok(())
+        })?;
+        // the upload has finished by now, so the acquire only has to make the writes
+        // visible to whatever reads the buffer
+        self.run_one_time_commands(graphics_queue, |command_buffer| {
+            let acquire_barrier = ownership_barrier.dst_access_mask(AccessFlags::MEMORY_READ);
+            unsafe {
+                self.logical_device.cmd_pipeline_barrier(
+                    command_buffer,
+                    PipelineStageFlags::TOP_OF_PIPE,
+                    PipelineStageFlags::ALL_COMMANDS,
+                    DependencyFlags::empty(),
+                    &[],
+                    &[acquire_barrier],
+                    &[],
+                )
+            };
+            Ok(())
+        })
+    }
+
+    /// Transitions all `mip_levels` of the first `layer_count` layers of the image to a
+    /// new layout, blocking until it's done. See [`transition_image_layout`] for the
+    /// supported transitions.
+    pub fn transition_image_layout(
+        &self,
+        image: Image,
+        old_layout: ImageLayout,
+        new_layout: ImageLayout,
+        aspect_mask: ImageAspectFlags,
+        mip_levels: u32,
+        layer_count: u32,
+    ) -> Result<()> {
+        let graphics_queue = self.logical_device.graphics_queue();
+        self.run_one_time_commands(graphics_queue, |command_buffer| {
+            transition_image_layout(
+                &self.logical_device,
+                command_buffer,
+                image,
+                old_layout,
+                new_layout,
+                ImageSubresourceRange::default()
+                    .aspect_mask(aspect_mask)
+                    .level_count(mip_levels)
+                    .layer_count(layer_count),
+            )
+        })
+    }
+
+    /// Reads the depth at pixel (`x`, `y`) of the pipeline's depth buffer, from 0.0 at the
+    /// near plane to 1.0 at the far plane (the other way around with
+    /// [`crate::DepthConfig::reverse_z`]), for picking what's under the mouse. Only
+    /// available for pipelines with a depth buffer, after at least one frame has been
+    /// rendered.
+    ///
+    /// The value comes from the last frame rendered, so lags a frame behind whatever is
+    /// being prepared next. Reading blocks until every frame in flight has finished, so
+    /// avoid doing it every frame.
+    pub fn read_depth_at(&self, x: u32, y: u32) -> Result<f32> {
+        let depth_image = self
+            .graphics_pipeline
+            .get_depth_stencil_image()
+            .ok_or_else(|| anyhow!("The pipeline has no depth buffer to read!"))?;
+        let extent = depth_image.get_extent();
+        ensure!(
+            x < extent.width && y < extent.height,
+            "Pixel ({x}, {y}) is outside the {}x{} depth buffer!",
+            extent.width,
+            extent.height
+        );
+
+        // the depth buffer is shared between frames, so none may be using it
+        for frame in &self.frames {
+            frame.wait_until_idle(DEFAULT_FRAME_TIMEOUT)?;
+        }
+
+        // every depth format this crate picks copies out as 4 bytes per pixel
+        let readback_buffer = crate::Buffer::new(
+            &self.logical_device,
+            4,
+            BufferUsageFlags::TRANSFER_DST,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        let depth_stencil_range = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL)
+            .level_count(1)
+            .layer_count(1);
+        let graphics_queue = self.logical_device.graphics_queue();
+        self.run_one_time_commands(graphics_queue, |command_buffer| {
+            transition_image_layout(
+                &self.logical_device,
+                command_buffer,
+                **depth_image,
+                ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                depth_stencil_range,
+            )?;
+            let regions = [BufferImageCopy::default()
+                .image_subresource(
+                    ImageSubresourceLayers::default()
+                        .aspect_mask(ImageAspectFlags::DEPTH)
+                        .layer_count(1),
+                )
+                .image_offset(Offset3D {
+                    x: x as i32,
+                    y: y as i32,
+                    z: 0,
+                })
+                .image_extent(Extent3D {
+                    width: 1,
+                    height: 1,
+                    depth: 1,
+                })];
+            unsafe {
+                self.logical_device.cmd_copy_image_to_buffer(
+                    command_buffer,
+                    **depth_image,
+                    ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    *readback_buffer,
+                    &regions,
+                )
+            };
+            transition_image_layout(
+                &self.logical_device,
+                command_buffer,
+                **depth_image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                depth_stencil_range,
+            )
+        })?;
+
+        let mut bytes = [0; 4];
+        readback_buffer.read(0, &mut bytes)?;
+        let depth = match depth_image.get_format() {
+            Format::D32_SFLOAT | Format::D32_SFLOAT_S8_UINT => f32::from_ne_bytes(bytes),
+            // the depth is in the low 24 bits, the rest is undefined
+            Format::D24_UNORM_S8_UINT | Format::X8_D24_UNORM_PACK32 => {
+                (u32::from_ne_bytes(bytes) & 0x00ff_ffff) as f32 / 0x00ff_ffff as f32
+            }
+            format => bail!("Can't read back depth from a {format:?} depth buffer!"),
+        };
+        Ok(depth)
+    }
+
+    /// Allocates a command buffer from a transient pool, records it with `record`, and
+    /// submits it to the queue, blocking until the GPU has finished running it. The
+    /// command buffer is freed afterwards, even if recording or submitting fails. Errors
+    /// if the queue doesn't belong to the graphics family, which the pool allocates from.
+    pub fn run_one_time_commands<F>(&self, queue: &Queue, record: F) -> Result<()>
+    where
+        F: FnOnce(CommandBuffer) -> Result<()>,
+    {
+        let graphics_family = self.logical_device.graphics_queue().get_family_index();
+        ensure!(
+            queue.get_family_index() == graphics_family,
+            "Command buffers from the pool can only be submitted to queue family {}, not {}!",
+            graphics_family,
+            queue.get_family_index()
+        );
+        self.run_in_pool(self.transient_command_pool, queue, record)
+    }
+
+    /// Like [`CommandPool::run_one_time_commands`], but allocates the command buffer from
+    /// `command_pool`, which must belong to the queue's family
+    fn run_in_pool<F>(&self, command_pool: vk::CommandPool, queue: &Queue, record: F) -> Result<()>
+    where
+        F: FnOnce(CommandBuffer) -> Result<()>,
+    {
+        let allocate_info = CommandBufferAllocateInfo::default()
+            .command_pool(command_pool)
+            .level(CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let command_buffers = unsafe {
+            self.logical_device
+                .allocate_command_buffers(&allocate_info)?
+        };
+
+        let result = self.record_and_submit(queue, command_buffers[0], record);
+
+        // clean up regardless of whether recording or the submit succeeded
+        unsafe {
+            self.logical_device
+                .free_command_buffers(command_pool, &command_buffers)
+        };
+        result
+    }
+
+    fn record_and_submit<F>(
+        &self,
+        queue: &Queue,
+        command_buffer: CommandBuffer,
+        record: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(CommandBuffer) -> Result<()>,
+    {
+        let command_buffer_begin_info =
+            CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)?
+        };
+        record(command_buffer)?;
+        unsafe { self.logical_device.end_command_buffer(command_buffer)? };
+
+        let fence = Fence::new(&self.logical_device, false)?;
+        SubmitBatch::new()
+            .add_submission(Submission::new().command_buffer(command_buffer))
+            .submit(&self.logical_device, queue, *fence)?;
+        fence.wait_forever()
+    }
 }
 
 impl Drop for CommandPool {
     fn drop(&mut self) {
         unsafe {
+            // the frames' command buffers are freed with the pool
+            if !self.allocated_command_buffers.is_empty() {
+                self.logical_device
+                    .free_command_buffers(self.command_pool, &self.allocated_command_buffers);
+            }
+            self.logical_device
+                .destroy_command_pool(self.command_pool, None);
             self.logical_device
-                .destroy_command_pool(self.command_pool, None)
+                .destroy_command_pool(self.transient_command_pool, None);
+            if let Some(transfer_command_pool) = self.transfer_command_pool {
+                self.logical_device
+                    .destroy_command_pool(transfer_command_pool, None)
+            }
         }
     }
 }
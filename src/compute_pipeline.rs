@@ -0,0 +1,68 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use ash::vk::{
+    ComputePipelineCreateInfo, Pipeline, PipelineCache, PipelineShaderStageCreateInfo,
+    ShaderModule, ShaderStageFlags,
+};
+
+use crate::{LogicalDevice, PipelineLayout, Shared};
+
+pub struct ComputePipeline {
+    logical_device: Shared<LogicalDevice>,
+    pipeline: Pipeline,
+    pipeline_layout: PipelineLayout,
+}
+
+impl ComputePipeline {
+    /// Creates a compute pipeline running the provided compute shader. The shader module
+    /// is only needed during creation, so it's up to the caller to destroy it afterwards.
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        shader_module: ShaderModule,
+        pipeline_layout: PipelineLayout,
+    ) -> Result<Self> {
+        let shader_stage_create_info = PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::COMPUTE)
+            .module(shader_module)
+            .name(c"main");
+
+        let compute_pipeline_create_info = [ComputePipelineCreateInfo::default()
+            .stage(shader_stage_create_info)
+            .layout(*pipeline_layout)];
+
+        let compute_pipeline = unsafe {
+            logical_device.create_compute_pipelines(
+                PipelineCache::null(),
+                &compute_pipeline_create_info,
+                None,
+            )
+        }
+        .map_err(|(_, r)| r)?;
+        logical_device.set_object_name(compute_pipeline[0], "compute pipeline")?;
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            pipeline: compute_pipeline[0],
+            pipeline_layout,
+        })
+    }
+
+    pub fn get_pipeline_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe { self.logical_device.destroy_pipeline(self.pipeline, None) }
+    }
+}
+
+impl Deref for ComputePipeline {
+    type Target = Pipeline;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pipeline
+    }
+}
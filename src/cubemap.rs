@@ -0,0 +1,160 @@
+use anyhow::{ensure, Result};
+use ash::vk::{
+    BufferImageCopy, BufferUsageFlags, Extent2D, Extent3D, Format, ImageAspectFlags,
+    ImageCreateFlags, ImageLayout, ImageSubresourceLayers, ImageSubresourceRange, ImageUsageFlags,
+    MemoryPropertyFlags, SamplerAddressMode,
+};
+
+use crate::{
+    transition_image_layout, Buffer, CommandPool, Image, ImageDescriptor, LogicalDevice, Sampler,
+    Shared,
+};
+
+/// The pixels of one face of a [`Cubemap`], tightly packed rows in `format`
+#[derive(Clone, Copy, Debug)]
+pub struct CubemapFace<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: Format,
+    pub pixels: &'a [u8],
+}
+
+/// A six layer image viewed as a cube, with a sampler to read it with, e.g. for skyboxes
+/// and environment maps
+pub struct Cubemap {
+    image: Image,
+    sampler: Sampler,
+}
+
+impl Cubemap {
+    /// Creates the cubemap, uploading the faces in the order +x, -x, +y, -y, +z, -z, and
+    /// blocks until they're ready to be sampled. Errors unless every face is square and
+    /// has the same size and format.
+    pub fn from_faces(
+        logical_device: &Shared<LogicalDevice>,
+        command_pool: &CommandPool,
+        faces: &[CubemapFace; 6],
+    ) -> Result<Self> {
+        let first_face = &faces[0];
+        ensure!(
+            first_face.width == first_face.height,
+            "Cubemap faces must be square, not {}x{}!",
+            first_face.width,
+            first_face.height
+        );
+        ensure!(
+            !first_face.pixels.is_empty(),
+            "Cubemap faces must have pixels!"
+        );
+        for (idx, face) in faces.iter().enumerate() {
+            ensure!(
+                face.width == first_face.width
+                    && face.height == first_face.height
+                    && face.format == first_face.format
+                    && face.pixels.len() == first_face.pixels.len(),
+                "Cubemap face {} is {}x{} {:?}, but face 0 is {}x{} {:?}!",
+                idx,
+                face.width,
+                face.height,
+                face.format,
+                first_face.width,
+                first_face.height,
+                first_face.format
+            );
+        }
+
+        // stage all the faces one after the other, to copy each into its layer
+        let face_size = first_face.pixels.len() as u64;
+        let staging_buffer = Buffer::new(
+            logical_device,
+            face_size * 6,
+            BufferUsageFlags::TRANSFER_SRC,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        for (idx, face) in faces.iter().enumerate() {
+            staging_buffer.write(face_size * idx as u64, face.pixels)?;
+        }
+
+        let image = Image::new(
+            logical_device,
+            &ImageDescriptor {
+                format: first_face.format,
+                extent: Extent2D {
+                    width: first_face.width,
+                    height: first_face.height,
+                },
+                usage: ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED,
+                array_layers: 6,
+                flags: ImageCreateFlags::CUBE_COMPATIBLE,
+                ..Default::default()
+            },
+        )?;
+
+        let regions = (0..6)
+            .map(|layer| {
+                BufferImageCopy::default()
+                    .buffer_offset(face_size * layer as u64)
+                    // tightly packed
+                    .buffer_row_length(0)
+                    .buffer_image_height(0)
+                    .image_subresource(
+                        ImageSubresourceLayers::default()
+                            .aspect_mask(ImageAspectFlags::COLOR)
+                            .mip_level(0)
+                            .base_array_layer(layer)
+                            .layer_count(1),
+                    )
+                    .image_extent(Extent3D {
+                        width: first_face.width,
+                        height: first_face.height,
+                        depth: 1,
+                    })
+            })
+            .collect::<Vec<_>>();
+        let all_faces = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(6);
+        command_pool.run_one_time_commands(logical_device.graphics_queue(), |command_buffer| {
+            transition_image_layout(
+                logical_device,
+                command_buffer,
+                *image,
+                ImageLayout::UNDEFINED,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                all_faces,
+            )?;
+            unsafe {
+                logical_device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    *staging_buffer,
+                    *image,
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &regions,
+                )
+            };
+            transition_image_layout(
+                logical_device,
+                command_buffer,
+                *image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                all_faces,
+            )
+        })?;
+
+        // clamp so the edges between faces don't bleed
+        let sampler = Sampler::new(logical_device, SamplerAddressMode::CLAMP_TO_EDGE)?;
+
+        Ok(Self { image, sampler })
+    }
+
+    /// Returns the cube image, whose view is a `CUBE` view of all six faces
+    pub fn get_image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn get_sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use anyhow::Result;
+use ash::{
+    ext::debug_utils,
+    vk::{DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessengerEXT},
+};
+
+use crate::{get_debug_messenger_create_info, Instance, Shared};
+
+/// Data handed to the debug utils callback through its user data pointer.
+/// Counts the messages received by severity so the application can react
+/// to validation errors (for instance failing a test if any fired).
+///
+/// The validation layers may invoke the callback from any thread, so the
+/// counters are atomics.
+#[derive(Default)]
+pub struct DebugMessengerData {
+    verbose_count: AtomicU32,
+    info_count: AtomicU32,
+    warning_count: AtomicU32,
+    error_count: AtomicU32,
+}
+
+impl DebugMessengerData {
+    /// Records that a message of the given severity was received
+    pub(crate) fn record(&self, message_severity: DebugUtilsMessageSeverityFlagsEXT) {
+        let counter = match message_severity {
+            DebugUtilsMessageSeverityFlagsEXT::VERBOSE => &self.verbose_count,
+            DebugUtilsMessageSeverityFlagsEXT::INFO => &self.info_count,
+            DebugUtilsMessageSeverityFlagsEXT::WARNING => &self.warning_count,
+            DebugUtilsMessageSeverityFlagsEXT::ERROR => &self.error_count,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_verbose_count(&self) -> u32 {
+        self.verbose_count.load(Ordering::Relaxed)
+    }
+
+    pub fn get_info_count(&self) -> u32 {
+        self.info_count.load(Ordering::Relaxed)
+    }
+
+    pub fn get_warning_count(&self) -> u32 {
+        self.warning_count.load(Ordering::Relaxed)
+    }
+
+    pub fn get_error_count(&self) -> u32 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+}
+
+/// Owns the DebugUtils messenger registered with the instance, along with the
+/// user data whose pointer is handed to the callback. The user data is boxed so
+/// its address stays stable for as long as the messenger is alive.
+pub struct DebugMessenger {
+    debug_utils: debug_utils::Instance,
+    messenger: DebugUtilsMessengerEXT,
+    // the callback holds a raw pointer to this, so it must outlive the
+    // messenger
+    data: Box<DebugMessengerData>,
+    // references to make sure we are dropped before these
+    _instance: Shared<Instance>,
+}
+
+impl DebugMessenger {
+    pub fn new(instance: &Shared<Instance>) -> Result<Self> {
+        let data = Box::<DebugMessengerData>::default();
+        let user_data = &*data as *const DebugMessengerData as *mut c_void;
+
+        let debug_utils_messenger_create_info =
+            get_debug_messenger_create_info().user_data(user_data);
+        let debug_utils = debug_utils::Instance::new(instance.get_entry(), instance);
+        let messenger = unsafe {
+            debug_utils.create_debug_utils_messenger(&debug_utils_messenger_create_info, None)?
+        };
+
+        Ok(Self {
+            debug_utils,
+            messenger,
+            data,
+            _instance: Shared::clone(instance),
+        })
+    }
+
+    /// Returns the message counters the callback has been recording into
+    pub fn get_data(&self) -> &DebugMessengerData {
+        &self.data
+    }
+}
+
+impl Drop for DebugMessenger {
+    fn drop(&mut self) {
+        unsafe {
+            self.debug_utils
+                .destroy_debug_utils_messenger(self.messenger, None)
+        }
+    }
+}
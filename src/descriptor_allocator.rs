@@ -0,0 +1,141 @@
+use anyhow::{ensure, Result};
+use ash::vk::{self, DescriptorPoolSize, DescriptorSet, DescriptorType};
+
+use crate::{DescriptorPool, DescriptorSetLayout, LogicalDevice, Shared};
+
+/// The most sets a single pool is grown to hold
+const MAX_SETS_PER_POOL: u32 = 4096;
+
+/// Allocates descriptor sets from a list of pools, creating a new pool with double the
+/// capacity whenever the current one runs out
+pub struct DescriptorAllocator {
+    logical_device: Shared<LogicalDevice>,
+    /// How many descriptors of each type a set needs, scaled by the sets in a pool
+    descriptors_per_set: Vec<(DescriptorType, u32)>,
+    /// How many sets the next pool is created with
+    next_pool_sets: u32,
+    /// Every pool created so far, the last one being allocated from
+    pools: Vec<DescriptorPool>,
+}
+
+impl DescriptorAllocator {
+    /// Creates an allocator whose first pool holds `initial_sets` sets, each with up to
+    /// the given number of descriptors of each type
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        initial_sets: u32,
+        descriptors_per_set: &[(DescriptorType, u32)],
+    ) -> Result<Self> {
+        ensure!(
+            initial_sets > 0,
+            "Descriptor pools must hold at least one set!"
+        );
+        let mut allocator = Self {
+            logical_device: Shared::clone(logical_device),
+            descriptors_per_set: descriptors_per_set.to_vec(),
+            next_pool_sets: initial_sets.min(MAX_SETS_PER_POOL),
+            pools: vec![],
+        };
+        allocator.grow()?;
+        Ok(allocator)
+    }
+
+    /// Allocates a set with the layout from whichever pool has room, creating a new
+    /// pool if none does
+    pub fn allocate(&mut self, layout: &DescriptorSetLayout) -> Result<DescriptorSet> {
+        let layouts = [**layout];
+        let current_pool = self.pools.last().unwrap();
+        match current_pool.allocate(&layouts) {
+            Ok(descriptor_sets) => return Ok(descriptor_sets[0]),
+            Err(error) if is_pool_exhausted(&error) => {}
+            Err(error) => return Err(error),
+        }
+
+        // full pools keep their sets alive, new sets just come from a bigger pool
+        self.grow()?;
+        let descriptor_sets = self.pools.last().unwrap().allocate(&layouts)?;
+        Ok(descriptor_sets[0])
+    }
+
+    /// Returns how many pools have been created so far
+    pub fn get_pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Creates the next pool to allocate from, and doubles the capacity of the one after it
+    fn grow(&mut self) -> Result<()> {
+        let max_sets = self.next_pool_sets;
+        let pool_sizes = get_pool_sizes(&self.descriptors_per_set, max_sets);
+        let pool = DescriptorPool::new(&self.logical_device, max_sets, &pool_sizes)?;
+        self.logical_device.set_object_name(
+            *pool,
+            &format!("descriptor pool {} ({max_sets} sets)", self.pools.len()),
+        )?;
+        self.pools.push(pool);
+        self.next_pool_sets = get_next_pool_sets(max_sets);
+        Ok(())
+    }
+}
+
+/// Returns how many descriptors of each type a pool of `max_sets` sets needs
+fn get_pool_sizes(
+    descriptors_per_set: &[(DescriptorType, u32)],
+    max_sets: u32,
+) -> Vec<DescriptorPoolSize> {
+    descriptors_per_set
+        .iter()
+        .map(|(descriptor_type, count)| {
+            DescriptorPoolSize::default()
+                .ty(*descriptor_type)
+                .descriptor_count(count * max_sets)
+        })
+        .collect()
+}
+
+/// Returns how many sets the pool after one of `max_sets` sets holds, double as many up
+/// to [`MAX_SETS_PER_POOL`]
+fn get_next_pool_sets(max_sets: u32) -> u32 {
+    max_sets.saturating_mul(2).min(MAX_SETS_PER_POOL)
+}
+
+/// True if the allocation failed because the pool has no room left
+fn is_pool_exhausted(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<vk::Result>(),
+        Some(&vk::Result::ERROR_OUT_OF_POOL_MEMORY) | Some(&vk::Result::ERROR_FRAGMENTED_POOL)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_sizes_scale_with_the_sets() {
+        let pool_sizes = get_pool_sizes(
+            &[
+                (DescriptorType::UNIFORM_BUFFER, 1),
+                (DescriptorType::COMBINED_IMAGE_SAMPLER, 4),
+            ],
+            8,
+        );
+        assert_eq!(pool_sizes.len(), 2);
+        assert_eq!(pool_sizes[0].ty, DescriptorType::UNIFORM_BUFFER);
+        assert_eq!(pool_sizes[0].descriptor_count, 8);
+        assert_eq!(pool_sizes[1].ty, DescriptorType::COMBINED_IMAGE_SAMPLER);
+        assert_eq!(pool_sizes[1].descriptor_count, 32);
+    }
+
+    #[test]
+    fn pools_double_in_size() {
+        assert_eq!(get_next_pool_sets(1), 2);
+        assert_eq!(get_next_pool_sets(64), 128);
+    }
+
+    #[test]
+    fn pools_stop_growing_at_the_cap() {
+        assert_eq!(get_next_pool_sets(MAX_SETS_PER_POOL / 2), MAX_SETS_PER_POOL);
+        assert_eq!(get_next_pool_sets(MAX_SETS_PER_POOL), MAX_SETS_PER_POOL);
+        assert_eq!(get_next_pool_sets(3000), MAX_SETS_PER_POOL);
+    }
+}
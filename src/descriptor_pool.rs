@@ -0,0 +1,88 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use ash::vk::{
+    self, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo,
+    DescriptorSetLayout, DescriptorSetVariableDescriptorCountAllocateInfo,
+};
+
+use crate::{LogicalDevice, Shared};
+
+/// A fixed amount of memory to allocate descriptor sets from. The sets are freed along
+/// with the pool.
+pub struct DescriptorPool {
+    logical_device: Shared<LogicalDevice>,
+    descriptor_pool: vk::DescriptorPool,
+}
+
+impl DescriptorPool {
+    /// Creates a pool holding up to `max_sets` sets, with `pool_sizes` limiting how many
+    /// descriptors of each type they can have between them
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        max_sets: u32,
+        pool_sizes: &[DescriptorPoolSize],
+    ) -> Result<Self> {
+        let pool_create_info = DescriptorPoolCreateInfo::default()
+            .max_sets(max_sets)
+            .pool_sizes(pool_sizes);
+        let descriptor_pool =
+            unsafe { logical_device.create_descriptor_pool(&pool_create_info, None)? };
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            descriptor_pool,
+        })
+    }
+
+    /// Allocates a descriptor set for each of the layouts
+    pub fn allocate(&self, layouts: &[DescriptorSetLayout]) -> Result<Vec<DescriptorSet>> {
+        let allocate_info = DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.descriptor_pool)
+            .set_layouts(layouts);
+        let descriptor_sets = unsafe {
+            self.logical_device
+                .allocate_descriptor_sets(&allocate_info)?
+        };
+        Ok(descriptor_sets)
+    }
+
+    /// Allocates a set for a layout whose last binding has a variable descriptor count,
+    /// with `descriptor_count` descriptors in that binding
+    pub fn allocate_variable(
+        &self,
+        layout: DescriptorSetLayout,
+        descriptor_count: u32,
+    ) -> Result<DescriptorSet> {
+        let layouts = [layout];
+        let descriptor_counts = [descriptor_count];
+        let mut variable_count_allocate_info =
+            DescriptorSetVariableDescriptorCountAllocateInfo::default()
+                .descriptor_counts(&descriptor_counts);
+        let allocate_info = DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.descriptor_pool)
+            .set_layouts(&layouts)
+            .push_next(&mut variable_count_allocate_info);
+        let descriptor_sets = unsafe {
+            self.logical_device
+                .allocate_descriptor_sets(&allocate_info)?
+        };
+        Ok(descriptor_sets[0])
+    }
+}
+
+impl Drop for DescriptorPool {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device
+                .destroy_descriptor_pool(self.descriptor_pool, None)
+        }
+    }
+}
+
+impl Deref for DescriptorPool {
+    type Target = vk::DescriptorPool;
+
+    fn deref(&self) -> &Self::Target {
+        &self.descriptor_pool
+    }
+}
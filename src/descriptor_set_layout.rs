@@ -0,0 +1,132 @@
+use std::ops::Deref;
+
+use anyhow::{ensure, Result};
+use ash::vk::{
+    self, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags,
+    DescriptorSetLayoutCreateInfo, DescriptorType, ShaderStageFlags,
+};
+
+use crate::{LogicalDevice, Shared};
+
+/// Describes the bindings of a descriptor set, which pipeline layouts are built from
+pub struct DescriptorSetLayout {
+    logical_device: Shared<LogicalDevice>,
+    layout: vk::DescriptorSetLayout,
+}
+
+impl DescriptorSetLayout {
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        bindings: &[DescriptorSetLayoutBinding],
+    ) -> Result<Self> {
+        Self::with_flags(
+            logical_device,
+            bindings,
+            DescriptorSetLayoutCreateFlags::empty(),
+        )
+    }
+
+    /// A layout for sets written with [`crate::PushDescriptor`] rather than allocated
+    /// from a pool. Requires push descriptors to be enabled on the device.
+    pub fn for_push_descriptors(
+        logical_device: &Shared<LogicalDevice>,
+        bindings: &[DescriptorSetLayoutBinding],
+    ) -> Result<Self> {
+        ensure!(
+            logical_device.is_push_descriptor_enabled(),
+            "VK_KHR_push_descriptor isn't enabled on the device!"
+        );
+        Self::with_flags(
+            logical_device,
+            bindings,
+            DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR,
+        )
+    }
+
+    /// Creates a layout from a full create info, for when extension structs like binding
+    /// flags need chaining
+    pub fn from_create_info(
+        logical_device: &Shared<LogicalDevice>,
+        layout_create_info: &DescriptorSetLayoutCreateInfo,
+    ) -> Result<Self> {
+        let layout =
+            unsafe { logical_device.create_descriptor_set_layout(layout_create_info, None)? };
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            layout,
+        })
+    }
+
+    fn with_flags(
+        logical_device: &Shared<LogicalDevice>,
+        bindings: &[DescriptorSetLayoutBinding],
+        flags: DescriptorSetLayoutCreateFlags,
+    ) -> Result<Self> {
+        let layout_create_info = DescriptorSetLayoutCreateInfo::default()
+            .flags(flags)
+            .bindings(bindings);
+        Self::from_create_info(logical_device, &layout_create_info)
+    }
+
+    /// A layout with `count` input attachments at bindings 0 to `count - 1`, for a
+    /// fragment shader reading what earlier subpasses wrote, see
+    /// [`crate::Subpass::add_input_attachment`]
+    pub fn input_attachments(logical_device: &Shared<LogicalDevice>, count: u32) -> Result<Self> {
+        let bindings = (0..count)
+            .map(|binding| {
+                DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(DescriptorType::INPUT_ATTACHMENT)
+                    .descriptor_count(1)
+                    .stage_flags(ShaderStageFlags::FRAGMENT)
+            })
+            .collect::<Vec<_>>();
+        Self::new(logical_device, &bindings)
+    }
+
+    /// A layout with a single dynamic uniform buffer at `binding`, whose offset is given
+    /// when binding the set, as for a [`crate::UniformRing`]
+    pub fn dynamic_uniform_buffer(
+        logical_device: &Shared<LogicalDevice>,
+        binding: u32,
+        stages: ShaderStageFlags,
+    ) -> Result<Self> {
+        let bindings = [DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_type(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .descriptor_count(1)
+            .stage_flags(stages)];
+        Self::new(logical_device, &bindings)
+    }
+
+    /// A layout with a single uniform buffer at `binding`, read by the given stages
+    pub fn uniform_buffer(
+        logical_device: &Shared<LogicalDevice>,
+        binding: u32,
+        stages: ShaderStageFlags,
+    ) -> Result<Self> {
+        let bindings = [DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_type(DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(stages)];
+        Self::new(logical_device, &bindings)
+    }
+}
+
+impl Drop for DescriptorSetLayout {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device
+                .destroy_descriptor_set_layout(self.layout, None)
+        }
+    }
+}
+
+impl Deref for DescriptorSetLayout {
+    type Target = vk::DescriptorSetLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.layout
+    }
+}
@@ -0,0 +1,30 @@
+/// Parameters for the draw call recorded each frame, see `vkCmdDraw`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawCommand {
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+}
+
+impl DrawCommand {
+    /// Draws a single instance of `vertex_count` vertices
+    pub fn vertices(vertex_count: u32) -> Self {
+        Self {
+            vertex_count,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for DrawCommand {
+    /// A single triangle
+    fn default() -> Self {
+        Self {
+            vertex_count: 3,
+            instance_count: 1,
+            first_vertex: 0,
+            first_instance: 0,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use anyhow::Result;
+use ash::{prelude::VkResult, vk};
+
+/// Errors the renderer reports that callers may want to handle specifically, rather
+/// than just bubbling up. They're returned wrapped in an [`anyhow::Error`], so check
+/// for them with `error.downcast_ref::<RustyGamesError>()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RustyGamesError {
+    /// The surface doesn't report any formats to create a swapchain with
+    NoSurfaceFormats,
+    /// The surface doesn't report any present modes to create a swapchain with
+    NoPresentModes,
+    /// The GPU was reset or the device otherwise stopped responding, for instance after
+    /// a driver timeout. Everything created from the logical device has to be recreated,
+    /// see [`crate::Renderer::recover`].
+    DeviceLost,
+    /// The driver has to be reinitialized, which happens when the system switches to
+    /// another GPU or the driver is updated while running. Everything down to the instance has to
+    /// be recreated, see [`crate::Renderer::reinitialize`].
+    InitializationFailed,
+    /// The swapchain no longer matches the surface, for instance because the window was
+    /// resized or moved to another monitor, so it has to be recreated before the next
+    /// frame, see [`crate::Renderer::resize`]. Reported for `ERROR_OUT_OF_DATE_KHR`, and
+    /// for presents that succeeded but were `SUBOPTIMAL_KHR`.
+    SwapchainOutOfDate,
+    /// The window's surface stopped being usable, for instance because the display it
+    /// was on went away, so it has to be recreated along with everything presenting to
+    /// it, see [`crate::Renderer::recreate_surface`]
+    SurfaceLost,
+    /// None of the device's memory types are suitable for an allocation
+    NoSuitableMemoryType,
+    /// A frame's previous draw didn't complete within the timeout, which usually means
+    /// the GPU has hung
+    FenceTimeout {
+        /// Index of the stalled frame within the frames in flight
+        frame_idx: usize,
+        timeout: Duration,
+    },
+}
+
+impl Display for RustyGamesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RustyGamesError::NoSurfaceFormats => write!(f, "The surface has no available formats!"),
+            RustyGamesError::NoPresentModes => {
+                write!(f, "The surface has no available present modes!")
+            }
+            RustyGamesError::DeviceLost => write!(f, "The logical device was lost!"),
+            RustyGamesError::InitializationFailed => {
+                write!(f, "The driver could not be initialized, it may have changed!")
+            }
+            RustyGamesError::SwapchainOutOfDate => {
+                write!(f, "The swapchain is out of date with the surface!")
+            }
+            RustyGamesError::SurfaceLost => write!(f, "The window's surface was lost!"),
+            RustyGamesError::NoSuitableMemoryType => {
+                write!(f, "Could not find a suitable memory type!")
+            }
+            RustyGamesError::FenceTimeout { frame_idx, timeout } => write!(
+                f,
+                "Frame {frame_idx} didn't finish rendering within {timeout:?}, the GPU may have hung!"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RustyGamesError {}
+
+impl RustyGamesError {
+    /// Returns true if the error is a [`RustyGamesError::DeviceLost`]
+    pub fn is_device_lost(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<RustyGamesError>() == Some(&RustyGamesError::DeviceLost)
+    }
+
+    /// Returns true if the error is a [`RustyGamesError::SwapchainOutOfDate`]
+    pub fn is_swapchain_out_of_date(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<RustyGamesError>() == Some(&RustyGamesError::SwapchainOutOfDate)
+    }
+
+    /// Returns true if the error is a [`RustyGamesError::SurfaceLost`]
+    pub fn is_surface_lost(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<RustyGamesError>() == Some(&RustyGamesError::SurfaceLost)
+    }
+
+    /// Returns true if the error is a [`RustyGamesError::InitializationFailed`]
+    pub fn is_initialization_failed(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<RustyGamesError>() == Some(&RustyGamesError::InitializationFailed)
+    }
+}
+
+pub(crate) trait VkResultExt<T> {
+    /// Converts the result into an [`anyhow::Result`], turning `ERROR_DEVICE_LOST` into
+    /// [`RustyGamesError::DeviceLost`] so callers can recover from it
+    fn map_device_lost(self) -> Result<T>;
+
+    /// Like [`VkResultExt::map_device_lost`], but also turns `ERROR_OUT_OF_DATE_KHR` into
+    /// [`RustyGamesError::SwapchainOutOfDate`], for calls on the swapchain
+    fn map_out_of_date(self) -> Result<T>;
+}
+
+impl<T> VkResultExt<T> for VkResult<T> {
+    fn map_device_lost(self) -> Result<T> {
+        match self {
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(RustyGamesError::DeviceLost.into()),
+            result => Ok(result?),
+        }
+    }
+
+    fn map_out_of_date(self) -> Result<T> {
+        match self {
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                Err(RustyGamesError::SwapchainOutOfDate.into())
+            }
+            result => result.map_device_lost(),
+        }
+    }
+}
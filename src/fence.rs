@@ -0,0 +1,81 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use ash::vk::{self, FenceCreateFlags, FenceCreateInfo};
+
+use crate::{error::VkResultExt, LogicalDevice, Shared};
+
+/// A fence, for the CPU to wait on work submitted to the GPU
+pub struct Fence {
+    logical_device: Shared<LogicalDevice>,
+    fence: vk::Fence,
+}
+
+impl Fence {
+    /// A timeout for [`Fence::wait`] that never elapses
+    pub const NO_TIMEOUT: u64 = u64::MAX;
+
+    /// Creates the fence, already signaled if `signaled` is true so the first
+    /// wait on it returns immediately
+    pub fn new(logical_device: &Shared<LogicalDevice>, signaled: bool) -> Result<Self> {
+        let mut fence_create_info = FenceCreateInfo::default();
+        if signaled {
+            fence_create_info = fence_create_info.flags(FenceCreateFlags::SIGNALED);
+        }
+        let fence = unsafe { logical_device.create_fence(&fence_create_info, None)? };
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            fence,
+        })
+    }
+
+    /// Blocks until the fence is signaled, or the timeout (in nanoseconds) elapses
+    pub fn wait(&self, timeout: u64) -> Result<()> {
+        unsafe {
+            self.logical_device
+                .wait_for_fences(&[self.fence], true, timeout)
+                .map_device_lost()?
+        };
+        Ok(())
+    }
+
+    /// Blocks until the fence is signaled, however long that takes
+    pub fn wait_forever(&self) -> Result<()> {
+        self.wait(Self::NO_TIMEOUT)
+    }
+
+    /// Returns whether the fence is currently signaled, without blocking
+    pub fn is_signaled(&self) -> Result<bool> {
+        let signaled = unsafe {
+            self.logical_device
+                .get_fence_status(self.fence)
+                .map_device_lost()?
+        };
+        Ok(signaled)
+    }
+
+    /// Unsignals the fence, so it can be signaled by another submission
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            self.logical_device
+                .reset_fences(&[self.fence])
+                .map_device_lost()?
+        };
+        Ok(())
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe { self.logical_device.destroy_fence(self.fence, None) }
+    }
+}
+
+impl Deref for Fence {
+    type Target = vk::Fence;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fence
+    }
+}
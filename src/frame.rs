@@ -1,22 +1,42 @@
-use std::rc::Rc;
+use std::{cell::Cell, time::Duration};
 
 use ash::vk::{
-    ClearColorValue, ClearValue, CommandBuffer, CommandBufferBeginInfo, CommandBufferResetFlags,
-    Fence, FenceCreateFlags, FenceCreateInfo, PipelineBindPoint, PipelineStageFlags,
-    PresentInfoKHR, Rect2D, RenderPassBeginInfo, Semaphore, SemaphoreCreateInfo, SubmitInfo,
-    SubpassContents,
+    self, ClearColorValue, ClearDepthStencilValue, ClearValue, CommandBuffer,
+    CommandBufferBeginInfo, CommandBufferResetFlags, CommandBufferUsageFlags,
+    DrawIndexedIndirectCommand, Extent2D, PipelineBindPoint, PipelineStageFlags, PresentInfoKHR,
+    QueueFlags, Rect2D, Viewport,
 };
+#[cfg(not(feature = "dynamic_rendering"))]
+use ash::vk::{RenderPassBeginInfo, SubpassContents};
 
-use anyhow::Result;
+#[cfg(feature = "dynamic_rendering")]
+use crate::transition_image_layout;
+#[cfg(feature = "dynamic_rendering")]
+use anyhow::Context;
+use anyhow::{ensure, Result};
+#[cfg(feature = "dynamic_rendering")]
+use ash::vk::{
+    AttachmentLoadOp, AttachmentStoreOp, ImageAspectFlags, ImageLayout, ImageSubresourceRange,
+    RenderingAttachmentInfo, RenderingInfo,
+};
+
+use crate::{
+    DrawCommand, Fence, GraphicsPipeline, IndirectDraw, LogicalDevice, RenderStep, RustyGamesError,
+    Semaphore, Shared, StaticCommands, Submission, SubmitBatch, Swapchain, TimelineSemaphore,
+    UiPass, FRAMES_IN_FLIGHT,
+};
 
-use crate::{GraphicsPipeline, LogicalDevice, Swapchain};
+/// How long to wait for a frame's previous draw to complete before giving up, by default
+pub const DEFAULT_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Struct representing an abstract "Frame" that can be
 /// rendered. Contains the resources needed for a particular
 /// frame rendering loop.
 pub struct Frame {
-    logical_device: Rc<LogicalDevice>,
-    graphics_pipeline: Rc<GraphicsPipeline>,
+    logical_device: Shared<LogicalDevice>,
+    /// Index of this frame within the frames in flight
+    frame_idx: usize,
+    graphics_pipeline: Shared<GraphicsPipeline>,
 
     pub command_buffer: CommandBuffer,
     /// Semaphore for when the image is available to be used from the
@@ -24,101 +44,417 @@ pub struct Frame {
     pub image_available_semaphore: Semaphore,
     /// Semaphore for when the rendering has finished
     pub render_finished_semaphore: Semaphore,
-    /// Fence for synchronizing render passes
+    /// Fence for synchronizing render passes. Unused if the timeline
+    /// semaphore is available.
     pub in_flight_fence: Fence,
+    /// Timeline semaphore shared between all frames in flight, signaled with
+    /// an increasing value on each submit. If present, it's waited on instead
+    /// of the fence.
+    timeline_semaphore: Option<Shared<TimelineSemaphore>>,
+    /// The timeline value this frame's last submission signals
+    last_signal_value: Cell<u64>,
+    /// What to draw when recording the command buffer
+    draw_command: Cell<DrawCommand>,
+    /// Drawn instead of the draw command, if set
+    indirect_draw: Cell<Option<IndirectDraw>>,
+    /// The color the image is cleared to before drawing
+    clear_color: Cell<[f32; 4]>,
 }
 
 impl Frame {
     pub fn new(
-        logical_device: &Rc<LogicalDevice>,
+        logical_device: &Shared<LogicalDevice>,
+        frame_idx: usize,
         command_buffer: CommandBuffer,
-        graphics_pipeline: &Rc<GraphicsPipeline>,
+        graphics_pipeline: &Shared<GraphicsPipeline>,
+        timeline_semaphore: Option<&Shared<TimelineSemaphore>>,
     ) -> Result<Self> {
-        let semaphore_create_info = SemaphoreCreateInfo::default();
-        let fence_create_info = FenceCreateInfo::default().flags(FenceCreateFlags::SIGNALED);
-
-        let image_available_semaphore =
-            unsafe { logical_device.create_semaphore(&semaphore_create_info, None)? };
-        let render_finished_semaphore =
-            unsafe { logical_device.create_semaphore(&semaphore_create_info, None)? };
-        let in_flight_fence = unsafe { logical_device.create_fence(&fence_create_info, None)? };
+        let image_available_semaphore = Semaphore::new(logical_device)?;
+        let render_finished_semaphore = Semaphore::new(logical_device)?;
+        // start signaled, so the first render doesn't wait forever
+        let in_flight_fence = Fence::new(logical_device, true)?;
 
         Ok(Self {
-            logical_device: Rc::clone(logical_device),
+            logical_device: Shared::clone(logical_device),
+            frame_idx,
             command_buffer,
             image_available_semaphore,
             render_finished_semaphore,
             in_flight_fence,
-            graphics_pipeline: Rc::clone(graphics_pipeline),
+            graphics_pipeline: Shared::clone(graphics_pipeline),
+            timeline_semaphore: timeline_semaphore.map(Shared::clone),
+            last_signal_value: Cell::new(0),
+            draw_command: Cell::new(DrawCommand::default()),
+            indirect_draw: Cell::new(None),
+            clear_color: Cell::new([0.0, 0.0, 0.0, 1.0]),
         })
     }
 
-    pub fn render(&self, swapchain: &Swapchain) -> Result<()> {
-        let fences = [self.in_flight_fence];
-        unsafe {
-            // wait for previous draw to complete
-            self.logical_device
-                .wait_for_fences(&fences, true, u64::MAX)?;
-            // reset the fence so that it can be re-signaled when this draw is complete
-            self.logical_device.reset_fences(&fences)?;
+    /// Names this frame's resources after its index in the frames in flight
+    pub fn set_object_names(&self) -> Result<()> {
+        let frame_idx = self.frame_idx;
+        self.logical_device.set_object_name(
+            self.command_buffer,
+            &format!("frame {frame_idx} command buffer"),
+        )?;
+        self.logical_device.set_object_name(
+            *self.image_available_semaphore,
+            &format!("frame {frame_idx} image available semaphore"),
+        )?;
+        self.logical_device.set_object_name(
+            *self.render_finished_semaphore,
+            &format!("frame {frame_idx} render finished semaphore"),
+        )?;
+        self.logical_device.set_object_name(
+            *self.in_flight_fence,
+            &format!("frame {frame_idx} in flight fence"),
+        )?;
+        Ok(())
+    }
+
+    pub fn get_draw_command(&self) -> DrawCommand {
+        self.draw_command.get()
+    }
+
+    /// Sets what's drawn the next time this frame is rendered
+    pub fn set_draw_command(&self, draw_command: DrawCommand) {
+        self.draw_command.set(draw_command);
+    }
+
+    pub fn get_indirect_draw(&self) -> Option<IndirectDraw> {
+        self.indirect_draw.get()
+    }
+
+    /// Sets an indirect draw to record in place of the draw command the next time this
+    /// frame is rendered, or goes back to the draw command if `None`
+    pub fn set_indirect_draw(&self, indirect_draw: Option<IndirectDraw>) {
+        self.indirect_draw.set(indirect_draw);
+    }
+
+    /// Returns the color the image is cleared to before drawing
+    pub fn get_clear_color(&self) -> [f32; 4] {
+        self.clear_color.get()
+    }
+
+    /// Sets the RGBA color the image is cleared to the next time this frame is rendered.
+    /// The color is in linear space: for `*_SRGB` swapchains (see
+    /// [`Swapchain::is_srgb`]) the hardware encodes it when writing, so a color picked in
+    /// sRGB should be passed through [`crate::srgb_to_linear`] first.
+    pub fn set_clear_color(&self, clear_color: [f32; 4]) {
+        self.clear_color.set(clear_color);
+    }
+
+    /// Returns the index of this frame within the frames in flight
+    pub fn get_frame_idx(&self) -> usize {
+        self.frame_idx
+    }
+
+    /// Blocks until this frame's previous draw has completed on the GPU. Errors with
+    /// [`RustyGamesError::FenceTimeout`] if it takes longer than the timeout, which
+    /// usually means the GPU has hung.
+    pub fn wait_until_idle(&self, timeout: Duration) -> Result<()> {
+        let timeout_ns = u64::try_from(timeout.as_nanos()).unwrap_or(Fence::NO_TIMEOUT);
+        let result = match &self.timeline_semaphore {
+            // the frames in flight take turns, so these are the same unless a frame
+            // failed before submitting and put them out of turn
+            Some(timeline_semaphore) => {
+                let wait_value = timeline_semaphore
+                    .get_wait_value(FRAMES_IN_FLIGHT.into())
+                    .max(self.last_signal_value.get());
+                timeline_semaphore.wait(wait_value, timeout_ns)
+            }
+            None => self.in_flight_fence.wait(timeout_ns),
+        };
+        match result {
+            Err(error) if error.downcast_ref() == Some(&vk::Result::TIMEOUT) => {
+                Err(RustyGamesError::FenceTimeout {
+                    frame_idx: self.frame_idx,
+                    timeout,
+                }
+                .into())
+            }
+            result => result,
         }
+    }
 
-        let image_index = swapchain.acquire_next_image_index(&self.image_available_semaphore)?;
+    /// Renders the frame and queues it for presentation. Waits at most `timeout` for
+    /// this frame's previous draw to complete first, see [`Frame::wait_until_idle`].
+    /// The `steps` are recorded in order before the main render pass, and the `ui_pass`
+    /// inside it after the scene.
+    pub fn render(
+        &self,
+        swapchain: &Swapchain,
+        timeout: Duration,
+        steps: &mut [&mut dyn RenderStep],
+        ui_pass: Option<&mut dyn UiPass>,
+    ) -> Result<()> {
+        let image_index = self.acquire_image(swapchain, timeout)?;
 
         unsafe {
             self.logical_device
                 .reset_command_buffer(self.command_buffer, CommandBufferResetFlags::empty())?
         }
 
-        self.record_command_buffer(image_index as usize, swapchain)?;
-
-        let wait_semaphores = [self.image_available_semaphore];
-        let signal_semaphores = [self.render_finished_semaphore];
-        let pipeline_stage_flags = [PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let command_buffers = [self.command_buffer];
-        let submit_info = [SubmitInfo::default()
-            .wait_semaphores(&wait_semaphores)
-            .wait_dst_stage_mask(&pipeline_stage_flags)
-            .command_buffers(&command_buffers)
-            .signal_semaphores(&signal_semaphores)];
-        unsafe {
-            self.logical_device.queue_submit(
-                self.logical_device.get_queues().graphics,
-                &submit_info,
-                self.in_flight_fence,
-            )?
+        self.record_command_buffer(image_index as usize, steps, ui_pass)?;
+        self.submit_and_present(swapchain, image_index, self.command_buffer)
+    }
+
+    /// Like [`Frame::render`], but submits the command buffer recorded for the image in
+    /// `static_commands` rather than recording this frame's own. Errors if they weren't
+    /// recorded with this frame's pipeline.
+    pub fn render_static(
+        &self,
+        swapchain: &Swapchain,
+        timeout: Duration,
+        static_commands: &StaticCommands,
+    ) -> Result<()> {
+        ensure!(
+            static_commands.is_recorded_for(&self.graphics_pipeline),
+            "The static commands were recorded for a pipeline that's since been recreated!"
+        );
+        let image_index = self.acquire_image(swapchain, timeout)?;
+        let command_buffer = static_commands.get_command_buffer(image_index as usize)?;
+        self.submit_and_present(swapchain, image_index, command_buffer)
+    }
+
+    /// Waits for this frame's previous draw to complete, then acquires the next swapchain
+    /// image to draw to, returning its index
+    fn acquire_image(&self, swapchain: &Swapchain, timeout: Duration) -> Result<u32> {
+        self.wait_until_idle(timeout)?;
+        // only reset the fence once an image was acquired, since an out of date swapchain
+        // skips the frame and the fence would never be signaled again
+        let image_index = swapchain.acquire_next_image_index(&self.image_available_semaphore)?;
+        if self.timeline_semaphore.is_none() {
+            // reset the fence so that it can be re-signaled when this draw is complete
+            self.in_flight_fence.reset()?;
         }
+        Ok(image_index)
+    }
+
+    /// Submits the command buffer drawing to the acquired image, then queues the image
+    /// for presentation once it's done
+    fn submit_and_present(
+        &self,
+        swapchain: &Swapchain,
+        image_index: u32,
+        command_buffer: CommandBuffer,
+    ) -> Result<()> {
+        // below full render scale the swapchain image is first written by a blit
+        let image_available_stage = match self.graphics_pipeline.get_scaled_target() {
+            Some(_) => PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | PipelineStageFlags::TRANSFER,
+            None => PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        };
+        let submission = Submission::new()
+            .command_buffer(command_buffer)
+            .wait_semaphore(*self.image_available_semaphore, image_available_stage)
+            .signal_semaphore(*self.render_finished_semaphore);
+        let graphics_queue = self.logical_device.graphics_queue();
+        match &self.timeline_semaphore {
+            Some(timeline_semaphore) => {
+                let signal_value = timeline_semaphore.next_signal_value();
+                self.last_signal_value.set(signal_value);
 
+                // presentation only supports binary semaphores, so signal both
+                let submission =
+                    submission.signal_timeline_semaphore(***timeline_semaphore, signal_value);
+                SubmitBatch::new()
+                    .add_submission(submission)
+                    .requires(QueueFlags::GRAPHICS)
+                    .submit(&self.logical_device, graphics_queue, vk::Fence::null())?;
+            }
+            None => SubmitBatch::new()
+                .add_submission(submission)
+                .requires(QueueFlags::GRAPHICS)
+                .submit(&self.logical_device, graphics_queue, *self.in_flight_fence)?,
+        }
+
+        let present_wait_semaphores = [*self.render_finished_semaphore];
         let swapchains = [*swapchain.get_handle()];
         let image_indicies = [image_index];
         let present_info = PresentInfoKHR::default()
-            .wait_semaphores(&signal_semaphores)
+            .wait_semaphores(&present_wait_semaphores)
             .swapchains(&swapchains)
             .image_indices(&image_indicies);
-        unsafe {
-            swapchain.queue_present(self.logical_device.get_queues().present, &present_info)?
-        };
+        self.logical_device
+            .present_queue()
+            .present(swapchain, &present_info)?;
 
         Ok(())
     }
 
     /// Records the command buffer for execution
-    fn record_command_buffer(&self, image_index: usize, swapchain: &Swapchain) -> Result<()> {
+    fn record_command_buffer(
+        &self,
+        image_index: usize,
+        steps: &mut [&mut dyn RenderStep],
+        ui_pass: Option<&mut dyn UiPass>,
+    ) -> Result<()> {
         let command_buffer_begin_info = CommandBufferBeginInfo::default();
         unsafe {
             self.logical_device
                 .begin_command_buffer(self.command_buffer, &command_buffer_begin_info)?
         };
 
-        let swapchain_extent = swapchain.get_extent();
-        let render_area = Rect2D::default().extent(*swapchain_extent);
+        // offscreen passes the main pass may read from go first
+        for step in steps.iter_mut() {
+            step.record(self.command_buffer, self.frame_idx)?;
+            step.record_barriers(self.command_buffer, self.frame_idx)?;
+        }
+
+        self.record_main_pass(self.command_buffer, image_index, |render_extent| {
+            match self.indirect_draw.get() {
+                Some(indirect_draw) => self.record_indirect_draw(indirect_draw),
+                None => unsafe {
+                    let draw_command = self.draw_command.get();
+                    self.logical_device.cmd_draw(
+                        self.command_buffer,
+                        draw_command.vertex_count,
+                        draw_command.instance_count,
+                        draw_command.first_vertex,
+                        draw_command.first_instance,
+                    );
+                },
+            }
+            // draw the overlay on top of the scene, in the same render pass (and so at
+            // the same resolution)
+            if let Some(ui_pass) = ui_pass {
+                ui_pass.record(self.command_buffer, render_extent, self.frame_idx)?;
+            }
+            Ok(())
+        })?;
+        unsafe {
+            self.logical_device
+                .end_command_buffer(self.command_buffer)?;
+        };
+
+        Ok(())
+    }
+
+    /// Records a command buffer for [`Frame::render_static`] to submit whenever the image
+    /// with the given index is drawn to, with `record` adding the draws inside the main
+    /// render pass. Since it's submitted again and again it isn't one time submit, and
+    /// may be pending for several frames in flight at once.
+    pub(crate) fn record_static_command_buffer<F>(
+        &self,
+        command_buffer: CommandBuffer,
+        image_index: usize,
+        record: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(CommandBuffer) -> Result<()>,
+    {
+        let command_buffer_begin_info =
+            CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::SIMULTANEOUS_USE);
+        unsafe {
+            self.logical_device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)?
+        };
+        self.record_main_pass(command_buffer, image_index, |_| record(command_buffer))?;
+        unsafe { self.logical_device.end_command_buffer(command_buffer)? };
+        Ok(())
+    }
+
+    /// Records the main render pass into the command buffer: clears the image, binds the
+    /// pipeline and has `record_draws` draw at the render extent, then scales the image
+    /// up to the swapchain image below full render scale
+    fn record_main_pass<F>(
+        &self,
+        command_buffer: CommandBuffer,
+        image_index: usize,
+        record_draws: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(Extent2D) -> Result<()>,
+    {
+        let render_extent = self.graphics_pipeline.get_render_extent();
+        let render_area = Rect2D::default().extent(render_extent);
 
+        // the second value clears the depth stencil buffer, if the pipeline has one, and
+        // is ignored otherwise
         let mut clear_value = ClearValue::default();
         clear_value.color = ClearColorValue {
-            uint32: [0, 0, 0, 1],
+            float32: self.clear_color.get(),
+        };
+        let mut depth_stencil_clear_value = ClearValue::default();
+        depth_stencil_clear_value.depth_stencil = ClearDepthStencilValue {
+            depth: self
+                .graphics_pipeline
+                .get_depth_config()
+                .unwrap_or_default()
+                .clear_value,
+            stencil: 0,
         };
-        let clear_values = [clear_value];
+        let clear_values = [clear_value, depth_stencil_clear_value];
+
+        self.begin_rendering(command_buffer, image_index, render_area, clear_values)?;
+        unsafe {
+            self.logical_device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                **self.graphics_pipeline,
+            );
+            // the pipeline's viewport and scissor are dynamic, so it survives resizes
+            self.logical_device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[Viewport::default()
+                    .width(render_extent.width as f32)
+                    .height(render_extent.height as f32)
+                    .max_depth(1.0)],
+            );
+            self.logical_device
+                .cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+        record_draws(render_extent)?;
+        self.end_rendering(command_buffer, image_index)?;
+        if let Some(scaled_target) = self.graphics_pipeline.get_scaled_target() {
+            scaled_target.record_blit(&self.logical_device, command_buffer, image_index)?;
+        }
+        Ok(())
+    }
+
+    /// Binds the index buffer and draws the indirect commands, with a call per command
+    /// if the device can't draw several per call
+    fn record_indirect_draw(&self, indirect_draw: IndirectDraw) {
+        let stride = std::mem::size_of::<DrawIndexedIndirectCommand>() as u32;
+        unsafe {
+            self.logical_device.cmd_bind_index_buffer(
+                self.command_buffer,
+                indirect_draw.index_buffer,
+                0,
+                indirect_draw.index_type,
+            );
+            if self.logical_device.is_multi_draw_indirect_enabled() {
+                self.logical_device.cmd_draw_indexed_indirect(
+                    self.command_buffer,
+                    indirect_draw.indirect_buffer,
+                    indirect_draw.offset,
+                    indirect_draw.draw_count,
+                    stride,
+                );
+            } else {
+                for draw_idx in 0..indirect_draw.draw_count {
+                    self.logical_device.cmd_draw_indexed_indirect(
+                        self.command_buffer,
+                        indirect_draw.indirect_buffer,
+                        indirect_draw.offset + (draw_idx * stride) as u64,
+                        1,
+                        stride,
+                    );
+                }
+            }
+        }
+    }
 
+    /// Begins the render pass drawing to the swapchain image's framebuffer
+    #[cfg(not(feature = "dynamic_rendering"))]
+    fn begin_rendering(
+        &self,
+        command_buffer: CommandBuffer,
+        image_index: usize,
+        render_area: Rect2D,
+        clear_values: [ClearValue; 2],
+    ) -> Result<()> {
         let render_pass_begin_info = RenderPassBeginInfo::default()
             .render_pass(**self.graphics_pipeline.get_render_pass())
             .framebuffer(
@@ -130,35 +466,123 @@ impl Frame {
             .clear_values(&clear_values);
         unsafe {
             self.logical_device.cmd_begin_render_pass(
-                self.command_buffer,
+                command_buffer,
                 &render_pass_begin_info,
                 SubpassContents::INLINE,
-            );
-            self.logical_device.cmd_bind_pipeline(
-                self.command_buffer,
-                PipelineBindPoint::GRAPHICS,
-                **self.graphics_pipeline,
-            );
-            self.logical_device
-                .cmd_draw(self.command_buffer, 3, 1, 0, 0);
-            self.logical_device.cmd_end_render_pass(self.command_buffer);
-            self.logical_device
-                .end_command_buffer(self.command_buffer)?;
+            )
         };
+        Ok(())
+    }
 
+    #[cfg(not(feature = "dynamic_rendering"))]
+    fn end_rendering(&self, command_buffer: CommandBuffer, _image_index: usize) -> Result<()> {
+        unsafe { self.logical_device.cmd_end_render_pass(command_buffer) };
         Ok(())
     }
-}
 
-impl Drop for Frame {
-    fn drop(&mut self) {
+    /// Transitions the swapchain image, or the offscreen image below full render scale,
+    /// (and depth stencil buffer, if any) to be rendered to, then starts rendering to them
+    /// directly. This does what the render pass's attachment descriptions and layouts
+    /// would otherwise.
+    #[cfg(feature = "dynamic_rendering")]
+    fn begin_rendering(
+        &self,
+        command_buffer: CommandBuffer,
+        image_index: usize,
+        render_area: Rect2D,
+        clear_values: [ClearValue; 2],
+    ) -> Result<()> {
+        let [color_clear_value, depth_stencil_clear_value] = clear_values;
+        let image = match self.graphics_pipeline.get_scaled_target() {
+            Some(scaled_target) => scaled_target.get_color_image(),
+            None => self.graphics_pipeline.get_image_for_index(image_index),
+        };
+        transition_image_layout(
+            &self.logical_device,
+            command_buffer,
+            **image,
+            ImageLayout::UNDEFINED,
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            color_subresource_range(),
+        )?;
+        let color_attachments = [RenderingAttachmentInfo::default()
+            .image_view(**image.get_view().context("Swapchain images have a view")?)
+            .image_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::STORE)
+            .clear_value(color_clear_value)];
+        let mut rendering_info = RenderingInfo::default()
+            .render_area(render_area)
+            .layer_count(1)
+            .color_attachments(&color_attachments);
+
+        let depth_stencil_attachment;
+        if let Some(depth_stencil_image) = self.graphics_pipeline.get_depth_stencil_image() {
+            transition_image_layout(
+                &self.logical_device,
+                command_buffer,
+                **depth_stencil_image,
+                ImageLayout::UNDEFINED,
+                ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                ImageSubresourceRange::default()
+                    .aspect_mask(ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL)
+                    .level_count(1)
+                    .layer_count(1),
+            )?;
+            // depth is stored so it can be read back after the frame, for picking
+            depth_stencil_attachment = RenderingAttachmentInfo::default()
+                .image_view(
+                    **depth_stencil_image
+                        .get_view()
+                        .context("Depth stencil images have a view")?,
+                )
+                .image_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .load_op(AttachmentLoadOp::CLEAR)
+                .store_op(AttachmentStoreOp::STORE)
+                .clear_value(depth_stencil_clear_value);
+            rendering_info = rendering_info
+                .depth_attachment(&depth_stencil_attachment)
+                .stencil_attachment(&depth_stencil_attachment);
+        }
+
         unsafe {
             self.logical_device
-                .destroy_fence(self.in_flight_fence, None);
-            self.logical_device
-                .destroy_semaphore(self.image_available_semaphore, None);
-            self.logical_device
-                .destroy_semaphore(self.render_finished_semaphore, None);
+                .cmd_begin_rendering(command_buffer, &rendering_info)
+        };
+        Ok(())
+    }
+
+    /// Stops rendering and transitions the swapchain image for presentation, or the
+    /// offscreen image to be blitted from below full render scale
+    #[cfg(feature = "dynamic_rendering")]
+    fn end_rendering(&self, command_buffer: CommandBuffer, image_index: usize) -> Result<()> {
+        unsafe { self.logical_device.cmd_end_rendering(command_buffer) };
+        if let Some(scaled_target) = self.graphics_pipeline.get_scaled_target() {
+            return transition_image_layout(
+                &self.logical_device,
+                command_buffer,
+                ***scaled_target.get_color_image(),
+                ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                color_subresource_range(),
+            );
         }
+        transition_image_layout(
+            &self.logical_device,
+            command_buffer,
+            **self.graphics_pipeline.get_image_for_index(image_index),
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            ImageLayout::PRESENT_SRC_KHR,
+            color_subresource_range(),
+        )
     }
 }
+
+/// The whole of a swapchain image, which has a single mip level and layer
+#[cfg(feature = "dynamic_rendering")]
+fn color_subresource_range() -> ImageSubresourceRange {
+    ImageSubresourceRange::default()
+        .aspect_mask(ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1)
+}
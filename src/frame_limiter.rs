@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+/// How long before a frame's deadline to stop sleeping and start spinning, since sleeps
+/// tend to overshoot by around a millisecond
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+/// How much each new frame time counts toward the measured average, smoothing out jitter
+const FRAME_TIME_SMOOTHING: f64 = 0.1;
+
+/// Caps how often frames are drawn, to save power when the display refreshes faster than
+/// the game needs. Independent of the swapchain's present mode, so it also caps frame
+/// rates under FIFO on high refresh rate monitors. Also measures the frame rate actually
+/// reached, cap included.
+pub struct FrameLimiter {
+    /// The time between frames to aim for, or `None` to not limit
+    target_frame_time: Option<Duration>,
+    /// When the next frame may start
+    next_frame_at: Option<Instant>,
+    /// When the previous frame started, for measuring the frame time
+    last_frame_at: Option<Instant>,
+    /// Moving average of the time between frames
+    average_frame_time: Option<Duration>,
+}
+
+impl FrameLimiter {
+    /// Creates a limiter targeting the frame rate, or not limiting it if `None`
+    pub fn new(target_fps: Option<u32>) -> Self {
+        let mut frame_limiter = Self {
+            target_frame_time: None,
+            next_frame_at: None,
+            last_frame_at: None,
+            average_frame_time: None,
+        };
+        frame_limiter.set_target_fps(target_fps);
+        frame_limiter
+    }
+
+    /// Returns the frame rate being targeted, if there is one
+    pub fn get_target_fps(&self) -> Option<u32> {
+        self.target_frame_time
+            .map(|frame_time| (1.0 / frame_time.as_secs_f64()).round() as u32)
+    }
+
+    /// Targets the frame rate, or stops limiting it if `None`. A target of 0 is treated
+    /// as `None`.
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) {
+        self.target_frame_time = target_fps
+            .filter(|target_fps| *target_fps > 0)
+            .map(|target_fps| Duration::from_secs_f64(1.0 / target_fps as f64));
+        self.next_frame_at = None;
+    }
+
+    /// Blocks until the next frame is due, then marks it as started. Call right before
+    /// drawing each frame.
+    pub fn wait(&mut self) {
+        if let Some(next_frame_at) = self.next_frame_at {
+            // sleep for most of the wait, then spin for precision
+            let now = Instant::now();
+            if next_frame_at > now + SPIN_MARGIN {
+                std::thread::sleep(next_frame_at - now - SPIN_MARGIN);
+            }
+            while Instant::now() < next_frame_at {
+                std::hint::spin_loop();
+            }
+        }
+
+        let now = Instant::now();
+        self.next_frame_at = self.target_frame_time.map(|target_frame_time| {
+            // keep a steady cadence, unless the frame ran long enough to miss its slot; then
+            // start over from now, rather than rushing to catch up
+            match self.next_frame_at {
+                Some(next_frame_at) if now < next_frame_at + target_frame_time => {
+                    next_frame_at + target_frame_time
+                }
+                _ => now + target_frame_time,
+            }
+        });
+
+        if let Some(last_frame_at) = self.last_frame_at {
+            let frame_time = now - last_frame_at;
+            self.average_frame_time = Some(match self.average_frame_time {
+                Some(average_frame_time) => {
+                    average_frame_time.mul_f64(1.0 - FRAME_TIME_SMOOTHING)
+                        + frame_time.mul_f64(FRAME_TIME_SMOOTHING)
+                }
+                None => frame_time,
+            });
+        }
+        self.last_frame_at = Some(now);
+    }
+
+    /// Returns the average time between frames, once at least two have been drawn
+    pub fn get_frame_time(&self) -> Option<Duration> {
+        self.average_frame_time
+    }
+
+    /// Returns the measured frame rate, once at least two frames have been drawn
+    pub fn get_fps(&self) -> Option<f64> {
+        self.average_frame_time
+            .filter(|frame_time| !frame_time.is_zero())
+            .map(|frame_time| 1.0 / frame_time.as_secs_f64())
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{ensure, Result};
+use ash::vk::{
+    BufferUsageFlags, DescriptorBufferInfo, DescriptorPoolSize, DescriptorSet, DescriptorType,
+    DeviceSize, MemoryPropertyFlags, WriteDescriptorSet,
+};
+
+use crate::{Buffer, DescriptorPool, DescriptorSetLayout, LogicalDevice, Shared, FRAMES_IN_FLIGHT};
+
+/// A uniform buffer and descriptor set for each frame in flight, so a frame can update
+/// its uniforms while the previous frame's draw is still reading its own.
+///
+/// Frame `n` (as in [`crate::CommandPool::get_next_frame`]) owns buffer and set `n`: only
+/// update them once that frame's previous draw has completed, i.e. after its
+/// `wait_until_idle`, and before its `render`. Neither needs the whole device to be
+/// idle.
+pub struct FrameUniforms {
+    /// One per frame in flight, indexed by the frame's index
+    buffers: Vec<Buffer>,
+    descriptor_sets: Vec<DescriptorSet>,
+    // the sets are freed with the pool, so it has to outlive them
+    _descriptor_pool: DescriptorPool,
+}
+
+impl FrameUniforms {
+    /// Creates a `size` byte uniform buffer per frame in flight, and a descriptor set
+    /// with `layout` pointing `binding` at it
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        layout: &DescriptorSetLayout,
+        binding: u32,
+        size: DeviceSize,
+    ) -> Result<Self> {
+        let frame_count = FRAMES_IN_FLIGHT;
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(frame_count)];
+        let descriptor_pool = DescriptorPool::new(logical_device, frame_count, &pool_sizes)?;
+        let layouts = vec![**layout; frame_count as usize];
+        let descriptor_sets = descriptor_pool.allocate(&layouts)?;
+
+        let buffers = (0..frame_count)
+            .map(|_| {
+                Buffer::new(
+                    logical_device,
+                    size,
+                    BufferUsageFlags::UNIFORM_BUFFER,
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (idx, buffer) in buffers.iter().enumerate() {
+            logical_device.set_object_name(**buffer, &format!("frame {idx} uniform buffer"))?;
+        }
+
+        // the sets always point at the same buffers, so they only need writing once
+        let buffer_infos = buffers
+            .iter()
+            .map(|buffer| [DescriptorBufferInfo::default().buffer(**buffer).range(size)])
+            .collect::<Vec<_>>();
+        let descriptor_writes = descriptor_sets
+            .iter()
+            .zip(&buffer_infos)
+            .map(|(descriptor_set, buffer_info)| {
+                WriteDescriptorSet::default()
+                    .dst_set(*descriptor_set)
+                    .dst_binding(binding)
+                    .descriptor_type(DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(buffer_info)
+            })
+            .collect::<Vec<_>>();
+        unsafe { logical_device.update_descriptor_sets(&descriptor_writes, &[]) };
+
+        Ok(Self {
+            buffers,
+            descriptor_sets,
+            _descriptor_pool: descriptor_pool,
+        })
+    }
+
+    /// Writes `data` to the start of the frame's uniform buffer. The frame's previous
+    /// draw must have completed.
+    pub fn update(&self, frame_idx: usize, data: &[u8]) -> Result<()> {
+        check_frame_idx(frame_idx, self.buffers.len())?;
+        self.buffers[frame_idx].write(0, data)
+    }
+
+    /// Returns the descriptor set to bind when recording the frame
+    pub fn get_descriptor_set(&self, frame_idx: usize) -> DescriptorSet {
+        self.descriptor_sets[frame_idx]
+    }
+
+    pub fn get_buffer(&self, frame_idx: usize) -> &Buffer {
+        &self.buffers[frame_idx]
+    }
+}
+
+/// Errors unless `frame_idx` is one of the `frame_count` frames in flight
+fn check_frame_idx(frame_idx: usize, frame_count: usize) -> Result<()> {
+    ensure!(
+        frame_idx < frame_count,
+        "There are only {frame_count} frames in flight, not {}!",
+        frame_idx + 1
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_frame_in_flight_can_be_updated() {
+        for frame_idx in 0..FRAMES_IN_FLIGHT as usize {
+            assert!(check_frame_idx(frame_idx, FRAMES_IN_FLIGHT as usize).is_ok());
+        }
+    }
+
+    #[test]
+    fn frames_past_those_in_flight_cant_be_updated() {
+        let frame_count = FRAMES_IN_FLIGHT as usize;
+        let error = check_frame_idx(frame_count, frame_count).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "There are only {frame_count} frames in flight, not {}!",
+                frame_count + 1
+            )
+        );
+    }
+}
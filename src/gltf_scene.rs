@@ -0,0 +1,499 @@
+use std::{mem::offset_of, ops::Range, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use ash::vk::{
+    BufferImageCopy, BufferUsageFlags, DescriptorImageInfo, DescriptorSet,
+    DescriptorSetLayoutBinding, DescriptorType, Extent2D, Extent3D, Format, ImageAspectFlags,
+    ImageLayout, ImageSubresourceLayers, ImageSubresourceRange, ImageUsageFlags,
+    MemoryPropertyFlags, SamplerAddressMode, ShaderStageFlags, VertexInputAttributeDescription,
+    VertexInputBindingDescription, WriteDescriptorSet,
+};
+use gltf::{image::Format as GltfFormat, mesh::Mode, Document};
+
+use crate::{
+    transition_image_layout,
+    vertex::{attribute_description_for, binding_description_for},
+    Buffer, CommandPool, DescriptorAllocator, DescriptorSetLayout, Image, ImageDescriptor,
+    LogicalDevice, Mat4, Mesh, Node, Sampler, Shared, Vertex,
+};
+
+/// A vertex of a glTF primitive. Attributes the primitive doesn't have are left zeroed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GltfVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// xyz is the tangent, w is 1.0 or -1.0 for the handedness of the bitangent
+    pub tangent: [f32; 4],
+    /// From the primitive's first set of texture coordinates
+    pub uv: [f32; 2],
+}
+
+impl Vertex for GltfVertex {
+    fn binding_description() -> VertexInputBindingDescription {
+        binding_description_for::<Self>()
+    }
+
+    fn attribute_descriptions() -> Vec<VertexInputAttributeDescription> {
+        vec![
+            attribute_description_for::<[f32; 3]>(0, offset_of!(GltfVertex, position)),
+            attribute_description_for::<[f32; 3]>(1, offset_of!(GltfVertex, normal)),
+            attribute_description_for::<[f32; 4]>(2, offset_of!(GltfVertex, tangent)),
+            attribute_description_for::<[f32; 2]>(3, offset_of!(GltfVertex, uv)),
+        ]
+    }
+}
+
+/// A glTF material's base color, along with a descriptor set binding its base color
+/// texture (white if it has none) as a combined image sampler at binding 0
+pub struct Material {
+    pub base_color_factor: [f32; 4],
+    /// Index into [`GltfScene::get_textures`], if the material has a base color texture
+    pub base_color_texture: Option<usize>,
+    descriptor_set: DescriptorSet,
+}
+
+impl Material {
+    /// Returns the set to bind for meshes drawn with the material, laid out as
+    /// [`GltfScene::get_material_layout`]
+    pub fn get_descriptor_set(&self) -> DescriptorSet {
+        self.descriptor_set
+    }
+}
+
+/// The meshes, materials and node hierarchy of a glTF model. Each primitive becomes a
+/// [`Mesh`] of [`GltfVertex`]s with the index of its material, and the nodes refer to
+/// meshes by their index into [`GltfScene::get_meshes`]. Only triangle list primitives
+/// and 8 bit base color textures are supported; the textures' samplers are ignored in
+/// favor of a linear, repeating one.
+pub struct GltfScene {
+    meshes: Vec<Mesh>,
+    nodes: Vec<Node>,
+    materials: Vec<Material>,
+    /// Used by primitives without a material
+    default_material: Material,
+    // the materials' sets are freed with the allocator's pools, before the layout and the
+    // textures they point at are destroyed
+    _descriptor_allocator: DescriptorAllocator,
+    material_layout: DescriptorSetLayout,
+    textures: Vec<Image>,
+    /// Bound by materials without a base color texture
+    _white_texture: Image,
+    _sampler: Sampler,
+}
+
+impl GltfScene {
+    /// Imports the model at `path` along with the buffers and images it refers to, and
+    /// uploads its meshes and base color textures, blocking until they're ready. The
+    /// nodes are those of the model's default scene, or its first if it has no default.
+    pub fn load(
+        logical_device: &Shared<LogicalDevice>,
+        command_pool: &CommandPool,
+        path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let (document, buffers, images) = gltf::import(path)
+            .with_context(|| format!("Failed to import the glTF model {}!", path.display()))?;
+
+        let sampler = Sampler::new(logical_device, SamplerAddressMode::REPEAT)?;
+        let white_texture = upload_texture(logical_device, command_pool, 1, 1, &[255; 4])?;
+        // only the images used as base colors are uploaded
+        let mut image_textures = vec![None; images.len()];
+        let mut textures = vec![];
+        for material in document.materials() {
+            let Some(info) = material.pbr_metallic_roughness().base_color_texture() else {
+                continue;
+            };
+            let image_idx = info.texture().source().index();
+            if image_textures[image_idx].is_none() {
+                let image = &images[image_idx];
+                let pixels = to_rgba8(image.format, &image.pixels)?;
+                textures.push(upload_texture(
+                    logical_device,
+                    command_pool,
+                    image.width,
+                    image.height,
+                    &pixels,
+                )?);
+                image_textures[image_idx] = Some(textures.len() - 1);
+            }
+        }
+
+        let material_layout = DescriptorSetLayout::new(
+            logical_device,
+            &[DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::FRAGMENT)],
+        )?;
+        let mut descriptor_allocator = DescriptorAllocator::new(
+            logical_device,
+            document.materials().len() as u32 + 1,
+            &[(DescriptorType::COMBINED_IMAGE_SAMPLER, 1)],
+        )?;
+        let mut create_material = |base_color_factor, base_color_texture: Option<usize>| {
+            let texture = base_color_texture.map_or(&white_texture, |idx| &textures[idx]);
+            let descriptor_set = descriptor_allocator.allocate(&material_layout)?;
+            write_texture(logical_device, descriptor_set, texture, &sampler)?;
+            Ok::<_, anyhow::Error>(Material {
+                base_color_factor,
+                base_color_texture,
+                descriptor_set,
+            })
+        };
+        let materials = document
+            .materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                let base_color_texture = pbr
+                    .base_color_texture()
+                    .and_then(|info| image_textures[info.texture().source().index()]);
+                create_material(pbr.base_color_factor(), base_color_texture)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let default_material = create_material([1.0; 4], None)?;
+
+        let mut meshes = vec![];
+        let mut mesh_primitives = vec![];
+        for mesh in document.meshes() {
+            let first_primitive = meshes.len();
+            for primitive in mesh.primitives() {
+                ensure!(
+                    primitive.mode() == Mode::Triangles,
+                    "Only triangle list primitives are supported, not {:?}!",
+                    primitive.mode()
+                );
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                let mut vertices = reader
+                    .read_positions()
+                    .context("glTF primitives need vertex positions!")?
+                    .map(|position| GltfVertex {
+                        position,
+                        ..Default::default()
+                    })
+                    .collect::<Vec<_>>();
+                for (vertex, normal) in vertices
+                    .iter_mut()
+                    .zip(reader.read_normals().into_iter().flatten())
+                {
+                    vertex.normal = normal;
+                }
+                for (vertex, tangent) in vertices
+                    .iter_mut()
+                    .zip(reader.read_tangents().into_iter().flatten())
+                {
+                    vertex.tangent = tangent;
+                }
+                if let Some(uvs) = reader.read_tex_coords(0) {
+                    for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                        vertex.uv = uv;
+                    }
+                }
+                let indices = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    // unindexed primitives draw their vertices in order
+                    None => (0..vertices.len() as u32).collect::<Vec<_>>(),
+                };
+                meshes.push(Mesh::new(
+                    logical_device,
+                    command_pool,
+                    &vertices,
+                    &indices,
+                    primitive.material().index(),
+                )?);
+            }
+            mesh_primitives.push(first_primitive..meshes.len());
+        }
+
+        Ok(Self {
+            meshes,
+            nodes: build_nodes(&document, &mesh_primitives),
+            materials,
+            default_material,
+            _descriptor_allocator: descriptor_allocator,
+            material_layout,
+            textures,
+            _white_texture: white_texture,
+            _sampler: sampler,
+        })
+    }
+
+    /// Returns one mesh per primitive, of every mesh in the model
+    pub fn get_meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    /// Returns the root nodes of the scene, whose traversal gives the world transform of
+    /// each mesh to draw
+    pub fn get_nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn get_materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// Returns the material with the index, such as [`Mesh::get_material_index`], or the
+    /// default white one for `None`
+    pub fn get_material(&self, index: Option<usize>) -> &Material {
+        index.map_or(&self.default_material, |idx| &self.materials[idx])
+    }
+
+    /// Returns the layout of the materials' descriptor sets, for building the pipeline
+    /// layouts that draw the meshes
+    pub fn get_material_layout(&self) -> &DescriptorSetLayout {
+        &self.material_layout
+    }
+
+    /// Returns the base color textures, in `R8G8B8A8_SRGB`
+    pub fn get_textures(&self) -> &[Image] {
+        &self.textures
+    }
+}
+
+/// Builds the nodes of the document's default scene (or first scene), given the range
+/// of [`Mesh`]es each glTF mesh's primitives were loaded into. Nodes only draw a single
+/// mesh, so the primitives after the first hang off the node as children.
+fn build_nodes(document: &Document, mesh_primitives: &[Range<usize>]) -> Vec<Node> {
+    fn build_node(node: gltf::Node, mesh_primitives: &[Range<usize>]) -> Node {
+        let mut scene_node = Node::new(Mat4 {
+            cols: node.transform().matrix(),
+        });
+        if let Some(mesh) = node.mesh() {
+            let mut primitives = mesh_primitives[mesh.index()].clone();
+            if let Some(first_primitive) = primitives.next() {
+                scene_node = scene_node.with_mesh(first_primitive);
+            }
+            for primitive in primitives {
+                scene_node = scene_node.add_child(Node::default().with_mesh(primitive));
+            }
+        }
+        for child in node.children() {
+            scene_node = scene_node.add_child(build_node(child, mesh_primitives));
+        }
+        scene_node
+    }
+
+    document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map_or(vec![], |scene| {
+            scene
+                .nodes()
+                .map(|node| build_node(node, mesh_primitives))
+                .collect()
+        })
+}
+
+/// Expands the pixels of a decoded glTF image to RGBA, with gray and gray + alpha
+/// images spreading the gray across the color channels
+fn to_rgba8(format: GltfFormat, pixels: &[u8]) -> Result<Vec<u8>> {
+    let channels = match format {
+        GltfFormat::R8 => 1,
+        GltfFormat::R8G8 => 2,
+        GltfFormat::R8G8B8 => 3,
+        GltfFormat::R8G8B8A8 => return Ok(pixels.to_vec()),
+        format => bail!("Textures in the {format:?} format aren't supported!"),
+    };
+    ensure!(
+        pixels.len().is_multiple_of(channels),
+        "The image's pixels don't fit its {format:?} format!"
+    );
+    Ok(pixels
+        .chunks_exact(channels)
+        .flat_map(|texel| match *texel {
+            [gray] => [gray, gray, gray, u8::MAX],
+            [gray, alpha] => [gray, gray, gray, alpha],
+            [red, green, blue] => [red, green, blue, u8::MAX],
+            _ => unreachable!(),
+        })
+        .collect())
+}
+
+/// Uploads tightly packed RGBA pixels into a sampled sRGB image, blocking until it's in
+/// `SHADER_READ_ONLY_OPTIMAL`
+fn upload_texture(
+    logical_device: &Shared<LogicalDevice>,
+    command_pool: &CommandPool,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<Image> {
+    ensure!(
+        pixels.len() as u64 == u64::from(width) * u64::from(height) * 4,
+        "A {width}x{height} texture needs {} bytes of pixels, not {}!",
+        u64::from(width) * u64::from(height) * 4,
+        pixels.len()
+    );
+    let staging_buffer = Buffer::new(
+        logical_device,
+        pixels.len() as u64,
+        BufferUsageFlags::TRANSFER_SRC,
+        MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+    staging_buffer.write(0, pixels)?;
+
+    let image = Image::new(
+        logical_device,
+        &ImageDescriptor {
+            format: Format::R8G8B8A8_SRGB,
+            extent: Extent2D { width, height },
+            usage: ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED,
+            ..Default::default()
+        },
+    )?;
+    let region = BufferImageCopy::default()
+        .image_subresource(
+            ImageSubresourceLayers::default()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .layer_count(1),
+        )
+        .image_extent(Extent3D {
+            width,
+            height,
+            depth: 1,
+        });
+    let subresource_range = ImageSubresourceRange::default()
+        .aspect_mask(ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1);
+    command_pool.run_one_time_commands(logical_device.graphics_queue(), |command_buffer| {
+        transition_image_layout(
+            logical_device,
+            command_buffer,
+            *image,
+            ImageLayout::UNDEFINED,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            subresource_range,
+        )?;
+        unsafe {
+            logical_device.cmd_copy_buffer_to_image(
+                command_buffer,
+                *staging_buffer,
+                *image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            )
+        };
+        transition_image_layout(
+            logical_device,
+            command_buffer,
+            *image,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            subresource_range,
+        )
+    })?;
+    Ok(image)
+}
+
+/// Points binding 0 of the set at the texture
+fn write_texture(
+    logical_device: &LogicalDevice,
+    descriptor_set: DescriptorSet,
+    texture: &Image,
+    sampler: &Sampler,
+) -> Result<()> {
+    let image_infos = [DescriptorImageInfo::default()
+        .image_view(**texture.get_view().context("Textures need an image view")?)
+        .sampler(**sampler)
+        .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+    let descriptor_writes = [WriteDescriptorSet::default()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .image_info(&image_infos)];
+    unsafe { logical_device.update_descriptor_sets(&descriptor_writes, &[]) };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A model with a parent node drawing a two primitive mesh, offset by a child node
+    /// drawing the other mesh. The buffers aren't loaded, so the accessors are never read.
+    const MODEL: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "mesh": 0, "scale": [2.0, 2.0, 2.0], "children": [1] },
+            { "mesh": 1, "translation": [1.0, 0.0, 0.0] }
+        ],
+        "meshes": [
+            { "primitives": [{ "attributes": { "POSITION": 0 } }, { "attributes": { "POSITION": 0 } }] },
+            { "primitives": [{ "attributes": { "POSITION": 0 } }] }
+        ],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{ "byteLength": 36 }]
+    }"#;
+
+    #[test]
+    fn nodes_keep_their_transforms_and_meshes() {
+        let gltf = gltf::Gltf::from_slice(MODEL.as_bytes()).unwrap();
+        let nodes = build_nodes(&gltf.document, &[0..2, 2..3]);
+
+        assert_eq!(nodes.len(), 1);
+        let root = &nodes[0];
+        assert_eq!(root.transform, Mat4::from_scale([2.0, 2.0, 2.0]));
+        assert_eq!(root.mesh, Some(0));
+        // the second primitive, then the child node
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].transform, Mat4::IDENTITY);
+        assert_eq!(root.children[0].mesh, Some(1));
+        assert_eq!(
+            root.children[1].transform,
+            Mat4::from_translation([1.0, 0.0, 0.0])
+        );
+        assert_eq!(root.children[1].mesh, Some(2));
+    }
+
+    #[test]
+    fn child_meshes_are_drawn_in_their_parents_space() {
+        let gltf = gltf::Gltf::from_slice(MODEL.as_bytes()).unwrap();
+        let nodes = build_nodes(&gltf.document, &[0..2, 2..3]);
+        let draws = nodes[0].collect_draws();
+
+        let scale = Mat4::from_scale([2.0, 2.0, 2.0]);
+        assert_eq!(
+            draws,
+            vec![
+                (scale, 0),
+                (scale, 1),
+                (scale * Mat4::from_translation([1.0, 0.0, 0.0]), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn images_are_expanded_to_rgba() {
+        assert_eq!(
+            to_rgba8(GltfFormat::R8, &[10, 20]).unwrap(),
+            [10, 10, 10, 255, 20, 20, 20, 255]
+        );
+        assert_eq!(
+            to_rgba8(GltfFormat::R8G8, &[10, 128]).unwrap(),
+            [10, 10, 10, 128]
+        );
+        assert_eq!(
+            to_rgba8(GltfFormat::R8G8B8, &[1, 2, 3]).unwrap(),
+            [1, 2, 3, 255]
+        );
+        assert_eq!(
+            to_rgba8(GltfFormat::R8G8B8A8, &[1, 2, 3, 4]).unwrap(),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn unsupported_images_are_rejected() {
+        assert!(to_rgba8(GltfFormat::R16G16B16A16, &[0; 8]).is_err());
+        assert!(to_rgba8(GltfFormat::R8G8B8, &[0; 4]).is_err());
+    }
+}
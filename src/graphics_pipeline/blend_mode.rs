@@ -0,0 +1,46 @@
+use ash::vk::{BlendFactor, BlendOp, ColorComponentFlags, PipelineColorBlendAttachmentState};
+
+/// How the colors output by the fragment shader are combined with what's
+/// already in the framebuffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Output colors replace the framebuffer's colors
+    #[default]
+    Opaque,
+    /// Output colors are mixed with the framebuffer's colors based on their
+    /// alpha, for transparent geometry
+    AlphaBlend,
+    /// Output colors, scaled by their alpha, are added onto the framebuffer's
+    /// colors, for glowing effects like particles and lights
+    Additive,
+}
+
+impl BlendMode {
+    /// Returns the blend settings for a color attachment in this mode
+    pub fn get_color_blend_attachment_state(&self) -> PipelineColorBlendAttachmentState {
+        let color_blend_attachment_state = PipelineColorBlendAttachmentState::default()
+            .color_write_mask(ColorComponentFlags::RGBA);
+        match self {
+            // color output from the fragment shader passes thru unmodified
+            BlendMode::Opaque => color_blend_attachment_state.blend_enable(false),
+            // final color = src.rgb * src.a + dst.rgb * (1 - src.a)
+            BlendMode::AlphaBlend => color_blend_attachment_state
+                .blend_enable(true)
+                .src_color_blend_factor(BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .color_blend_op(BlendOp::ADD)
+                .src_alpha_blend_factor(BlendFactor::ONE)
+                .dst_alpha_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .alpha_blend_op(BlendOp::ADD),
+            // final color = src.rgb * src.a + dst.rgb
+            BlendMode::Additive => color_blend_attachment_state
+                .blend_enable(true)
+                .src_color_blend_factor(BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(BlendFactor::ONE)
+                .color_blend_op(BlendOp::ADD)
+                .src_alpha_blend_factor(BlendFactor::ONE)
+                .dst_alpha_blend_factor(BlendFactor::ONE)
+                .alpha_blend_op(BlendOp::ADD),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use ash::vk::CompareOp;
+
+use crate::Mat4;
+
+/// How a pipeline tests fragments against the depth buffer, and what the buffer is
+/// cleared to at the start of every frame.
+///
+/// The two have to agree: the clear value should be the depth that loses every test,
+/// so the first fragment drawn to a pixel always passes. With the default `LESS` that's
+/// the far plane at 1.0, while comparing with `GREATER` or `GREATER_OR_EQUAL`, as
+/// reverse-Z does, needs a clear value of 0.0 instead. [`DepthConfig::reverse_z`] sets
+/// both, and [`DepthConfig::perspective`] builds a projection matching either mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthConfig {
+    /// Compares a fragment's depth with the buffer's, keeping the fragment if it passes
+    pub compare_op: CompareOp,
+    /// The depth the buffer is cleared to
+    pub clear_value: f32,
+}
+
+impl Default for DepthConfig {
+    /// Keeps the fragments closest to the camera, with depth growing from 0.0 at the
+    /// near plane to 1.0 at the far plane
+    fn default() -> Self {
+        Self {
+            compare_op: CompareOp::LESS,
+            clear_value: 1.0,
+        }
+    }
+}
+
+impl DepthConfig {
+    /// Reverse-Z: depth goes from 1.0 at the near plane to 0.0 at the far plane, keeping
+    /// the fragments with the greatest depth. Since floats are most precise near 0.0,
+    /// this spreads precision far more evenly across the scene than the default, which
+    /// avoids z-fighting on distant geometry. Vertices must be projected with
+    /// [`DepthConfig::perspective`] (or [`Mat4::perspective_reverse_z`]) to match.
+    pub fn reverse_z() -> Self {
+        Self {
+            compare_op: CompareOp::GREATER_OR_EQUAL,
+            clear_value: 0.0,
+        }
+    }
+
+    /// Whether depth decreases away from the camera, as set up by
+    /// [`DepthConfig::reverse_z`]
+    pub fn is_reverse_z(&self) -> bool {
+        matches!(
+            self.compare_op,
+            CompareOp::GREATER | CompareOp::GREATER_OR_EQUAL
+        )
+    }
+
+    /// Builds a perspective projection whose depth range matches this config, so the
+    /// depth test, the clear value and the projection can't disagree. See
+    /// [`Mat4::perspective`] for the parameters; `aspect` would usually be
+    /// [`crate::Swapchain::get_aspect_ratio`], read each frame so resizes are picked up.
+    pub fn perspective(&self, fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        if self.is_reverse_z() {
+            Mat4::perspective_reverse_z(fov_y, aspect, near, far)
+        } else {
+            Mat4::perspective(fov_y, aspect, near, far)
+        }
+    }
+}
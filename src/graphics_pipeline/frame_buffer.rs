@@ -1,29 +1,57 @@
-use std::{ops::Deref, rc::Rc};
+use std::ops::Deref;
 
-use crate::{ImageView, LogicalDevice};
+use crate::{Image, LogicalDevice, Shared};
 
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
 use ash::vk::{self, Extent2D, FramebufferCreateInfo};
 
 use super::render_pass::RenderPass;
 
 pub struct Framebuffer {
-    logical_device: Rc<LogicalDevice>,
+    logical_device: Shared<LogicalDevice>,
     framebuffer: vk::Framebuffer,
     // variables we need to hold onto so they dont get cleaned
     // up before we do
-    _render_pass: Rc<RenderPass>,
-    _image_view: ImageView,
+    _render_pass: Shared<RenderPass>,
+    _image: Shared<Image>,
+    _depth_stencil_image: Option<Shared<Image>>,
 }
 
 impl Framebuffer {
+    /// Creates a framebuffer for the render pass's first subpass, drawing to `image` and
+    /// testing against `depth_stencil_image` if given. Errors if the images don't have
+    /// the number of samples the subpass rasterizes with.
     pub fn new(
-        logical_device: &Rc<LogicalDevice>,
-        render_pass: &Rc<RenderPass>,
+        logical_device: &Shared<LogicalDevice>,
+        render_pass: &Shared<RenderPass>,
         extent: &Extent2D,
-        image_view: ImageView,
+        image: Shared<Image>,
+        depth_stencil_image: Option<&Shared<Image>>,
     ) -> Result<Self> {
-        let attachments = [*image_view];
+        let image_view = image
+            .get_view()
+            .context("Framebuffer attachments need an image view")?;
+        let samples = render_pass.get_sample_count(0);
+        ensure!(
+            image.get_samples() == samples,
+            "The color attachment has {:?} samples, but the render pass draws with {:?}!",
+            image.get_samples(),
+            samples
+        );
+        let mut attachments = vec![**image_view];
+        if let Some(depth_stencil_image) = depth_stencil_image {
+            // validation fails unless depth is sampled just like color
+            ensure!(
+                depth_stencil_image.get_samples() == samples,
+                "The depth stencil attachment has {:?} samples, but the color attachment has {:?}!",
+                depth_stencil_image.get_samples(),
+                samples
+            );
+            let depth_stencil_view = depth_stencil_image
+                .get_view()
+                .context("Framebuffer attachments need an image view")?;
+            attachments.push(**depth_stencil_view);
+        }
         let create_info = FramebufferCreateInfo::default()
             .render_pass(***render_pass)
             .attachments(&attachments)
@@ -34,9 +62,10 @@ impl Framebuffer {
 
         Ok(Self {
             framebuffer,
-            logical_device: Rc::clone(logical_device),
-            _image_view: image_view,
-            _render_pass: Rc::clone(render_pass),
+            logical_device: Shared::clone(logical_device),
+            _image: image,
+            _depth_stencil_image: depth_stencil_image.map(Shared::clone),
+            _render_pass: Shared::clone(render_pass),
         })
     }
 }
@@ -1,80 +1,230 @@
+mod blend_mode;
+mod depth_config;
+#[cfg(not(feature = "dynamic_rendering"))]
 mod frame_buffer;
+mod pipeline_config;
 mod pipeline_layout;
+mod rasterization_config;
 mod render_pass;
+mod render_scale;
+mod shader_stages;
+mod specialization;
+mod stencil_config;
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
+#[cfg(feature = "dynamic_rendering")]
+use ash::vk::PipelineRenderingCreateInfo;
 use ash::vk::{
-    ColorComponentFlags, CullModeFlags, FrontFace, GraphicsPipelineCreateInfo, Pipeline,
-    PipelineCache, PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo,
-    PipelineInputAssemblyStateCreateInfo, PipelineMultisampleStateCreateInfo,
-    PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo,
+    CullModeFlags, DynamicState, Extent2D, Format, FrontFace, GraphicsPipelineCreateInfo,
+    ImageAspectFlags, ImageUsageFlags, Pipeline, PipelineCache, PipelineColorBlendStateCreateInfo,
+    PipelineDynamicStateCreateInfo, PipelineInputAssemblyStateCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineTessellationStateCreateInfo,
     PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode,
-    PrimitiveTopology, Rect2D, SampleCountFlags, ShaderModule, ShaderModuleCreateInfo,
-    ShaderStageFlags, Viewport,
+    PrimitiveTopology, SampleCountFlags, ShaderModule, ShaderModuleCreateInfo, ShaderStageFlags,
 };
-use std::{ffi::CStr, ops::Deref, rc::Rc};
+use std::ops::Deref;
 
-use crate::{
-    shaders::{FRAGMENT_SHADER_CODE, VERTEX_SHADER_CODE},
-    LogicalDevice, Swapchain,
-};
+use crate::{Image, ImageDescriptor, LogicalDevice, Swapchain, SwapchainObserver, Vertex};
+
+#[cfg(not(feature = "dynamic_rendering"))]
+use self::frame_buffer::Framebuffer;
+pub(crate) use self::render_scale::ScaledTarget;
 
-use self::{frame_buffer::Framebuffer, pipeline_layout::PipelineLayout, render_pass::RenderPass};
+use crate::Shared;
+
+pub use self::{
+    blend_mode::BlendMode,
+    depth_config::DepthConfig,
+    pipeline_config::PipelineConfig,
+    pipeline_layout::PipelineLayout,
+    rasterization_config::{ConservativeRasterization, DepthBias, RasterizationConfig, Topology},
+    render_pass::{RenderPass, RenderPassBuilder, Subpass},
+    render_scale::RenderScale,
+    shader_stages::{ShaderStages, TessellationShaders},
+    specialization::Specialization,
+    stencil_config::StencilConfig,
+};
 
 pub struct GraphicsPipeline {
-    logical_device: Rc<LogicalDevice>,
+    logical_device: Shared<LogicalDevice>,
     pipeline: Pipeline,
-    render_pass: Rc<RenderPass>,
+    #[cfg(not(feature = "dynamic_rendering"))]
+    render_pass: Shared<RenderPass>,
+    #[cfg(not(feature = "dynamic_rendering"))]
     framebuffers: Vec<Framebuffer>,
+    /// The swapchain images, which are rendered to directly with dynamic rendering
+    #[cfg(feature = "dynamic_rendering")]
+    images: Vec<Shared<Image>>,
+    /// Shared by every frame, if the pipeline was created with a stencil config
+    depth_stencil_image: Option<Shared<Image>>,
+    /// How the depth stencil buffer is tested and cleared, if the pipeline has one
+    depth_config: Option<DepthConfig>,
+    /// The extent drawn at, smaller than the swapchain's below full render scale
+    render_extent: Extent2D,
+    render_scale: RenderScale,
+    /// The swapchain's format, which the pipeline and render pass are built for
+    color_format: Format,
+    /// Drawn to instead of the swapchain images below full render scale
+    scaled_target: Option<ScaledTarget>,
     // references we need to keep to ensure we are cleaned up before
     // they are
     _pipeline_layout: PipelineLayout,
 }
 
 impl GraphicsPipeline {
-    pub fn new(logical_device: &Rc<LogicalDevice>, swapchain: &Swapchain) -> Result<Self> {
-        let shaders = create_shader_modules(logical_device)?;
-        let pipeline_layout = PipelineLayout::new(logical_device)?;
-        let render_pass = Rc::new(RenderPass::new(logical_device, swapchain)?);
+    /// Creates the graphics pipeline, reading vertices laid out as `V` from the bound vertex
+    /// buffer, set up as `config` says. Use `()` when the shaders generate their own
+    /// vertices, as the built in ones do. Errors if the device features or extensions the
+    /// config needs aren't enabled.
+    pub fn new<V: Vertex>(
+        logical_device: &Shared<LogicalDevice>,
+        swapchain: &Swapchain,
+        config: PipelineConfig,
+    ) -> Result<Self> {
+        let PipelineConfig {
+            blend_mode,
+            stencil,
+            stages,
+            render_scale,
+            rasterization,
+            pipeline_cache,
+        } = config;
+        ensure!(
+            stages.geometry.is_none() || logical_device.is_geometry_shader_enabled(),
+            "The geometryShader feature isn't enabled on the device!"
+        );
+        ensure!(
+            stages.tessellation.is_none() || logical_device.is_tessellation_shader_enabled(),
+            "The tessellationShader feature isn't enabled on the device!"
+        );
+        if let Some(conservative) = rasterization.conservative {
+            let max_extra_overestimation_size = logical_device
+                .get_max_extra_primitive_overestimation_size()
+                .ok_or_else(|| {
+                    anyhow!("VK_EXT_conservative_rasterization isn't enabled on the device!")
+                })?;
+            ensure!(
+                (0.0..=max_extra_overestimation_size)
+                    .contains(&conservative.extra_overestimation_size),
+                "The extra overestimation size has to be between 0 and {max_extra_overestimation_size}!"
+            );
+        }
+        // without the primitiveTopologyListRestart feature, lists can't be restarted
+        ensure!(
+            !rasterization.primitive_restart || rasterization.topology.is_strip(),
+            "Primitive restart is only supported for strip topologies, not {:?}!",
+            rasterization.topology
+        );
+        if let Some(depth_bias) = rasterization.depth_bias {
+            ensure!(
+                depth_bias.clamp == 0.0 || logical_device.is_depth_bias_clamp_enabled(),
+                "The depthBiasClamp feature isn't enabled on the device!"
+            );
+        }
+
+        let render_extent = render_scale.scale_extent(*swapchain.get_extent());
+        let scaled_target = if render_scale.is_full() {
+            None
+        } else {
+            Some(ScaledTarget::new(logical_device, swapchain, render_extent)?)
+        };
+        let shaders = create_shader_modules(logical_device, &stages)?;
+        let pipeline_layout = PipelineLayout::new(logical_device, &[])?;
+
+        // the depth stencil buffer has to have as many samples as the color attachment
+        let samples = SampleCountFlags::TYPE_1;
+        let depth_stencil_image = match stencil {
+            Some(_) => Some(create_depth_stencil_image(
+                logical_device,
+                render_extent,
+                samples,
+            )?),
+            None => None,
+        };
+        #[cfg(not(feature = "dynamic_rendering"))]
+        let render_pass = {
+            let color_format = swapchain.get_surface_format().format;
+            let render_pass_builder = match &depth_stencil_image {
+                Some(depth_stencil_image) => RenderPassBuilder::swapchain_color_depth_stencil(
+                    color_format,
+                    depth_stencil_image.get_format(),
+                ),
+                None => RenderPassBuilder::swapchain_color(color_format),
+            };
+            // the offscreen image is scaled up to the swapchain image afterwards
+            let render_pass_builder = match &scaled_target {
+                Some(_) => render_pass_builder.blit_source(),
+                None => render_pass_builder,
+            };
+            Shared::new(render_pass_builder.build(logical_device)?)
+        };
 
-        let shader_entrypoint_name = CStr::from_bytes_with_nul(b"main\0")?;
+        let shader_entrypoint_name = c"main";
+        let specialization_infos = shaders
+            .iter()
+            .map(|(_, shader_stage)| {
+                stages
+                    .get_specialization(*shader_stage)
+                    .map(Specialization::get_specialization_info)
+            })
+            .collect::<Vec<_>>();
         let shader_stage_create_infos = shaders
-            .into_iter()
-            .map(|(shader_module, shader_stage)| {
-                PipelineShaderStageCreateInfo::default()
-                    .stage(shader_stage)
-                    .module(shader_module)
-                    .name(&shader_entrypoint_name)
+            .iter()
+            .zip(&specialization_infos)
+            .map(|((shader_module, shader_stage), specialization_info)| {
+                let shader_stage_create_info = PipelineShaderStageCreateInfo::default()
+                    .stage(*shader_stage)
+                    .module(*shader_module)
+                    .name(shader_entrypoint_name);
+                match specialization_info {
+                    Some(specialization_info) => {
+                        shader_stage_create_info.specialization_info(specialization_info)
+                    }
+                    None => shader_stage_create_info,
+                }
             })
             .collect::<Vec<_>>();
 
-        // we're not using vertex buffers, so just an empty object
-        let pipeline_vertex_input_state_create_info = PipelineVertexInputStateCreateInfo::default();
+        // describe how to read vertices out of the vertex buffer. without any attributes
+        // there's nothing to read, so no binding is needed either
+        let vertex_binding_descriptions = [V::binding_description()];
+        let vertex_attribute_descriptions = V::attribute_descriptions();
+        let mut pipeline_vertex_input_state_create_info =
+            PipelineVertexInputStateCreateInfo::default();
+        if !vertex_attribute_descriptions.is_empty() {
+            pipeline_vertex_input_state_create_info = pipeline_vertex_input_state_create_info
+                .vertex_binding_descriptions(&vertex_binding_descriptions)
+                .vertex_attribute_descriptions(&vertex_attribute_descriptions);
+        }
 
-        // configure the vertexes to be interpreted as a list of triangles
+        // configure the vertexes to be interpreted as the configured primitives (a list of
+        // triangles by default), or as patches when tessellating them
+        let topology = match stages.tessellation {
+            Some(_) => PrimitiveTopology::PATCH_LIST,
+            None => rasterization.topology.get_primitive_topology(),
+        };
         let pipeline_input_assembly_state_create_info =
             PipelineInputAssemblyStateCreateInfo::default()
-                .topology(PrimitiveTopology::TRIANGLE_LIST)
-                .primitive_restart_enable(false);
-
-        // default viewport covering entire swapchain extent, no depth filtering
-        let swapchain_extent = *swapchain.get_extent();
-        let viewport = [Viewport::default()
-            .x(0.0f32)
-            .y(0.0f32)
-            .width(swapchain_extent.width as f32)
-            .height(swapchain_extent.height as f32)
-            .min_depth(0.0f32)
-            .max_depth(1.0f32)];
-
-        // default scissor, doing nothing
-        let scissor = [Rect2D::default().extent(swapchain_extent)];
+                .topology(topology)
+                .primitive_restart_enable(
+                    stages.tessellation.is_none() && rasterization.primitive_restart,
+                );
+        let tessellation_state_create_info = stages.tessellation.map(|tessellation| {
+            PipelineTessellationStateCreateInfo::default()
+                .patch_control_points(tessellation.patch_control_points)
+        });
 
+        // the viewport and scissor are set when recording, covering the render extent, so
+        // the pipeline outlives resizes and only the framebuffers have to be rebuilt
         let viewport_create_info = PipelineViewportStateCreateInfo::default()
-            .viewports(&viewport)
-            .scissors(&scissor);
+            .viewport_count(1)
+            .scissor_count(1);
+        let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
+        let dynamic_state_create_info =
+            PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
 
-        let rasteratization_create_info = PipelineRasterizationStateCreateInfo::default()
+        let mut rasteratization_create_info = PipelineRasterizationStateCreateInfo::default()
             // setting this to false discards points before the near plane or after the far plane
             // setting it to true would instead clamp them
             .depth_clamp_enable(false)
@@ -89,73 +239,220 @@ impl GraphicsPipeline {
             .cull_mode(CullModeFlags::BACK)
             // determines how to know which face is front or back
             // in CLOCKWISE faces composed of verticies traveling in a clockwise direction are front facing
-            .front_face(FrontFace::CLOCKWISE)
-            // disable depth biasing, mainly used for shadow mapping
-            .depth_bias_enable(false);
+            .front_face(FrontFace::CLOCKWISE);
+        // depth biasing is mainly used for shadow mapping and decals
+        rasteratization_create_info = match rasterization.depth_bias {
+            Some(depth_bias) => rasteratization_create_info
+                .depth_bias_enable(true)
+                .depth_bias_constant_factor(depth_bias.constant_factor)
+                .depth_bias_clamp(depth_bias.clamp)
+                .depth_bias_slope_factor(depth_bias.slope_factor),
+            None => rasteratization_create_info.depth_bias_enable(false),
+        };
+        let mut conservative_state = rasterization
+            .conservative
+            .map(|conservative| conservative.get_conservative_state());
+        if let Some(conservative_state) = &mut conservative_state {
+            rasteratization_create_info = rasteratization_create_info.push_next(conservative_state);
+        }
 
         // disable multisampling
         let multisampling_state_create_info = PipelineMultisampleStateCreateInfo::default()
             .sample_shading_enable(false)
-            .rasterization_samples(SampleCountFlags::TYPE_1);
+            .rasterization_samples(samples);
 
-        // settings for color blending per framebuffer. disable this for now, resulting in color output
-        // from vertex shader passing thru
-        let color_blend_attachment_state = [PipelineColorBlendAttachmentState::default()
-            .blend_enable(false)
-            .color_write_mask(ColorComponentFlags::RGBA)];
+        // settings for color blending per framebuffer
+        let color_blend_attachment_state = [blend_mode.get_color_blend_attachment_state()];
 
         // settings for global color blending. disable this as well.
         let pipeline_color_blend_state = PipelineColorBlendStateCreateInfo::default()
             .logic_op_enable(false)
             .attachments(&color_blend_attachment_state);
 
-        let graphics_pipeline_create_info = [GraphicsPipelineCreateInfo::default()
+        let depth_stencil_state = stencil.map(|stencil| stencil.get_depth_stencil_state());
+
+        let mut graphics_pipeline_create_info = GraphicsPipelineCreateInfo::default()
             .stages(&shader_stage_create_infos)
             .vertex_input_state(&pipeline_vertex_input_state_create_info)
             .input_assembly_state(&pipeline_input_assembly_state_create_info)
-            .render_pass(**render_pass)
             .color_blend_state(&pipeline_color_blend_state)
             .multisample_state(&multisampling_state_create_info)
             .viewport_state(&viewport_create_info)
+            .dynamic_state(&dynamic_state_create_info)
             .rasterization_state(&rasteratization_create_info)
-            .layout(*pipeline_layout)];
+            .layout(*pipeline_layout);
+        if let Some(depth_stencil_state) = &depth_stencil_state {
+            graphics_pipeline_create_info =
+                graphics_pipeline_create_info.depth_stencil_state(depth_stencil_state);
+        }
+        if let Some(tessellation_state_create_info) = &tessellation_state_create_info {
+            graphics_pipeline_create_info =
+                graphics_pipeline_create_info.tessellation_state(tessellation_state_create_info);
+        }
+        #[cfg(not(feature = "dynamic_rendering"))]
+        let graphics_pipeline_create_info =
+            graphics_pipeline_create_info.render_pass(**render_pass);
+        // without a render pass, the pipeline is told the attachment formats directly
+        #[cfg(feature = "dynamic_rendering")]
+        let color_attachment_formats = [swapchain.get_surface_format().format];
+        #[cfg(feature = "dynamic_rendering")]
+        let mut pipeline_rendering_create_info = {
+            let depth_stencil_format = depth_stencil_image
+                .as_ref()
+                .map_or(Format::UNDEFINED, |image| image.get_format());
+            PipelineRenderingCreateInfo::default()
+                .color_attachment_formats(&color_attachment_formats)
+                .depth_attachment_format(depth_stencil_format)
+                .stencil_attachment_format(depth_stencil_format)
+        };
+        #[cfg(feature = "dynamic_rendering")]
+        let graphics_pipeline_create_info =
+            graphics_pipeline_create_info.push_next(&mut pipeline_rendering_create_info);
 
         let graphics_pipeline = unsafe {
             logical_device.create_graphics_pipelines(
-                PipelineCache::null(),
-                &graphics_pipeline_create_info,
+                pipeline_cache.map_or(PipelineCache::null(), |pipeline_cache| **pipeline_cache),
+                &[graphics_pipeline_create_info],
                 None,
             )
         }
         .map_err(|(_, r)| r)?;
+        logical_device.set_object_name(graphics_pipeline[0], "graphics pipeline")?;
 
         for (shader_module, _) in shaders {
             unsafe { logical_device.destroy_shader_module(shader_module, None) }
         }
 
-        let framebuffers = swapchain
-            .create_image_views(logical_device)?
-            .into_iter()
-            .map(|image_view| {
-                Framebuffer::new(logical_device, &render_pass, &swapchain_extent, image_view)
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        #[cfg(not(feature = "dynamic_rendering"))]
+        let framebuffers = create_framebuffers(
+            logical_device,
+            &render_pass,
+            swapchain,
+            render_extent,
+            scaled_target.as_ref(),
+            depth_stencil_image.as_ref(),
+        )?;
 
         Ok(Self {
-            logical_device: Rc::clone(logical_device),
+            logical_device: Shared::clone(logical_device),
             pipeline: graphics_pipeline[0],
             _pipeline_layout: pipeline_layout,
+            #[cfg(not(feature = "dynamic_rendering"))]
             render_pass,
+            #[cfg(not(feature = "dynamic_rendering"))]
             framebuffers,
+            #[cfg(feature = "dynamic_rendering")]
+            images: swapchain.get_images().to_vec(),
+            depth_stencil_image,
+            depth_config: stencil.map(|stencil| stencil.depth),
+            render_extent,
+            render_scale,
+            color_format: swapchain.get_surface_format().format,
+            scaled_target,
         })
     }
 
+    /// Rebuilds everything sized to the swapchain's images after it has been recreated,
+    /// i.e. the framebuffers (or the images drawn to directly with dynamic rendering), the
+    /// depth stencil buffer and the offscreen image below full render scale. The pipeline
+    /// itself, its layout and render pass are kept, which is much cheaper than creating a
+    /// new pipeline. Errors if the swapchain's format changed, in which case the pipeline
+    /// has to be recreated. None of the old framebuffers may still be in use.
+    pub fn recreate_framebuffers(&mut self, swapchain: &Swapchain) -> Result<()> {
+        ensure!(
+            swapchain.get_surface_format().format == self.color_format,
+            "The swapchain's format changed, so the pipeline has to be recreated!"
+        );
+        // the old framebuffers go before the images they're built from
+        #[cfg(not(feature = "dynamic_rendering"))]
+        self.framebuffers.clear();
+        #[cfg(feature = "dynamic_rendering")]
+        self.images.clear();
+
+        self.render_extent = self.render_scale.scale_extent(*swapchain.get_extent());
+        self.scaled_target = if self.render_scale.is_full() {
+            None
+        } else {
+            Some(ScaledTarget::new(
+                &self.logical_device,
+                swapchain,
+                self.render_extent,
+            )?)
+        };
+        self.depth_stencil_image = match &self.depth_stencil_image {
+            Some(depth_stencil_image) => Some(create_depth_stencil_image(
+                &self.logical_device,
+                self.render_extent,
+                depth_stencil_image.get_samples(),
+            )?),
+            None => None,
+        };
+
+        #[cfg(not(feature = "dynamic_rendering"))]
+        {
+            self.framebuffers = create_framebuffers(
+                &self.logical_device,
+                &self.render_pass,
+                swapchain,
+                self.render_extent,
+                self.scaled_target.as_ref(),
+                self.depth_stencil_image.as_ref(),
+            )?;
+        }
+        #[cfg(feature = "dynamic_rendering")]
+        {
+            self.images = swapchain.get_images().to_vec();
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "dynamic_rendering"))]
     pub fn get_render_pass(&self) -> &RenderPass {
         &self.render_pass
     }
 
+    /// Returns the framebuffer drawn to for the swapchain image with the given index.
+    /// Below full render scale every index shares the offscreen image's framebuffer.
+    #[cfg(not(feature = "dynamic_rendering"))]
     pub fn get_framebuffer_for_index(&self, idx: usize) -> &Framebuffer {
-        &self.framebuffers[idx]
+        match self.scaled_target {
+            Some(_) => &self.framebuffers[0],
+            None => &self.framebuffers[idx],
+        }
+    }
+
+    /// Returns the swapchain image with the given index, which is rendered to directly
+    #[cfg(feature = "dynamic_rendering")]
+    pub fn get_image_for_index(&self, idx: usize) -> &Image {
+        &self.images[idx]
+    }
+
+    /// Returns the extent the pipeline draws at, which is the swapchain's scaled by the
+    /// render scale
+    pub fn get_render_extent(&self) -> Extent2D {
+        self.render_extent
+    }
+
+    pub(crate) fn get_scaled_target(&self) -> Option<&ScaledTarget> {
+        self.scaled_target.as_ref()
+    }
+
+    /// Returns how the depth stencil buffer is tested and cleared, if the pipeline was
+    /// created with a stencil config
+    pub fn get_depth_config(&self) -> Option<DepthConfig> {
+        self.depth_config
+    }
+
+    /// Returns the depth stencil buffer, if the pipeline was created with a stencil config
+    pub fn get_depth_stencil_image(&self) -> Option<&Image> {
+        self.depth_stencil_image.as_deref()
+    }
+}
+
+/// Rebuilds the framebuffers, see [`GraphicsPipeline::recreate_framebuffers`]
+impl SwapchainObserver for GraphicsPipeline {
+    fn on_swapchain_recreated(&mut self, swapchain: &Swapchain) -> Result<()> {
+        self.recreate_framebuffers(swapchain)
     }
 }
 
@@ -173,33 +470,124 @@ impl Deref for GraphicsPipeline {
     }
 }
 
+/// Creates the depth stencil buffer drawn to at the render extent. A single one is shared
+/// by every framebuffer, since only one frame draws at a time.
+fn create_depth_stencil_image(
+    logical_device: &Shared<LogicalDevice>,
+    render_extent: Extent2D,
+    samples: SampleCountFlags,
+) -> Result<Shared<Image>> {
+    let depth_stencil_format = logical_device.find_depth_stencil_format()?;
+    let depth_stencil_image = Image::new(
+        logical_device,
+        &ImageDescriptor {
+            format: depth_stencil_format,
+            extent: render_extent,
+            // copied from when reading back depth values
+            usage: ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+            aspect: ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL,
+            samples,
+            ..Default::default()
+        },
+    )?;
+    logical_device.set_object_name(*depth_stencil_image, "depth stencil image")?;
+    Ok(Shared::new(depth_stencil_image))
+}
+
+/// Creates a framebuffer per swapchain image, or below full render scale a single one
+/// for the offscreen image every frame draws to
+#[cfg(not(feature = "dynamic_rendering"))]
+fn create_framebuffers(
+    logical_device: &Shared<LogicalDevice>,
+    render_pass: &Shared<RenderPass>,
+    swapchain: &Swapchain,
+    render_extent: Extent2D,
+    scaled_target: Option<&ScaledTarget>,
+    depth_stencil_image: Option<&Shared<Image>>,
+) -> Result<Vec<Framebuffer>> {
+    let images = swapchain.get_images();
+    let framebuffers = (0..framebuffer_count(images.len(), scaled_target.is_some()))
+        .map(|idx| {
+            let image = match scaled_target {
+                Some(scaled_target) => scaled_target.get_color_image(),
+                None => &images[idx],
+            };
+            Framebuffer::new(
+                logical_device,
+                render_pass,
+                &render_extent,
+                Shared::clone(image),
+                depth_stencil_image,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (idx, framebuffer) in framebuffers.iter().enumerate() {
+        logical_device.set_object_name(**framebuffer, &format!("framebuffer {idx}"))?;
+    }
+    Ok(framebuffers)
+}
+
+/// Returns how many framebuffers [`create_framebuffers`] builds for a swapchain with
+/// `image_count` images: one per image, or one for the offscreen image when `scaled`
+#[cfg(not(feature = "dynamic_rendering"))]
+fn framebuffer_count(image_count: usize, scaled: bool) -> usize {
+    if scaled {
+        1
+    } else {
+        image_count
+    }
+}
+
 /// Creates the shader modules and their associated pipeline create infos for use
 /// in creating the graphics pipeline
-fn create_shader_modules<'a>(
-    logical_device: &Rc<LogicalDevice>,
-) -> Result<[(ShaderModule, ShaderStageFlags); 2]> {
-    let vertex_shader_code = VERTEX_SHADER_CODE;
-    ensure!(
-        vertex_shader_code.len() % 4 == 0,
-        "Invalid vertex shader code read!"
-    );
-    let vertex_shader_module = create_shader_module(logical_device, vertex_shader_code)?;
-
-    let fragment_shader_code = FRAGMENT_SHADER_CODE;
-    ensure!(
-        fragment_shader_code.len() % 4 == 0,
-        "Invalid fragment shader code read!"
-    );
-    let fragment_shader_module = create_shader_module(logical_device, fragment_shader_code)?;
-
-    Ok([
-        (vertex_shader_module, ShaderStageFlags::VERTEX),
-        (fragment_shader_module, ShaderStageFlags::FRAGMENT),
-    ])
+fn create_shader_modules(
+    logical_device: &Shared<LogicalDevice>,
+    stages: &ShaderStages,
+) -> Result<Vec<(ShaderModule, ShaderStageFlags)>> {
+    let mut stage_codes = vec![(stages.vertex, ShaderStageFlags::VERTEX, "vertex")];
+    if let Some(tessellation) = stages.tessellation {
+        stage_codes.push((
+            tessellation.control,
+            ShaderStageFlags::TESSELLATION_CONTROL,
+            "tessellation control",
+        ));
+        stage_codes.push((
+            tessellation.evaluation,
+            ShaderStageFlags::TESSELLATION_EVALUATION,
+            "tessellation evaluation",
+        ));
+    }
+    if let Some(geometry) = stages.geometry {
+        stage_codes.push((geometry, ShaderStageFlags::GEOMETRY, "geometry"));
+    }
+    stage_codes.push((stages.fragment, ShaderStageFlags::FRAGMENT, "fragment"));
+
+    let mut shaders = Vec::with_capacity(stage_codes.len());
+    for (code, stage, stage_name) in stage_codes {
+        let shader_module = if code.len().is_multiple_of(4) {
+            create_shader_module(logical_device, code)
+        } else {
+            Err(anyhow!("Invalid {stage_name} shader code read!"))
+        };
+        match shader_module {
+            Ok(shader_module) => shaders.push((shader_module, stage)),
+            Err(err) => {
+                // don't leak the modules created before the failing one
+                for (shader_module, _) in shaders {
+                    unsafe { logical_device.destroy_shader_module(shader_module, None) }
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(shaders)
 }
 
 /// Reads in the raw bytes and creates a shader module from the read byte code
-fn create_shader_module(logical_device: &Rc<LogicalDevice>, code: &[u8]) -> Result<ShaderModule> {
+pub fn create_shader_module(
+    logical_device: &Shared<LogicalDevice>,
+    code: &[u8],
+) -> Result<ShaderModule> {
     let code = code
         .chunks_exact(4)
         .map(|chunks| {
@@ -212,3 +600,22 @@ fn create_shader_module(logical_device: &Rc<LogicalDevice>, code: &[u8]) -> Resu
         unsafe { logical_device.create_shader_module(&shader_module_create_info, None)? };
     Ok(shader_module)
 }
+
+#[cfg(all(test, not(feature = "dynamic_rendering")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_framebuffer_per_image_at_full_scale() {
+        for image_count in 1..5 {
+            assert_eq!(framebuffer_count(image_count, false), image_count);
+        }
+    }
+
+    #[test]
+    fn one_framebuffer_below_full_scale() {
+        for image_count in 1..5 {
+            assert_eq!(framebuffer_count(image_count, true), 1);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::{BlendMode, RasterizationConfig, RenderScale, ShaderStages, StencilConfig};
+use crate::PipelineCache;
+
+/// Everything about a [`super::GraphicsPipeline`] that can be configured, in any
+/// combination. The default draws opaquely with the built in shaders, straight to the
+/// swapchain images and without a depth stencil buffer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PipelineConfig<'a> {
+    /// How the output colors are combined with the framebuffer's
+    pub blend_mode: BlendMode,
+    /// Renders into a depth stencil buffer, tested and updated as configured, if set. The
+    /// buffer is cleared to the config's depth clear value (1.0 by default) and a stencil
+    /// value of 0 at the start of every frame.
+    pub stencil: Option<StencilConfig>,
+    /// The shaders run and how they're specialized, see [`ShaderStages`]
+    pub stages: ShaderStages<'a>,
+    /// The fraction of the swapchain's resolution drawn at, see [`RenderScale`]. Below
+    /// full scale the swapchain's images have to support being blitted to.
+    pub render_scale: RenderScale,
+    /// How primitives are assembled and rasterized, see [`RasterizationConfig`]
+    pub rasterization: RasterizationConfig,
+    /// Speeds up creating the pipeline, if it's in the cache, see [`PipelineCache`]
+    pub pipeline_cache: Option<&'a PipelineCache>,
+}
+
+impl<'a> PipelineConfig<'a> {
+    /// Combines output colors with the framebuffer's as `blend_mode` says
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Tests and updates a depth stencil buffer as `stencil` configures
+    pub fn with_stencil(mut self, stencil: StencilConfig) -> Self {
+        self.stencil = Some(stencil);
+        self
+    }
+
+    /// Runs the shaders in `stages`
+    pub fn with_stages(mut self, stages: ShaderStages<'a>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Draws at the fraction of the swapchain's resolution `render_scale` gives
+    pub fn with_render_scale(mut self, render_scale: RenderScale) -> Self {
+        self.render_scale = render_scale;
+        self
+    }
+
+    /// Assembles and rasterizes primitives as `rasterization` configures
+    pub fn with_rasterization(mut self, rasterization: RasterizationConfig) -> Self {
+        self.rasterization = rasterization;
+        self
+    }
+
+    /// Looks up and stores the compiled pipeline in `pipeline_cache`
+    pub fn with_pipeline_cache(mut self, pipeline_cache: &'a PipelineCache) -> Self {
+        self.pipeline_cache = Some(pipeline_cache);
+        self
+    }
+}
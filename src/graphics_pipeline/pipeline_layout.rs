@@ -1,22 +1,38 @@
-use std::{ops::Deref, rc::Rc};
+use std::ops::Deref;
 
-use crate::LogicalDevice;
+use crate::{LogicalDevice, Shared};
 use anyhow::Result;
-use ash::vk::{self, PipelineLayoutCreateInfo};
+use ash::vk::{self, DescriptorSetLayout, PipelineLayoutCreateInfo, PushConstantRange};
 
 pub struct PipelineLayout {
-    logical_device: Rc<LogicalDevice>,
+    logical_device: Shared<LogicalDevice>,
     layout: vk::PipelineLayout,
 }
 
 impl PipelineLayout {
-    pub fn new(logical_device: &Rc<LogicalDevice>) -> Result<Self> {
-        let pipeline_layout_create_info = PipelineLayoutCreateInfo::default();
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        descriptor_set_layouts: &[DescriptorSetLayout],
+    ) -> Result<Self> {
+        Self::with_push_constants(logical_device, descriptor_set_layouts, &[])
+    }
+
+    /// Like [`PipelineLayout::new`], also giving the pipeline push constants, for small
+    /// per-draw values like a mesh's world transform
+    pub fn with_push_constants(
+        logical_device: &Shared<LogicalDevice>,
+        descriptor_set_layouts: &[DescriptorSetLayout],
+        push_constant_ranges: &[PushConstantRange],
+    ) -> Result<Self> {
+        let pipeline_layout_create_info = PipelineLayoutCreateInfo::default()
+            .set_layouts(descriptor_set_layouts)
+            .push_constant_ranges(push_constant_ranges);
         let pipeline_layout =
             unsafe { logical_device.create_pipeline_layout(&pipeline_layout_create_info, None)? };
+        logical_device.set_object_name(pipeline_layout, "pipeline layout")?;
 
         Ok(Self {
-            logical_device: Rc::clone(logical_device),
+            logical_device: Shared::clone(logical_device),
             layout: pipeline_layout,
         })
     }
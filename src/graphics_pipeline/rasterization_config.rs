@@ -0,0 +1,122 @@
+use ash::vk::{
+    ConservativeRasterizationModeEXT, PipelineRasterizationConservativeStateCreateInfoEXT,
+    PrimitiveTopology,
+};
+
+/// How a pipeline assembles vertices into primitives and turns those into fragments,
+/// beyond what's fixed for every pipeline. The default reads a list of triangles and
+/// rasterizes the fragments whose centers they cover.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RasterizationConfig {
+    /// How vertices are assembled into primitives. Ignored when tessellating, which
+    /// always reads patches.
+    pub topology: Topology,
+    /// Whether an index of all ones (`u32::MAX` for the `u32` indices meshes use) ends
+    /// the current strip and starts a new one, so several strips can be drawn with a
+    /// single indexed draw. Only allowed with strip topologies.
+    pub primitive_restart: bool,
+    /// Rasterizes conservatively, if set, see [`ConservativeRasterization`]
+    pub conservative: Option<ConservativeRasterization>,
+    /// Offsets the depth of every fragment, if set, see [`DepthBias`]
+    pub depth_bias: Option<DepthBias>,
+}
+
+impl RasterizationConfig {
+    /// Assembles vertices as `topology` describes
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Reads strips of `topology`, which are restarted wherever the index buffer holds
+    /// the sentinel index, see [`RasterizationConfig::primitive_restart`]
+    pub fn with_primitive_restart(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self.primitive_restart = true;
+        self
+    }
+
+    /// Rasterizes conservatively, as `conservative` configures
+    pub fn with_conservative(mut self, conservative: ConservativeRasterization) -> Self {
+        self.conservative = Some(conservative);
+        self
+    }
+
+    /// Offsets fragment depths as `depth_bias` configures
+    pub fn with_depth_bias(mut self, depth_bias: DepthBias) -> Self {
+        self.depth_bias = Some(depth_bias);
+        self
+    }
+}
+
+/// How vertices are assembled into primitives
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    /// Every 3 vertices make a separate triangle
+    #[default]
+    TriangleList,
+    /// Every vertex makes a triangle with the 2 before it, so `n` triangles only take
+    /// `n + 2` vertices, as in terrain grids
+    TriangleStrip,
+    /// Every 2 vertices make a separate line
+    LineList,
+    /// Every vertex makes a line with the one before it
+    LineStrip,
+    /// Every vertex is a separate point
+    PointList,
+}
+
+impl Topology {
+    pub fn get_primitive_topology(&self) -> PrimitiveTopology {
+        match self {
+            Topology::TriangleList => PrimitiveTopology::TRIANGLE_LIST,
+            Topology::TriangleStrip => PrimitiveTopology::TRIANGLE_STRIP,
+            Topology::LineList => PrimitiveTopology::LINE_LIST,
+            Topology::LineStrip => PrimitiveTopology::LINE_STRIP,
+            Topology::PointList => PrimitiveTopology::POINT_LIST,
+        }
+    }
+
+    /// Whether the topology is made of strips, which primitive restart can split
+    pub fn is_strip(&self) -> bool {
+        matches!(self, Topology::TriangleStrip | Topology::LineStrip)
+    }
+}
+
+/// Pushes fragments' depths away from the viewer by a constant amount plus an amount
+/// growing with the polygon's slope, so geometry drawn at the same depth as other
+/// geometry wins (or loses) the depth test consistently. Used against shadow acne when
+/// rendering shadow maps, and to keep decals from z-fighting with the surface under them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    /// A constant added to each fragment's depth, in units of the smallest resolvable
+    /// depth difference
+    pub constant_factor: f32,
+    /// The largest (or, if negative, smallest) bias added. 0 leaves it unclamped;
+    /// anything else requires the depthBiasClamp device feature.
+    pub clamp: f32,
+    /// Scales the depth slope of the polygon, added to each fragment's depth
+    pub slope_factor: f32,
+}
+
+/// Generates a fragment for every pixel a primitive touches, even partially, instead of
+/// only those whose center it covers. Used for voxelization and collision tests, where
+/// thin or small primitives must not fall between pixel centers. Requires
+/// `VK_EXT_conservative_rasterization`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConservativeRasterization {
+    /// How much further, in pixels, primitives are grown on top of the device's own
+    /// overestimation. Can be at most
+    /// [`crate::LogicalDevice::get_max_extra_primitive_overestimation_size`].
+    pub extra_overestimation_size: f32,
+}
+
+impl ConservativeRasterization {
+    pub(crate) fn get_conservative_state(
+        &self,
+    ) -> PipelineRasterizationConservativeStateCreateInfoEXT<'static> {
+        PipelineRasterizationConservativeStateCreateInfoEXT::default()
+            .conservative_rasterization_mode(ConservativeRasterizationModeEXT::OVERESTIMATE)
+            .extra_primitive_overestimation_size(self.extra_overestimation_size)
+    }
+}
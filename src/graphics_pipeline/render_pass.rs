@@ -1,24 +1,72 @@
-use std::{ops::Deref, rc::Rc};
+use std::ops::Deref;
 
-use crate::{LogicalDevice, Swapchain};
+use crate::{LogicalDevice, Shared, Swapchain};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::vk::{
     self, AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference,
-    AttachmentStoreOp, ImageLayout, PipelineBindPoint, PipelineStageFlags, RenderPassCreateInfo,
-    SampleCountFlags, SubpassDependency, SubpassDescription, SUBPASS_EXTERNAL,
+    AttachmentStoreOp, DependencyFlags, Format, ImageLayout, PipelineBindPoint, PipelineStageFlags,
+    RenderPassCreateInfo, SampleCountFlags, SubpassDependency, SubpassDescription,
+    ATTACHMENT_UNUSED, SUBPASS_EXTERNAL,
 };
 
 pub struct RenderPass {
-    logical_device: Rc<LogicalDevice>,
+    logical_device: Shared<LogicalDevice>,
     render_pass: vk::RenderPass,
+    /// How many samples each subpass rasterizes with
+    sample_counts: Vec<SampleCountFlags>,
 }
 
 impl RenderPass {
-    pub fn new(logical_device: &Rc<LogicalDevice>, swapchain: &Swapchain) -> Result<Self> {
-        let attachment_description = [AttachmentDescription::default()
+    /// Creates a render pass with a single color attachment matching the swapchain, see
+    /// [`RenderPassBuilder::swapchain_color`]
+    pub fn new(logical_device: &Shared<LogicalDevice>, swapchain: &Swapchain) -> Result<Self> {
+        RenderPassBuilder::swapchain_color(swapchain.get_surface_format().format)
+            .build(logical_device)
+    }
+
+    /// Creates a render pass with a color attachment matching the swapchain and a depth
+    /// stencil attachment of the given format, see
+    /// [`RenderPassBuilder::swapchain_color_depth_stencil`]
+    pub fn with_depth_stencil(
+        logical_device: &Shared<LogicalDevice>,
+        swapchain: &Swapchain,
+        depth_stencil_format: Format,
+    ) -> Result<Self> {
+        RenderPassBuilder::swapchain_color_depth_stencil(
+            swapchain.get_surface_format().format,
+            depth_stencil_format,
+        )
+        .build(logical_device)
+    }
+
+    /// Returns how many samples the subpass's color and depth attachments have, which
+    /// pipelines drawing in it have to rasterize with
+    pub fn get_sample_count(&self, subpass: usize) -> SampleCountFlags {
+        self.sample_counts[subpass]
+    }
+}
+
+/// Builds up a render pass's attachments, subpasses and dependencies incrementally.
+/// Attachments are referred to by the index they were added at.
+#[derive(Clone, Debug, Default)]
+pub struct RenderPassBuilder {
+    attachments: Vec<AttachmentDescription>,
+    subpasses: Vec<Subpass>,
+    dependencies: Vec<SubpassDependency>,
+}
+
+impl RenderPassBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A render pass with a single subpass drawing to one color attachment of the
+    /// given format, which is cleared beforehand and presented afterwards
+    pub fn swapchain_color(format: Format) -> Self {
+        let attachment_description = AttachmentDescription::default()
             // ensure attachment format matches that of swapchain
-            .format(swapchain.get_surface_format().format)
+            .format(format)
             // not using multisampling, so stick to 1 sample
             .samples(SampleCountFlags::TYPE_1)
             // clear the data in the attachment before rendering
@@ -32,37 +80,599 @@ impl RenderPass {
             .final_layout(ImageLayout::PRESENT_SRC_KHR)
             // not using stencils
             .stencil_load_op(AttachmentLoadOp::DONT_CARE)
-            .stencil_store_op(AttachmentStoreOp::DONT_CARE)];
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE);
+
+        let subpass = Subpass::new(PipelineBindPoint::GRAPHICS).add_color_attachment(
+            AttachmentReference::default()
+                .attachment(0)
+                .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+        );
 
-        let attachment_ref = [AttachmentReference::default()
-            .attachment(0)
-            .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+        let subpass_dependency = SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(AccessFlags::empty())
+            .dst_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE);
+
+        Self::new()
+            .add_attachment(attachment_description)
+            .add_subpass(subpass)
+            .add_dependency(subpass_dependency)
+    }
+
+    /// Like [`RenderPassBuilder::swapchain_color`], with a depth stencil attachment of the
+    /// given format at index 1. Depth and stencil are both cleared beforehand. Depth is
+    /// stored afterwards, so it can be read back, and stencil is discarded.
+    pub fn swapchain_color_depth_stencil(
+        color_format: Format,
+        depth_stencil_format: Format,
+    ) -> Self {
+        let color_attachment_description = AttachmentDescription::default()
+            .format(color_format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .store_op(AttachmentStoreOp::STORE)
+            .final_layout(ImageLayout::PRESENT_SRC_KHR)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE);
+
+        let depth_stencil_attachment_description = AttachmentDescription::default()
+            .format(depth_stencil_format)
+            .samples(SampleCountFlags::TYPE_1)
+            // start every frame with a clear depth and stencil buffer. depth is kept for
+            // reading back after the pass, but nothing reads the stencil
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::CLEAR)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
 
-        let subpass_description = [SubpassDescription::default()
-            .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
-            .color_attachments(&attachment_ref)];
+        let subpass = Subpass::new(PipelineBindPoint::GRAPHICS)
+            .add_color_attachment(
+                AttachmentReference::default()
+                    .attachment(0)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            )
+            .depth_stencil_attachment(
+                AttachmentReference::default()
+                    .attachment(1)
+                    .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            );
 
-        let subpass_dependencies = [SubpassDependency::default()
+        // the depth stencil buffer is shared between frames, so also wait for the
+        // previous frame's depth and stencil tests before clearing it
+        let subpass_dependency = SubpassDependency::default()
             .src_subpass(SUBPASS_EXTERNAL)
             .dst_subpass(0)
+            .src_stage_mask(
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            )
+            .src_access_mask(AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .dst_stage_mask(
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            )
+            .dst_access_mask(
+                AccessFlags::COLOR_ATTACHMENT_WRITE | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+
+        Self::new()
+            .add_attachment(color_attachment_description)
+            .add_attachment(depth_stencil_attachment_description)
+            .add_subpass(subpass)
+            .add_dependency(subpass_dependency)
+    }
+
+    /// A render pass drawing only depth, into a single attachment of the given format which
+    /// is cleared beforehand and left ready for fragment shaders to sample afterwards, as
+    /// for a shadow map
+    pub fn depth_only(depth_format: Format) -> Self {
+        let attachment_description = AttachmentDescription::default()
+            .format(depth_format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+
+        let subpass = Subpass::new(PipelineBindPoint::GRAPHICS).depth_stencil_attachment(
+            AttachmentReference::default()
+                .attachment(0)
+                .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+        );
+
+        // the previous frame's fragment shaders have to be done sampling the depth before
+        // it's cleared, and this frame's have to wait for it to be written
+        let before_dependency = SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(PipelineStageFlags::FRAGMENT_SHADER)
+            .src_access_mask(AccessFlags::SHADER_READ)
+            .dst_stage_mask(PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+            .dst_access_mask(AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE);
+        let after_dependency = SubpassDependency::default()
+            .src_subpass(0)
+            .dst_subpass(SUBPASS_EXTERNAL)
+            .src_stage_mask(PipelineStageFlags::LATE_FRAGMENT_TESTS)
+            .src_access_mask(AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .dst_stage_mask(PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(AccessFlags::SHADER_READ);
+
+        Self::new()
+            .add_attachment(attachment_description)
+            .add_subpass(subpass)
+            .add_dependency(before_dependency)
+            .add_dependency(after_dependency)
+    }
+
+    /// A render pass drawing to a multisampled color attachment (index 0) with `samples`
+    /// samples per pixel, resolved at the end of the subpass into a single sampled color
+    /// attachment (index 1) left ready for fragment shaders to sample, so a post
+    /// processing pass can read the anti-aliased image. With a depth format, a depth
+    /// attachment with as many samples is added at index 2. Only the resolved image is
+    /// stored; the resolve image should be created with `SAMPLED` usage.
+    pub fn msaa_resolve(
+        color_format: Format,
+        samples: SampleCountFlags,
+        depth_format: Option<Format>,
+    ) -> Self {
+        let multisampled_attachment_description = AttachmentDescription::default()
+            .format(color_format)
+            .samples(samples)
+            .load_op(AttachmentLoadOp::CLEAR)
+            // the samples are only needed until they're resolved
+            .store_op(AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let resolve_attachment_description = AttachmentDescription::default()
+            .format(color_format)
+            .samples(SampleCountFlags::TYPE_1)
+            // every pixel is overwritten by the resolve
+            .load_op(AttachmentLoadOp::DONT_CARE)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        let mut subpass = Subpass::new(PipelineBindPoint::GRAPHICS)
+            .add_color_attachment(
+                AttachmentReference::default()
+                    .attachment(0)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            )
+            .add_resolve_attachment(
+                AttachmentReference::default()
+                    .attachment(1)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            );
+        if depth_format.is_some() {
+            subpass = subpass.depth_stencil_attachment(
+                AttachmentReference::default()
+                    .attachment(2)
+                    .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            );
+        }
+
+        // the previous frame's post pass has to be done sampling the resolved image before
+        // it's overwritten, and this frame's has to wait for the resolve. resolves happen
+        // in the color output stage
+        let before_dependency = SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(
+                PipelineStageFlags::FRAGMENT_SHADER | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            )
+            .src_access_mask(AccessFlags::empty())
+            .dst_stage_mask(
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            )
+            .dst_access_mask(
+                AccessFlags::COLOR_ATTACHMENT_WRITE | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+        let after_dependency = SubpassDependency::default()
+            .src_subpass(0)
+            .dst_subpass(SUBPASS_EXTERNAL)
+            .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(AccessFlags::SHADER_READ);
+
+        let mut render_pass_builder = Self::new()
+            .add_attachment(multisampled_attachment_description)
+            .add_attachment(resolve_attachment_description);
+        if let Some(depth_format) = depth_format {
+            render_pass_builder = render_pass_builder.add_attachment(
+                AttachmentDescription::default()
+                    .format(depth_format)
+                    .samples(samples)
+                    .load_op(AttachmentLoadOp::CLEAR)
+                    .store_op(AttachmentStoreOp::DONT_CARE)
+                    .stencil_load_op(AttachmentLoadOp::CLEAR)
+                    .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(ImageLayout::UNDEFINED)
+                    .final_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            );
+        }
+        render_pass_builder
+            .add_subpass(subpass)
+            .add_dependency(before_dependency)
+            .add_dependency(after_dependency)
+    }
+
+    /// A render pass for deferred shading in two subpasses. The first fills the G-buffer:
+    /// albedo (attachment 1) and normals (attachment 2) as color attachments and depth
+    /// (attachment 3). The second reads all three back as input attachments, in that
+    /// order, to light the swapchain image (attachment 0). The G-buffer only lives for the
+    /// length of the pass, so isn't stored.
+    pub fn deferred(
+        color_format: Format,
+        albedo_format: Format,
+        normal_format: Format,
+        depth_format: Format,
+    ) -> Self {
+        let color_attachment_description = AttachmentDescription::default()
+            .format(color_format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .store_op(AttachmentStoreOp::STORE)
+            .final_layout(ImageLayout::PRESENT_SRC_KHR)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE);
+        let g_buffer_attachment_description = |format| {
+            AttachmentDescription::default()
+                .format(format)
+                .samples(SampleCountFlags::TYPE_1)
+                .load_op(AttachmentLoadOp::CLEAR)
+                .store_op(AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+                .initial_layout(ImageLayout::UNDEFINED)
+        };
+        let albedo_attachment_description = g_buffer_attachment_description(albedo_format)
+            .final_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        let normal_attachment_description = g_buffer_attachment_description(normal_format)
+            .final_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        let depth_attachment_description = g_buffer_attachment_description(depth_format)
+            .final_layout(ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+
+        let geometry_subpass = Subpass::new(PipelineBindPoint::GRAPHICS)
+            .add_color_attachment(
+                AttachmentReference::default()
+                    .attachment(1)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            )
+            .add_color_attachment(
+                AttachmentReference::default()
+                    .attachment(2)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            )
+            .depth_stencil_attachment(
+                AttachmentReference::default()
+                    .attachment(3)
+                    .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            );
+        let lighting_subpass = Subpass::new(PipelineBindPoint::GRAPHICS)
+            .add_input_attachment(
+                AttachmentReference::default()
+                    .attachment(1)
+                    .layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            )
+            .add_input_attachment(
+                AttachmentReference::default()
+                    .attachment(2)
+                    .layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            )
+            .add_input_attachment(
+                AttachmentReference::default()
+                    .attachment(3)
+                    .layout(ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL),
+            )
+            .add_color_attachment(
+                AttachmentReference::default()
+                    .attachment(0)
+                    .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            );
+
+        // the G-buffer is shared between frames, so wait for the previous frame to be
+        // done with it before clearing it
+        let geometry_dependency = SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | PipelineStageFlags::FRAGMENT_SHADER,
+            )
+            .src_access_mask(AccessFlags::empty())
+            .dst_stage_mask(
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            )
+            .dst_access_mask(
+                AccessFlags::COLOR_ATTACHMENT_WRITE | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+        // lighting reads the G-buffer at the same pixel it was written, so it only has to
+        // wait per region
+        let lighting_dependency = SubpassDependency::default()
+            .src_subpass(0)
+            .dst_subpass(1)
+            .src_stage_mask(
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            )
+            .src_access_mask(
+                AccessFlags::COLOR_ATTACHMENT_WRITE | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            )
+            .dst_stage_mask(PipelineStageFlags::FRAGMENT_SHADER)
+            .dst_access_mask(AccessFlags::INPUT_ATTACHMENT_READ)
+            .dependency_flags(DependencyFlags::BY_REGION);
+        let color_dependency = SubpassDependency::default()
+            .src_subpass(SUBPASS_EXTERNAL)
+            .dst_subpass(1)
             .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
             .src_access_mask(AccessFlags::empty())
             .dst_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-            .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)];
+            .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE);
+
+        Self::new()
+            .add_attachment(color_attachment_description)
+            .add_attachment(albedo_attachment_description)
+            .add_attachment(normal_attachment_description)
+            .add_attachment(depth_attachment_description)
+            .add_subpass(geometry_subpass)
+            .add_subpass(lighting_subpass)
+            .add_dependency(geometry_dependency)
+            .add_dependency(lighting_dependency)
+            .add_dependency(color_dependency)
+    }
+
+    /// Leaves the color attachment at index 0 in `TRANSFER_SRC_OPTIMAL` for blitting out
+    /// of after the pass, rather than presenting it, for rendering to an offscreen image.
+    /// Since it's shared between frames, clearing it also waits on the previous blit.
+    pub fn blit_source(mut self) -> Self {
+        if let Some(color_attachment) = self.attachments.first_mut() {
+            color_attachment.final_layout = ImageLayout::TRANSFER_SRC_OPTIMAL;
+        }
+        for dependency in &mut self.dependencies {
+            if dependency.src_subpass == SUBPASS_EXTERNAL {
+                dependency.src_stage_mask |= PipelineStageFlags::TRANSFER;
+            }
+        }
+        let last_subpass = self.subpasses.len().saturating_sub(1) as u32;
+        self.add_dependency(
+            SubpassDependency::default()
+                .src_subpass(last_subpass)
+                .dst_subpass(SUBPASS_EXTERNAL)
+                .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(PipelineStageFlags::TRANSFER)
+                .dst_access_mask(AccessFlags::TRANSFER_READ),
+        )
+    }
+
+    /// Adds an attachment, which subpasses refer to by the index it was added at
+    pub fn add_attachment(mut self, attachment_description: AttachmentDescription) -> Self {
+        self.attachments.push(attachment_description);
+        self
+    }
+
+    /// Adds a subpass, which dependencies refer to by the index it was added at
+    pub fn add_subpass(mut self, subpass: Subpass) -> Self {
+        self.subpasses.push(subpass);
+        self
+    }
+
+    pub fn add_dependency(mut self, subpass_dependency: SubpassDependency) -> Self {
+        self.dependencies.push(subpass_dependency);
+        self
+    }
+
+    /// Creates the render pass. Errors if there are no subpasses, a subpass refers to an
+    /// attachment that wasn't added, a dependency refers to a subpass that wasn't, or a
+    /// subpass's sample counts don't line up (see [`Subpass::add_resolve_attachment`]).
+    pub fn build(&self, logical_device: &Shared<LogicalDevice>) -> Result<RenderPass> {
+        ensure!(
+            !self.subpasses.is_empty(),
+            "A render pass needs at least one subpass!"
+        );
+        for dependency in &self.dependencies {
+            for subpass in [dependency.src_subpass, dependency.dst_subpass] {
+                ensure!(
+                    subpass == SUBPASS_EXTERNAL || (subpass as usize) < self.subpasses.len(),
+                    "Dependency refers to subpass {}, but only {} were added!",
+                    subpass,
+                    self.subpasses.len()
+                );
+            }
+        }
+        for attachment_ref in self.subpasses.iter().flat_map(Subpass::attachment_refs) {
+            ensure!(
+                attachment_ref.attachment == ATTACHMENT_UNUSED
+                    || (attachment_ref.attachment as usize) < self.attachments.len(),
+                "Subpass refers to attachment {}, but only {} were added!",
+                attachment_ref.attachment,
+                self.attachments.len()
+            );
+        }
+
+        let sample_counts = self
+            .subpasses
+            .iter()
+            .enumerate()
+            .map(|(idx, subpass)| self.validate_sample_counts(idx, subpass))
+            .collect::<Result<Vec<_>>>()?;
+
+        let subpass_descriptions = self
+            .subpasses
+            .iter()
+            .map(Subpass::get_subpass_description)
+            .collect::<Vec<_>>();
 
         let render_pass_create_info = RenderPassCreateInfo::default()
-            .attachments(&attachment_description)
-            .subpasses(&subpass_description)
-            .dependencies(&subpass_dependencies);
+            .attachments(&self.attachments)
+            .subpasses(&subpass_descriptions)
+            .dependencies(&self.dependencies);
 
         let render_pass =
             unsafe { logical_device.create_render_pass(&render_pass_create_info, None)? };
+        logical_device.set_object_name(render_pass, "render pass")?;
 
-        Ok(Self {
-            logical_device: Rc::clone(logical_device),
+        Ok(RenderPass {
+            logical_device: Shared::clone(logical_device),
             render_pass,
+            sample_counts,
         })
     }
+
+    /// Checks the subpass's color and depth attachments all have the same sample count,
+    /// and that resolves go from multisampled attachments to single sampled ones. Returns
+    /// the sample count, which is 1 if the subpass has no such attachments.
+    fn validate_sample_counts(&self, idx: usize, subpass: &Subpass) -> Result<SampleCountFlags> {
+        let samples_of = |attachment_ref: &AttachmentReference| {
+            self.attachments[attachment_ref.attachment as usize].samples
+        };
+        let is_used =
+            |attachment_ref: &&AttachmentReference| attachment_ref.attachment != ATTACHMENT_UNUSED;
+
+        let mut sample_counts = subpass
+            .color_attachments
+            .iter()
+            .chain(&subpass.depth_stencil_attachment)
+            .filter(is_used)
+            .map(samples_of);
+        let sample_count = sample_counts.next().unwrap_or(SampleCountFlags::TYPE_1);
+        ensure!(
+            sample_counts.all(|samples| samples == sample_count),
+            "Subpass {idx}'s color and depth attachments have different sample counts!"
+        );
+
+        if subpass.resolve_attachments.is_empty() {
+            return Ok(sample_count);
+        }
+        ensure!(
+            subpass.resolve_attachments.len() == subpass.color_attachments.len(),
+            "Subpass {idx} needs a resolve attachment per color attachment, or none!"
+        );
+        for (color_ref, resolve_ref) in subpass
+            .color_attachments
+            .iter()
+            .zip(&subpass.resolve_attachments)
+            .filter(|(_, resolve_ref)| is_used(resolve_ref))
+        {
+            ensure!(
+                is_used(&color_ref) && samples_of(color_ref) != SampleCountFlags::TYPE_1,
+                "Subpass {idx} resolves attachment {}, which isn't multisampled!",
+                color_ref.attachment
+            );
+            ensure!(
+                samples_of(resolve_ref) == SampleCountFlags::TYPE_1,
+                "Subpass {idx} resolves into attachment {}, which is multisampled!",
+                resolve_ref.attachment
+            );
+            ensure!(
+                self.attachments[color_ref.attachment as usize].format
+                    == self.attachments[resolve_ref.attachment as usize].format,
+                "Subpass {idx} resolves into attachment {} of a different format!",
+                resolve_ref.attachment
+            );
+        }
+        Ok(sample_count)
+    }
+}
+
+/// The attachments a single subpass of a [`RenderPassBuilder`] reads from and writes to
+#[derive(Clone, Debug)]
+pub struct Subpass {
+    pipeline_bind_point: PipelineBindPoint,
+    input_attachments: Vec<AttachmentReference>,
+    color_attachments: Vec<AttachmentReference>,
+    /// Either empty, or one per color attachment for resolving multisampled images
+    resolve_attachments: Vec<AttachmentReference>,
+    depth_stencil_attachment: Option<AttachmentReference>,
+    /// Attachments not used by this subpass whose contents must be kept for later ones
+    preserve_attachments: Vec<u32>,
+}
+
+impl Subpass {
+    pub fn new(pipeline_bind_point: PipelineBindPoint) -> Self {
+        Self {
+            pipeline_bind_point,
+            input_attachments: vec![],
+            color_attachments: vec![],
+            resolve_attachments: vec![],
+            depth_stencil_attachment: None,
+            preserve_attachments: vec![],
+        }
+    }
+
+    /// Adds an attachment written by an earlier subpass for the fragment shader to read
+    /// at the current pixel, as `subpassInput` at the `input_attachment_index` matching
+    /// the order added
+    pub fn add_input_attachment(mut self, attachment_ref: AttachmentReference) -> Self {
+        self.input_attachments.push(attachment_ref);
+        self
+    }
+
+    pub fn add_color_attachment(mut self, attachment_ref: AttachmentReference) -> Self {
+        self.color_attachments.push(attachment_ref);
+        self
+    }
+
+    /// Adds the attachment the color attachment at the same index is resolved into at the
+    /// end of the subpass. If any are added, every color attachment needs one (or
+    /// `ATTACHMENT_UNUSED`), and each resolve is from a multisampled attachment into a
+    /// single sampled one of the same format.
+    pub fn add_resolve_attachment(mut self, attachment_ref: AttachmentReference) -> Self {
+        self.resolve_attachments.push(attachment_ref);
+        self
+    }
+
+    pub fn depth_stencil_attachment(mut self, attachment_ref: AttachmentReference) -> Self {
+        self.depth_stencil_attachment = Some(attachment_ref);
+        self
+    }
+
+    pub fn add_preserve_attachment(mut self, attachment: u32) -> Self {
+        self.preserve_attachments.push(attachment);
+        self
+    }
+
+    fn attachment_refs(&self) -> impl Iterator<Item = &AttachmentReference> {
+        self.input_attachments
+            .iter()
+            .chain(&self.color_attachments)
+            .chain(&self.resolve_attachments)
+            .chain(&self.depth_stencil_attachment)
+    }
+
+    fn get_subpass_description(&self) -> SubpassDescription<'_> {
+        let mut subpass_description = SubpassDescription::default()
+            .pipeline_bind_point(self.pipeline_bind_point)
+            .input_attachments(&self.input_attachments)
+            .color_attachments(&self.color_attachments)
+            .preserve_attachments(&self.preserve_attachments);
+        if !self.resolve_attachments.is_empty() {
+            subpass_description =
+                subpass_description.resolve_attachments(&self.resolve_attachments);
+        }
+        if let Some(depth_stencil_attachment) = &self.depth_stencil_attachment {
+            subpass_description =
+                subpass_description.depth_stencil_attachment(depth_stencil_attachment);
+        }
+        subpass_description
+    }
 }
 
 impl Drop for RenderPass {
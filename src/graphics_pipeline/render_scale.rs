@@ -0,0 +1,169 @@
+use anyhow::{ensure, Result};
+use ash::vk::{
+    self, CommandBuffer, Extent2D, Filter, ImageAspectFlags, ImageBlit, ImageLayout,
+    ImageSubresourceLayers, ImageSubresourceRange, ImageUsageFlags, Offset3D,
+};
+
+use crate::{transition_image_layout, Image, ImageDescriptor, LogicalDevice, Shared, Swapchain};
+
+/// The fraction of the swapchain's resolution the scene is rendered at. Below full
+/// resolution the scene is drawn to a smaller offscreen image, then scaled up to the
+/// swapchain image with linear filtering, trading sharpness for fill rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderScale(f32);
+
+impl RenderScale {
+    /// Renders straight to the swapchain images
+    pub const FULL: Self = Self(1.0);
+
+    /// Errors unless `scale` is above 0 and at most 1
+    pub fn new(scale: f32) -> Result<Self> {
+        ensure!(
+            scale > 0.0 && scale <= 1.0,
+            "Render scale must be above 0 and at most 1, got {scale}!"
+        );
+        Ok(Self(scale))
+    }
+
+    pub fn get_scale(&self) -> f32 {
+        self.0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.0 >= 1.0
+    }
+
+    /// Returns the extent rendered at for a swapchain of the given extent, at least a
+    /// pixel in each direction
+    pub fn scale_extent(&self, extent: Extent2D) -> Extent2D {
+        Extent2D {
+            width: ((extent.width as f32 * self.0) as u32).max(1),
+            height: ((extent.height as f32 * self.0) as u32).max(1),
+        }
+    }
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// The offscreen color image a pipeline renders to below full resolution, and the
+/// swapchain images it's blitted to afterwards
+pub(crate) struct ScaledTarget {
+    color_image: Shared<Image>,
+    /// Owned by the swapchain
+    swapchain_images: Vec<vk::Image>,
+    swapchain_extent: Extent2D,
+}
+
+impl ScaledTarget {
+    /// Creates the offscreen image at `extent`, erroring if the swapchain's images can't
+    /// be blitted to with linear filtering
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        swapchain: &Swapchain,
+        extent: Extent2D,
+    ) -> Result<Self> {
+        let format = swapchain.get_surface_format().format;
+        ensure!(
+            swapchain
+                .get_image_usage()
+                .contains(ImageUsageFlags::TRANSFER_DST),
+            "The swapchain's images can't be blitted to!"
+        );
+        ensure!(
+            logical_device.get_format_features(format).contains(
+                vk::FormatFeatureFlags::BLIT_SRC
+                    | vk::FormatFeatureFlags::BLIT_DST
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+            ),
+            "The device can't scale {format:?} images with linear filtering!"
+        );
+
+        let color_image = Image::new(
+            logical_device,
+            &ImageDescriptor {
+                format,
+                extent,
+                usage: ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+                aspect: ImageAspectFlags::COLOR,
+                ..Default::default()
+            },
+        )?;
+        logical_device.set_object_name(*color_image, "scaled color image")?;
+
+        Ok(Self {
+            color_image: Shared::new(color_image),
+            swapchain_images: swapchain.get_swapchain_images()?,
+            swapchain_extent: *swapchain.get_extent(),
+        })
+    }
+
+    pub fn get_color_image(&self) -> &Shared<Image> {
+        &self.color_image
+    }
+
+    /// Scales the offscreen image, which has to be in `TRANSFER_SRC_OPTIMAL`, up onto the
+    /// swapchain image and leaves that ready to present
+    pub fn record_blit(
+        &self,
+        logical_device: &LogicalDevice,
+        command_buffer: CommandBuffer,
+        image_index: usize,
+    ) -> Result<()> {
+        let swapchain_image = self.swapchain_images[image_index];
+        let color_range = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+        transition_image_layout(
+            logical_device,
+            command_buffer,
+            swapchain_image,
+            ImageLayout::UNDEFINED,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            color_range,
+        )?;
+
+        let color_layers = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .layer_count(1);
+        let extent = self.color_image.get_extent();
+        let regions = [ImageBlit::default()
+            .src_subresource(color_layers)
+            .src_offsets([Offset3D::default(), far_corner(extent)])
+            .dst_subresource(color_layers)
+            .dst_offsets([Offset3D::default(), far_corner(self.swapchain_extent)])];
+        unsafe {
+            logical_device.cmd_blit_image(
+                command_buffer,
+                **self.color_image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &regions,
+                Filter::LINEAR,
+            )
+        };
+
+        transition_image_layout(
+            logical_device,
+            command_buffer,
+            swapchain_image,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::PRESENT_SRC_KHR,
+            color_range,
+        )
+    }
+}
+
+/// The corner of a 2D image region opposite its origin, as blits describe regions
+fn far_corner(extent: Extent2D) -> Offset3D {
+    Offset3D {
+        x: extent.width as i32,
+        y: extent.height as i32,
+        z: 1,
+    }
+}
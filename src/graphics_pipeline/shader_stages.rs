@@ -0,0 +1,58 @@
+use ash::vk::ShaderStageFlags;
+
+use super::Specialization;
+use crate::shaders::{FRAGMENT_SHADER_CODE, VERTEX_SHADER_CODE};
+
+/// The shader stages a pipeline runs, each given as SPIR-V byte code, and the
+/// specialization constants of every stage. The default runs the built in vertex and
+/// fragment shaders, which draw a hardcoded triangle rather than reading vertex input,
+/// without specializing them.
+#[derive(Clone, Copy, Debug)]
+pub struct ShaderStages<'a> {
+    /// Run on each vertex, reading the attributes the pipeline's [`crate::Vertex`] type
+    /// describes
+    pub vertex: &'a [u8],
+    /// Run on each fragment, writing its color
+    pub fragment: &'a [u8],
+    /// Run on each primitive after vertex (or tessellation) processing. Requires the
+    /// geometryShader device feature.
+    pub geometry: Option<&'a [u8]>,
+    /// Subdivides patches of vertices, see [`TessellationShaders`]
+    pub tessellation: Option<TessellationShaders<'a>>,
+    /// The constants to specialize each stage with, if any, by the stage they're for
+    pub specializations: &'a [(ShaderStageFlags, &'a Specialization)],
+}
+
+impl Default for ShaderStages<'_> {
+    fn default() -> Self {
+        Self {
+            vertex: VERTEX_SHADER_CODE,
+            fragment: FRAGMENT_SHADER_CODE,
+            geometry: None,
+            tessellation: None,
+            specializations: &[],
+        }
+    }
+}
+
+impl<'a> ShaderStages<'a> {
+    /// Returns the specialization for the stage, if it has one
+    pub(crate) fn get_specialization(&self, stage: ShaderStageFlags) -> Option<&'a Specialization> {
+        self.specializations
+            .iter()
+            .find(|(specialized_stage, _)| *specialized_stage == stage)
+            .map(|(_, specialization)| *specialization)
+    }
+}
+
+/// The shaders for tessellating patches. The pipeline reads its vertices as a list of
+/// patches rather than triangles. Requires the tessellationShader device feature.
+#[derive(Clone, Copy, Debug)]
+pub struct TessellationShaders<'a> {
+    /// Decides how much each patch is subdivided
+    pub control: &'a [u8],
+    /// Positions the vertices generated by subdividing
+    pub evaluation: &'a [u8],
+    /// How many vertices make up each patch
+    pub patch_control_points: u32,
+}
@@ -0,0 +1,110 @@
+use ash::vk::{SpecializationInfo, SpecializationMapEntry, FALSE, TRUE};
+
+/// Values for a shader's specialization constants (`layout(constant_id = n) const`),
+/// set when the pipeline is created rather than when the shader is compiled, so one
+/// SPIR-V module can build differently configured pipelines. Setting a constant again
+/// replaces its value.
+#[derive(Clone, Debug, Default)]
+pub struct Specialization {
+    map_entries: Vec<SpecializationMapEntry>,
+    /// The values of every constant, packed one after another
+    data: Vec<u8>,
+}
+
+impl Specialization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `uint` or `int` constant with the id
+    pub fn constant_u32(self, constant_id: u32, value: u32) -> Self {
+        self.constant(constant_id, &value.to_ne_bytes())
+    }
+
+    /// Sets the `float` constant with the id
+    pub fn constant_f32(self, constant_id: u32, value: f32) -> Self {
+        self.constant(constant_id, &value.to_ne_bytes())
+    }
+
+    /// Sets the `bool` constant with the id
+    pub fn constant_bool(self, constant_id: u32, value: bool) -> Self {
+        let value = if value { TRUE } else { FALSE };
+        self.constant(constant_id, &value.to_ne_bytes())
+    }
+
+    /// Returns the info to pass to the shader stage, borrowing the constants
+    pub fn get_specialization_info(&self) -> SpecializationInfo<'_> {
+        SpecializationInfo::default()
+            .map_entries(&self.map_entries)
+            .data(&self.data)
+    }
+
+    fn constant(mut self, constant_id: u32, bytes: &[u8]) -> Self {
+        // each id may only be mapped once. every constant is 4 bytes, so the new value
+        // fits where the old one was
+        if let Some(map_entry) = self
+            .map_entries
+            .iter()
+            .find(|map_entry| map_entry.constant_id == constant_id)
+        {
+            let offset = map_entry.offset as usize;
+            self.data[offset..offset + map_entry.size].copy_from_slice(bytes);
+            return self;
+        }
+        self.map_entries.push(
+            SpecializationMapEntry::default()
+                .constant_id(constant_id)
+                .offset(self.data.len() as u32)
+                .size(bytes.len()),
+        );
+        self.data.extend_from_slice(bytes);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants_are_packed_in_order() {
+        let specialization = Specialization::new()
+            .constant_u32(0, 7)
+            .constant_f32(3, 0.5)
+            .constant_bool(1, true);
+        let offsets_and_sizes = specialization
+            .map_entries
+            .iter()
+            .map(|map_entry| (map_entry.constant_id, map_entry.offset, map_entry.size))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets_and_sizes, [(0, 0, 4), (3, 4, 4), (1, 8, 4)]);
+
+        let mut data = vec![];
+        data.extend_from_slice(&7u32.to_ne_bytes());
+        data.extend_from_slice(&0.5f32.to_ne_bytes());
+        data.extend_from_slice(&TRUE.to_ne_bytes());
+        assert_eq!(specialization.data, data);
+    }
+
+    #[test]
+    fn setting_a_constant_again_replaces_it() {
+        let specialization = Specialization::new()
+            .constant_u32(0, 7)
+            .constant_u32(1, 8)
+            .constant_u32(0, 9);
+        assert_eq!(specialization.map_entries.len(), 2);
+        assert_eq!(specialization.map_entries[0].offset, 0);
+        assert_eq!(&specialization.data[..4], &9u32.to_ne_bytes());
+        assert_eq!(&specialization.data[4..], &8u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn specialization_info_covers_every_constant() {
+        let specialization = Specialization::new()
+            .constant_u32(0, 7)
+            .constant_bool(1, false);
+        let specialization_info = specialization.get_specialization_info();
+        assert_eq!(specialization_info.map_entry_count, 2);
+        assert_eq!(specialization_info.data_size, 8);
+    }
+}
@@ -0,0 +1,74 @@
+use ash::vk::{CompareOp, PipelineDepthStencilStateCreateInfo, StencilOp, StencilOpState};
+
+use super::DepthConfig;
+
+/// How a pipeline tests and updates the stencil buffer, for front and back facing
+/// geometry. Pipelines with a stencil config also depth test, by default keeping the
+/// closest fragments.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StencilConfig {
+    pub front: StencilOpState,
+    pub back: StencilOpState,
+    /// How the depth test is done, and what depth the buffer is cleared to
+    pub depth: DepthConfig,
+}
+
+impl StencilConfig {
+    /// Uses the same stencil operations for front and back faces
+    pub fn both(op_state: StencilOpState) -> Self {
+        Self {
+            front: op_state,
+            back: op_state,
+            depth: DepthConfig::default(),
+        }
+    }
+
+    /// Depth tests as `depth` configures, instead of keeping the closest fragments
+    pub fn with_depth(mut self, depth: DepthConfig) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Writes `reference` to the stencil buffer wherever geometry is drawn, the first
+    /// pass of masking
+    pub fn write_mask(reference: u32) -> Self {
+        Self::both(
+            StencilOpState::default()
+                .compare_op(CompareOp::ALWAYS)
+                .pass_op(StencilOp::REPLACE)
+                .fail_op(StencilOp::KEEP)
+                .depth_fail_op(StencilOp::KEEP)
+                .compare_mask(0xff)
+                .write_mask(0xff)
+                .reference(reference),
+        )
+    }
+
+    /// Only draws where the stencil buffer holds `reference`, leaving it unchanged, the
+    /// second pass of masking. Outlines, which draw where the mask *wasn't* written,
+    /// compare with `NOT_EQUAL` instead.
+    pub fn test_mask(reference: u32) -> Self {
+        Self::both(
+            StencilOpState::default()
+                .compare_op(CompareOp::EQUAL)
+                .pass_op(StencilOp::KEEP)
+                .fail_op(StencilOp::KEEP)
+                .depth_fail_op(StencilOp::KEEP)
+                .compare_mask(0xff)
+                .write_mask(0x00)
+                .reference(reference),
+        )
+    }
+
+    /// Returns the depth stencil state for a pipeline using this config
+    pub fn get_depth_stencil_state(&self) -> PipelineDepthStencilStateCreateInfo<'static> {
+        PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(self.depth.compare_op)
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(true)
+            .front(self.front)
+            .back(self.back)
+    }
+}
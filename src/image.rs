@@ -0,0 +1,207 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use ash::vk::{
+    self, DeviceMemory, Extent2D, Extent3D, Format, ImageAspectFlags, ImageCreateFlags,
+    ImageCreateInfo, ImageLayout, ImageTiling, ImageType, ImageUsageFlags, ImageViewType,
+    MemoryAllocateInfo, MemoryPropertyFlags, SampleCountFlags, SharingMode,
+};
+
+use crate::{ImageView, ImageViewConfig, LogicalDevice, Shared};
+
+/// Everything needed to create an [`Image`]
+#[derive(Clone, Copy, Debug)]
+pub struct ImageDescriptor {
+    pub format: Format,
+    pub extent: Extent2D,
+    pub usage: ImageUsageFlags,
+    /// Which aspects the image's view covers, e.g. `COLOR` or `DEPTH`
+    pub aspect: ImageAspectFlags,
+    pub mip_levels: u32,
+    /// 6 for a cubemap, along with the `CUBE_COMPATIBLE` flag
+    pub array_layers: u32,
+    pub samples: SampleCountFlags,
+    pub flags: ImageCreateFlags,
+    /// Whether to create a view onto the image, which is needed to sample from it or
+    /// use it as an attachment
+    pub create_view: bool,
+}
+
+impl Default for ImageDescriptor {
+    fn default() -> Self {
+        Self {
+            format: Format::R8G8B8A8_SRGB,
+            extent: Extent2D::default(),
+            usage: ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST,
+            aspect: ImageAspectFlags::COLOR,
+            mip_levels: 1,
+            array_layers: 1,
+            samples: SampleCountFlags::TYPE_1,
+            flags: ImageCreateFlags::empty(),
+            create_view: true,
+        }
+    }
+}
+
+/// A 2D image, along with the memory backing it and a view onto it
+pub struct Image {
+    logical_device: Shared<LogicalDevice>,
+    image: vk::Image,
+    /// False when the image is owned by something else, like a swapchain, in which case
+    /// only the view is destroyed with us
+    owns_image: bool,
+    memory: Option<DeviceMemory>,
+    /// Option so it can be destroyed before the image it views
+    view: Option<ImageView>,
+    format: Format,
+    extent: Extent2D,
+    mip_levels: u32,
+    samples: SampleCountFlags,
+}
+
+impl Image {
+    /// Creates an image in device local memory as described, in the `UNDEFINED` layout
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        descriptor: &ImageDescriptor,
+    ) -> Result<Self> {
+        let image_create_info = ImageCreateInfo::default()
+            .image_type(ImageType::TYPE_2D)
+            .format(descriptor.format)
+            .extent(Extent3D {
+                width: descriptor.extent.width,
+                height: descriptor.extent.height,
+                depth: 1,
+            })
+            .mip_levels(descriptor.mip_levels)
+            .flags(descriptor.flags)
+            .array_layers(descriptor.array_layers)
+            .samples(descriptor.samples)
+            .tiling(ImageTiling::OPTIMAL)
+            .usage(descriptor.usage)
+            .sharing_mode(SharingMode::EXCLUSIVE)
+            .initial_layout(ImageLayout::UNDEFINED);
+        let image = unsafe { logical_device.create_image(&image_create_info, None)? };
+
+        // build up the image as we go so everything created so far is cleaned up if a
+        // later step fails
+        let mut this = Self {
+            logical_device: Shared::clone(logical_device),
+            image,
+            owns_image: true,
+            memory: None,
+            view: None,
+            format: descriptor.format,
+            extent: descriptor.extent,
+            mip_levels: descriptor.mip_levels,
+            samples: descriptor.samples,
+        };
+
+        let memory_requirements = unsafe { logical_device.get_image_memory_requirements(image) };
+        let memory_type_index = logical_device.find_memory_type(
+            memory_requirements.memory_type_bits,
+            MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let memory_allocate_info = MemoryAllocateInfo::default()
+            .allocation_size(memory_requirements.size)
+            .memory_type_index(memory_type_index);
+        let memory = unsafe { logical_device.allocate_memory(&memory_allocate_info, None)? };
+        this.memory = Some(memory);
+        unsafe { logical_device.bind_image_memory(image, memory, 0)? };
+
+        if descriptor.create_view {
+            // view every mip level and layer, as a cube if the image can be one
+            let view_type = if descriptor.flags.contains(ImageCreateFlags::CUBE_COMPATIBLE)
+                && descriptor.array_layers == 6
+            {
+                ImageViewType::CUBE
+            } else if descriptor.array_layers > 1 {
+                ImageViewType::TYPE_2D_ARRAY
+            } else {
+                ImageViewType::TYPE_2D
+            };
+            let view_config = ImageViewConfig {
+                view_type,
+                aspect_mask: descriptor.aspect,
+                level_count: descriptor.mip_levels,
+                layer_count: descriptor.array_layers,
+                ..Default::default()
+            };
+            this.view = Some(ImageView::new(
+                logical_device,
+                image,
+                descriptor.format,
+                &view_config,
+            )?);
+        }
+
+        Ok(this)
+    }
+
+    /// Wraps an image owned by a swapchain, creating a color view onto it. Only the
+    /// view is destroyed when this is dropped.
+    pub fn from_swapchain_image(
+        logical_device: &Shared<LogicalDevice>,
+        image: vk::Image,
+        format: Format,
+        extent: Extent2D,
+    ) -> Result<Self> {
+        let view = ImageView::new(logical_device, image, format, &ImageViewConfig::default())?;
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            image,
+            owns_image: false,
+            memory: None,
+            view: Some(view),
+            format,
+            extent,
+            mip_levels: 1,
+            samples: SampleCountFlags::TYPE_1,
+        })
+    }
+
+    /// Returns the view onto the image, if one was created
+    pub fn get_view(&self) -> Option<&ImageView> {
+        self.view.as_ref()
+    }
+
+    pub fn get_format(&self) -> Format {
+        self.format
+    }
+
+    pub fn get_extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    pub fn get_mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    /// Returns how many samples each pixel has, more than one for multisampled images
+    pub fn get_samples(&self) -> SampleCountFlags {
+        self.samples
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        // the view has to go before the image it views
+        self.view = None;
+        unsafe {
+            if self.owns_image {
+                self.logical_device.destroy_image(self.image, None);
+            }
+            if let Some(memory) = self.memory {
+                self.logical_device.free_memory(memory, None);
+            }
+        }
+    }
+}
+
+impl Deref for Image {
+    type Target = vk::Image;
+
+    fn deref(&self) -> &Self::Target {
+        &self.image
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{bail, Result};
+use ash::vk::{
+    AccessFlags, CommandBuffer, DependencyFlags, Image, ImageLayout, ImageMemoryBarrier,
+    ImageSubresourceRange, PipelineStageFlags, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::LogicalDevice;
+
+/// Records a pipeline barrier transitioning the `subresource_range` of the image (its
+/// aspects, mip levels and layers) from `old_layout` to `new_layout`, picking the access masks and stages for
+/// the transition. Supports the common transitions:
+///
+/// - `UNDEFINED` to `TRANSFER_DST_OPTIMAL`, before uploading to the image
+/// - `TRANSFER_DST_OPTIMAL` to `SHADER_READ_ONLY_OPTIMAL`, after uploading a texture
+/// - `UNDEFINED` to `DEPTH_ATTACHMENT_OPTIMAL` or `DEPTH_STENCIL_ATTACHMENT_OPTIMAL`,
+///   before using the image as a depth buffer
+/// - `UNDEFINED` to `COLOR_ATTACHMENT_OPTIMAL`, before rendering to a swapchain image
+/// - `COLOR_ATTACHMENT_OPTIMAL` to `PRESENT_SRC_KHR`, before presenting it
+/// - `COLOR_ATTACHMENT_OPTIMAL` to `TRANSFER_SRC_OPTIMAL`, before blitting out of an
+///   offscreen image
+/// - `TRANSFER_DST_OPTIMAL` to `PRESENT_SRC_KHR`, before presenting a blitted to image
+/// - `DEPTH_STENCIL_ATTACHMENT_OPTIMAL` to `TRANSFER_SRC_OPTIMAL` and back, around
+///   copying out of a depth buffer
+///
+/// and errors on any others.
+pub fn transition_image_layout(
+    logical_device: &LogicalDevice,
+    command_buffer: CommandBuffer,
+    image: Image,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+    subresource_range: ImageSubresourceRange,
+) -> Result<()> {
+    let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) =
+        transition_masks(old_layout, new_layout)?;
+
+    let image_memory_barriers = [ImageMemoryBarrier::default()
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        // not transferring ownership between queue families
+        .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource_range)
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)];
+
+    unsafe {
+        logical_device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage_mask,
+            dst_stage_mask,
+            DependencyFlags::empty(),
+            &[],
+            &[],
+            &image_memory_barriers,
+        )
+    };
+    Ok(())
+}
+
+/// Returns the source and destination access masks and stages for transitioning an
+/// image from `old_layout` to `new_layout`, see [`transition_image_layout`]
+fn transition_masks(
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+) -> Result<(
+    AccessFlags,
+    AccessFlags,
+    PipelineStageFlags,
+    PipelineStageFlags,
+)> {
+    let masks = match (old_layout, new_layout) {
+        // nothing to wait on, but the transfer has to wait for the transition. waiting
+        // on the transfer stage chains onto a semaphore wait for a swapchain image
+        // being available, before blitting to it
+        (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL) => (
+            AccessFlags::empty(),
+            AccessFlags::TRANSFER_WRITE,
+            PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::TRANSFER,
+        ),
+        // the upload has to finish before fragment shaders sample the image
+        (ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
+            AccessFlags::TRANSFER_WRITE,
+            AccessFlags::SHADER_READ,
+            PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::FRAGMENT_SHADER,
+        ),
+        // depth is first read during the early fragment tests. a depth buffer shared
+        // between frames may still be written by the previous frame's late tests
+        (
+            ImageLayout::UNDEFINED,
+            ImageLayout::DEPTH_ATTACHMENT_OPTIMAL | ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ) => (
+            AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+        ),
+        // waiting on the color output stage chains onto the semaphore wait for the
+        // swapchain image being available. an offscreen image shared between frames
+        // may also still be blitted from by the previous frame
+        (ImageLayout::UNDEFINED, ImageLayout::COLOR_ATTACHMENT_OPTIMAL) => (
+            AccessFlags::empty(),
+            AccessFlags::COLOR_ATTACHMENT_WRITE,
+            PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        ),
+        // rendering has to finish before the image is presented, which the present
+        // waits for with a semaphore
+        (ImageLayout::COLOR_ATTACHMENT_OPTIMAL, ImageLayout::PRESENT_SRC_KHR) => (
+            AccessFlags::COLOR_ATTACHMENT_WRITE,
+            AccessFlags::empty(),
+            PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            PipelineStageFlags::BOTTOM_OF_PIPE,
+        ),
+        // rendering has to finish before the image is blitted from
+        (ImageLayout::COLOR_ATTACHMENT_OPTIMAL, ImageLayout::TRANSFER_SRC_OPTIMAL) => (
+            AccessFlags::COLOR_ATTACHMENT_WRITE,
+            AccessFlags::TRANSFER_READ,
+            PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            PipelineStageFlags::TRANSFER,
+        ),
+        // and the blit before the image is presented
+        (ImageLayout::TRANSFER_DST_OPTIMAL, ImageLayout::PRESENT_SRC_KHR) => (
+            AccessFlags::TRANSFER_WRITE,
+            AccessFlags::empty(),
+            PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::BOTTOM_OF_PIPE,
+        ),
+        // the frame's depth writes have to land before they're copied out
+        (ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL, ImageLayout::TRANSFER_SRC_OPTIMAL) => (
+            AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            AccessFlags::TRANSFER_READ,
+            PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            PipelineStageFlags::TRANSFER,
+        ),
+        // and the copy has to finish before the next frame's tests touch it again
+        (ImageLayout::TRANSFER_SRC_OPTIMAL, ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL) => (
+            AccessFlags::TRANSFER_READ,
+            AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            PipelineStageFlags::TRANSFER,
+            PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+        ),
+        _ => bail!(
+            "Unsupported image layout transition from {:?} to {:?}!",
+            old_layout,
+            new_layout
+        ),
+    };
+    Ok(masks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUPPORTED: [(ImageLayout, ImageLayout); 10] = [
+        (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL),
+        (
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        ),
+        (
+            ImageLayout::UNDEFINED,
+            ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+        ),
+        (
+            ImageLayout::UNDEFINED,
+            ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ),
+        (
+            ImageLayout::UNDEFINED,
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        ),
+        (
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            ImageLayout::PRESENT_SRC_KHR,
+        ),
+        (
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ),
+        (
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::PRESENT_SRC_KHR,
+        ),
+        (
+            ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ),
+        (
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ),
+    ];
+
+    #[test]
+    fn supported_transitions_have_stages() {
+        for (old_layout, new_layout) in SUPPORTED {
+            let (_, _, src_stage_mask, dst_stage_mask) =
+                transition_masks(old_layout, new_layout).unwrap();
+            assert!(
+                !src_stage_mask.is_empty(),
+                "{old_layout:?} to {new_layout:?}"
+            );
+            assert!(
+                !dst_stage_mask.is_empty(),
+                "{old_layout:?} to {new_layout:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn uploads_finish_before_sampling() {
+        assert_eq!(
+            transition_masks(
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            )
+            .unwrap(),
+            (
+                AccessFlags::TRANSFER_WRITE,
+                AccessFlags::SHADER_READ,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            )
+        );
+    }
+
+    #[test]
+    fn depth_transitions_match() {
+        // both depth formats are transitioned the same way
+        assert_eq!(
+            transition_masks(
+                ImageLayout::UNDEFINED,
+                ImageLayout::DEPTH_ATTACHMENT_OPTIMAL
+            )
+            .unwrap(),
+            transition_masks(
+                ImageLayout::UNDEFINED,
+                ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn unsupported_transition_errors() {
+        assert!(transition_masks(
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ImageLayout::TRANSFER_DST_OPTIMAL
+        )
+        .is_err());
+    }
+}
@@ -1,32 +1,58 @@
-use std::{ops::Deref, rc::Rc};
+use std::ops::Deref;
 
 use anyhow::Result;
 use ash::vk::{
-    self, ComponentMapping, ComponentSwizzle, Image, ImageAspectFlags, ImageSubresourceRange,
-    ImageViewCreateInfo, ImageViewType, SurfaceFormatKHR,
+    self, ComponentMapping, ComponentSwizzle, Format, Image, ImageAspectFlags,
+    ImageSubresourceRange, ImageViewCreateInfo, ImageViewType,
 };
 
-use crate::LogicalDevice;
+use crate::{LogicalDevice, Shared};
 
+/// Which part of an image a view covers, and how it's interpreted
+#[derive(Clone, Copy, Debug)]
+pub struct ImageViewConfig {
+    /// e.g. `TYPE_2D`, `TYPE_2D_ARRAY` or `CUBE`
+    pub view_type: ImageViewType,
+    pub aspect_mask: ImageAspectFlags,
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    /// 6 for a `CUBE` view, one per face
+    pub layer_count: u32,
+}
+
+impl Default for ImageViewConfig {
+    /// The first mip level of the first layer of a 2D color image
+    fn default() -> Self {
+        Self {
+            view_type: ImageViewType::TYPE_2D,
+            aspect_mask: ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+}
+
+/// A view onto an image. Doesn't keep the image alive, so it's usually owned by the
+/// [`crate::Image`] it views.
 pub struct ImageView {
-    logical_device: Rc<LogicalDevice>,
+    logical_device: Shared<LogicalDevice>,
     image_view: vk::ImageView,
-    // need to keep references to these to make sure they aren't
-    // cleaned up before we are
-    _image: Image,
 }
 
 impl ImageView {
     pub fn new(
-        logical_device: &Rc<LogicalDevice>,
-        surface_format: SurfaceFormatKHR,
+        logical_device: &Shared<LogicalDevice>,
         image: Image,
+        format: Format,
+        config: &ImageViewConfig,
     ) -> Result<Self> {
         let image_view_create_info = ImageViewCreateInfo::default()
             .image(image)
-            // 2D images
-            .view_type(ImageViewType::TYPE_2D)
-            .format(surface_format.format)
+            .view_type(config.view_type)
+            .format(format)
             // no swizzling
             .components(
                 ComponentMapping::default()
@@ -35,22 +61,20 @@ impl ImageView {
                     .g(ComponentSwizzle::IDENTITY)
                     .r(ComponentSwizzle::IDENTITY),
             )
-            // color images with no mipmapping or layers
             .subresource_range(
                 ImageSubresourceRange::default()
-                    .aspect_mask(ImageAspectFlags::COLOR)
-                    .base_mip_level(0)
-                    .level_count(1)
-                    .base_array_layer(0)
-                    .layer_count(1),
+                    .aspect_mask(config.aspect_mask)
+                    .base_mip_level(config.base_mip_level)
+                    .level_count(config.level_count)
+                    .base_array_layer(config.base_array_layer)
+                    .layer_count(config.layer_count),
             );
         let image_view =
             unsafe { logical_device.create_image_view(&image_view_create_info, None)? };
 
         Ok(Self {
-            logical_device: Rc::clone(logical_device),
+            logical_device: Shared::clone(logical_device),
             image_view,
-            _image: image,
         })
     }
 }
@@ -0,0 +1,97 @@
+use std::mem::size_of;
+
+use anyhow::{ensure, Result};
+use ash::vk::{
+    self, BufferUsageFlags, DeviceSize, DrawIndexedIndirectCommand, IndexType, MemoryPropertyFlags,
+};
+
+use crate::{Buffer, LogicalDevice, Shared};
+
+/// An indexed indirect draw recorded each frame in place of the [`crate::DrawCommand`],
+/// see `vkCmdDrawIndexedIndirect`. Holds raw handles, so the buffers have to outlive
+/// every frame drawing with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndirectDraw {
+    /// Buffer of tightly packed `VkDrawIndexedIndirectCommand`s
+    pub indirect_buffer: vk::Buffer,
+    /// Byte offset of the first command in the indirect buffer
+    pub offset: DeviceSize,
+    /// How many commands to draw
+    pub draw_count: u32,
+    /// The buffer the commands' indicies are read from
+    pub index_buffer: vk::Buffer,
+    pub index_type: IndexType,
+}
+
+/// A buffer of draw commands for [`IndirectDraw`]s, written from the CPU or filled in by
+/// a compute pass, since it can also be bound as a storage buffer
+pub struct IndirectBuffer {
+    buffer: Buffer,
+    /// How many commands the buffer holds
+    capacity: u32,
+}
+
+impl IndirectBuffer {
+    /// Creates a buffer with room for `capacity` commands
+    pub fn new(logical_device: &Shared<LogicalDevice>, capacity: u32) -> Result<Self> {
+        ensure!(
+            capacity > 0,
+            "Indirect buffers must hold at least one command!"
+        );
+        let buffer = Buffer::new(
+            logical_device,
+            capacity as DeviceSize * size_of::<DrawIndexedIndirectCommand>() as DeviceSize,
+            BufferUsageFlags::INDIRECT_BUFFER | BufferUsageFlags::STORAGE_BUFFER,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        Ok(Self { buffer, capacity })
+    }
+
+    /// Creates a buffer holding the commands
+    pub fn from_commands(
+        logical_device: &Shared<LogicalDevice>,
+        commands: &[DrawIndexedIndirectCommand],
+    ) -> Result<Self> {
+        let indirect_buffer = Self::new(logical_device, commands.len() as u32)?;
+        indirect_buffer.write(0, commands)?;
+        Ok(indirect_buffer)
+    }
+
+    /// Writes the commands into the buffer, starting at command `first_command`. The GPU
+    /// must not be drawing from them.
+    pub fn write(&self, first_command: u32, commands: &[DrawIndexedIndirectCommand]) -> Result<()> {
+        let offset =
+            first_command as DeviceSize * size_of::<DrawIndexedIndirectCommand>() as DeviceSize;
+        self.buffer.write_slice(offset, commands)
+    }
+
+    /// Returns a draw of the buffer's first `draw_count` commands, with indicies read from
+    /// the index buffer
+    pub fn draw(
+        &self,
+        draw_count: u32,
+        index_buffer: vk::Buffer,
+        index_type: IndexType,
+    ) -> Result<IndirectDraw> {
+        ensure!(
+            draw_count <= self.capacity,
+            "Can't draw {draw_count} commands from a buffer of {}!",
+            self.capacity
+        );
+        Ok(IndirectDraw {
+            indirect_buffer: *self.buffer,
+            offset: 0,
+            draw_count,
+            index_buffer,
+            index_type,
+        })
+    }
+
+    pub fn get_buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn get_capacity(&self) -> u32 {
+        self.capacity
+    }
+}
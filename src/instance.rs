@@ -1,31 +1,111 @@
-use std::{ffi::CString, ops::Deref};
+use std::{ffi::CString, fmt::Write, ops::Deref};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::{
     ext::debug_utils,
-    vk::{make_api_version, ApplicationInfo, InstanceCreateInfo, API_VERSION_1_3},
+    vk::{
+        api_version_major, api_version_minor, api_version_patch, make_api_version, ApplicationInfo,
+        InstanceCreateFlags, InstanceCreateInfo, PhysicalDevice, API_VERSION_1_3,
+        KHR_PORTABILITY_ENUMERATION_NAME,
+    },
     Entry,
 };
 use tracing::debug;
 
-use crate::get_debug_messenger_create_info;
+use crate::{get_debug_messenger_create_info, Surface, REQUIRED_DEVICE_EXTENSIONS};
 
 const API_VERSION: u32 = API_VERSION_1_3;
 
+// without either there's no way to get at the Vulkan loader
+#[cfg(not(any(feature = "linked_loader", feature = "dynamic_loader")))]
+compile_error!("enable linked_loader or dynamic_loader");
+
 #[cfg(feature = "enable_validations")]
 const ENABLE_VALIDATIONS: bool = true;
 #[cfg(not(feature = "enable_validations"))]
 const ENABLE_VALIDATIONS: bool = false;
 
+/// Whether to have the loader list portability implementations like MoltenVK, which
+/// aren't fully conformant and so are hidden by default. They're the only way to run
+/// Vulkan on Apple platforms.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const ENABLE_PORTABILITY: bool = true;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+const ENABLE_PORTABILITY: bool = false;
+
+/// Whether instances load the DebugUtils extension for naming objects unless
+/// [`InstanceBuilder::debug_names`] says otherwise
+#[cfg(feature = "debug_names")]
+const DEBUG_NAMES_DEFAULT: bool = true;
+#[cfg(not(feature = "debug_names"))]
+const DEBUG_NAMES_DEFAULT: bool = false;
+
+/// Whether a physical device can present to a surface, see
+/// [`Instance::surface_support_summary`]
+#[derive(Clone, Debug)]
+pub struct SurfaceSupport {
+    pub physical_device: PhysicalDevice,
+    pub device_name: String,
+    /// The queue families able to present to the surface
+    pub present_queue_families: Vec<u32>,
+}
+
+impl SurfaceSupport {
+    pub fn can_present(&self) -> bool {
+        !self.present_queue_families.is_empty()
+    }
+}
+
+/// Configures and creates an [`Instance`]
+#[derive(Clone, Debug)]
+pub struct InstanceBuilder<'a> {
+    required_extensions: Vec<&'a str>,
+    debug_names: bool,
+}
+
+impl<'a> InstanceBuilder<'a> {
+    /// Starts configuring an instance enabling the given extensions, for instance those
+    /// the windowing system needs
+    pub fn new(required_extensions: Vec<&'a str>) -> Self {
+        Self {
+            required_extensions,
+            debug_names: DEBUG_NAMES_DEFAULT,
+        }
+    }
+
+    /// Whether to load `VK_EXT_debug_utils` to name Vulkan objects for tools like
+    /// RenderDoc, without the validation layer's overhead. Defaults to whether the
+    /// `debug_names` feature is enabled. It's always loaded with validations.
+    pub fn debug_names(mut self, debug_names: bool) -> Self {
+        self.debug_names = debug_names;
+        self
+    }
+
+    pub fn build(self) -> Result<Instance> {
+        Instance::create(
+            self.required_extensions,
+            ENABLE_VALIDATIONS || self.debug_names,
+        )
+    }
+}
+
 pub struct Instance {
     instance: ash::Instance,
     entry: Entry,
+    debug_utils_enabled: bool,
 }
 
 impl Instance {
     /// Creates an Instance to interact with the core of Vulkan. Registers the needed extensions and
-    /// layers, as well as basic information about the application.
-    pub fn new(entry: Entry, required_extensions: Vec<&str>) -> Result<Self> {
+    /// layers, as well as basic information about the application. See
+    /// [`InstanceBuilder`] for more options.
+    pub fn new(required_extensions: Vec<&str>) -> Result<Self> {
+        InstanceBuilder::new(required_extensions).build()
+    }
+
+    /// Creates the instance, loading the DebugUtils extension if `debug_utils_enabled`
+    fn create(required_extensions: Vec<&str>, debug_utils_enabled: bool) -> Result<Self> {
+        let entry = Self::load_entry()?;
         let appname = CString::new(env!("CARGO_PKG_NAME"))?;
         let version_major = env!("CARGO_PKG_VERSION_MAJOR").parse::<u32>()?;
         let version_minor = env!("CARGO_PKG_VERSION_MINOR").parse::<u32>()?;
@@ -39,18 +119,54 @@ impl Instance {
             .engine_name(&appname)
             .engine_version(app_version);
 
-        let enabled_extension_names = Self::get_required_instance_extensions(required_extensions)?
+        let available_extensions = Self::available_extensions(&entry)?;
+        debug!(
+            "Available instance extensions: {}",
+            available_extensions.join(", ")
+        );
+        // older loaders list portability implementations without being asked to
+        let portability_enumeration = ENABLE_PORTABILITY
+            && available_extensions
+                .iter()
+                .any(|ext| ext.as_bytes() == KHR_PORTABILITY_ENUMERATION_NAME.to_bytes());
+        let required_extensions = Self::get_required_instance_extensions(
+            required_extensions,
+            debug_utils_enabled,
+            portability_enumeration,
+        )?;
+        for required_extension in &required_extensions {
+            ensure!(
+                available_extensions
+                    .iter()
+                    .any(|ext| ext == required_extension),
+                "Instance extension {} is not supported on this system",
+                required_extension
+            );
+        }
+
+        let enabled_extension_names = required_extensions
             .into_iter()
-            .map(|extension_name| CString::new(extension_name))
+            .map(CString::new)
             .collect::<Result<Vec<_>, _>>()?;
         let enabled_extension_name_ptrs = enabled_extension_names
             .iter()
             .map(|extension_name| extension_name.as_ptr())
             .collect::<Vec<_>>();
 
-        let enabled_layer_names = Self::gen_required_layers()
+        let required_layers = Self::gen_required_layers();
+        let available_layers = Self::available_layers(&entry)?;
+        debug!("Available instance layers: {}", available_layers.join(", "));
+        for required_layer in &required_layers {
+            ensure!(
+                available_layers.contains(required_layer),
+                "Instance layer {} is not supported on this system",
+                required_layer
+            );
+        }
+
+        let enabled_layer_names = required_layers
             .into_iter()
-            .map(|layer_name| CString::new(layer_name))
+            .map(CString::new)
             .collect::<Result<Vec<_>, _>>()?;
         let enabled_layer_name_pts = enabled_layer_names
             .iter()
@@ -59,30 +175,223 @@ impl Instance {
 
         let mut debug_messenger_create_info = get_debug_messenger_create_info();
 
-        let instance_create_info = InstanceCreateInfo::default()
+        let create_flags = if portability_enumeration {
+            InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
+        } else {
+            InstanceCreateFlags::empty()
+        };
+        let mut instance_create_info = InstanceCreateInfo::default()
+            .flags(create_flags)
             .application_info(&app_info)
             .enabled_extension_names(&enabled_extension_name_ptrs)
-            .enabled_layer_names(&enabled_layer_name_pts)
-            .push_next(&mut debug_messenger_create_info);
+            .enabled_layer_names(&enabled_layer_name_pts);
+        // reports problems creating and destroying the instance itself
+        if ENABLE_VALIDATIONS {
+            instance_create_info = instance_create_info.push_next(&mut debug_messenger_create_info);
+        }
 
         let instance = unsafe { entry.create_instance(&instance_create_info, None)? };
 
-        Ok(Self { instance, entry })
+        Ok(Self {
+            instance,
+            entry,
+            debug_utils_enabled,
+        })
     }
 
     pub fn get_entry(&self) -> &Entry {
         &self.entry
     }
 
+    /// Loads the Vulkan loader at runtime when the `dynamic_loader` feature is enabled,
+    /// otherwise uses the one linked at build time.
+    #[cfg(feature = "dynamic_loader")]
+    pub fn load_entry() -> Result<Entry> {
+        let entry = unsafe { Entry::load() }.map_err(|err| {
+            anyhow::anyhow!("Could not find a Vulkan loader on this system: {}", err)
+        })?;
+        Ok(entry)
+    }
+
+    /// Loads the Vulkan loader at runtime when the `dynamic_loader` feature is enabled,
+    /// otherwise uses the one linked at build time.
+    #[cfg(all(feature = "linked_loader", not(feature = "dynamic_loader")))]
+    pub fn load_entry() -> Result<Entry> {
+        Ok(Entry::linked())
+    }
+
+    // only there so the compile_error is the one error without a loader feature
+    #[cfg(not(any(feature = "linked_loader", feature = "dynamic_loader")))]
+    pub fn load_entry() -> Result<Entry> {
+        unreachable!("enable linked_loader or dynamic_loader")
+    }
+
+    /// Returns the names of the instance extensions supported on this system
+    pub fn available_extensions(entry: &Entry) -> Result<Vec<String>> {
+        let extension_properties = unsafe { entry.enumerate_instance_extension_properties(None)? };
+        let extension_names = extension_properties
+            .iter()
+            .map(|properties| Ok(properties.extension_name_as_c_str()?.to_str()?.to_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(extension_names)
+    }
+
+    /// Returns the names of the instance layers installed on this system
+    pub fn available_layers(entry: &Entry) -> Result<Vec<String>> {
+        let layer_properties = unsafe { entry.enumerate_instance_layer_properties()? };
+        let layer_names = layer_properties
+            .iter()
+            .map(|properties| Ok(properties.layer_name_as_c_str()?.to_str()?.to_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(layer_names)
+    }
+
+    /// True if the DebugUtils extension was enabled when creating the instance, either with
+    /// validations or just for object names, see [`InstanceBuilder::debug_names`]
+    pub fn is_debug_utils_enabled(&self) -> bool {
+        self.debug_utils_enabled
+    }
+
+    /// True if the validation layer was enabled when creating the instance
+    pub fn is_validation_enabled(&self) -> bool {
+        ENABLE_VALIDATIONS
+    }
+
+    /// Describes every physical device on the system: its name, type, API and driver
+    /// versions, whether it has the device extensions needed for presenting, and its queue
+    /// families. Meant for bug reports, e.g. when no suitable device is found. If querying
+    /// fails partway through, the error is included at the end of the report.
+    pub fn device_report(&self) -> String {
+        let mut report = String::new();
+        if let Err(err) = self.write_device_report(&mut report) {
+            let _ = writeln!(report, "Error querying devices: {err}");
+        }
+        report
+    }
+
+    /// Reports, for every physical device on the system, which of its queue families can
+    /// present to the surface. Lets an app warn early that the window can't be drawn to,
+    /// before picking a device for it.
+    pub fn surface_support_summary(&self, surface: &Surface) -> Result<Vec<SurfaceSupport>> {
+        let physical_devices = unsafe { self.instance.enumerate_physical_devices()? };
+        physical_devices
+            .into_iter()
+            .map(|physical_device| {
+                let properties = unsafe {
+                    self.instance
+                        .get_physical_device_properties(physical_device)
+                };
+                let queue_family_count = unsafe {
+                    self.instance
+                        .get_physical_device_queue_family_properties(physical_device)
+                        .len() as u32
+                };
+                let present_queue_families = (0..queue_family_count)
+                    .filter_map(|family_idx| {
+                        surface
+                            .get_physical_device_surface_support(&physical_device, family_idx)
+                            .map(|supported| supported.then_some(family_idx))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(SurfaceSupport {
+                    physical_device,
+                    device_name: properties
+                        .device_name_as_c_str()?
+                        .to_string_lossy()
+                        .into_owned(),
+                    present_queue_families,
+                })
+            })
+            .collect()
+    }
+
+    fn write_device_report(&self, report: &mut String) -> Result<()> {
+        let physical_devices = unsafe { self.instance.enumerate_physical_devices()? };
+        writeln!(report, "{} physical device(s)", physical_devices.len())?;
+        for (idx, physical_device) in physical_devices.into_iter().enumerate() {
+            let properties = unsafe {
+                self.instance
+                    .get_physical_device_properties(physical_device)
+            };
+            writeln!(
+                report,
+                "Device {idx}: {} ({:?})",
+                properties.device_name_as_c_str()?.to_string_lossy(),
+                properties.device_type
+            )?;
+            writeln!(
+                report,
+                "  API version: {}.{}.{}",
+                api_version_major(properties.api_version),
+                api_version_minor(properties.api_version),
+                api_version_patch(properties.api_version)
+            )?;
+            // the encoding is vendor specific, so this may not match the vendor's numbering
+            writeln!(
+                report,
+                "  Driver version: {}.{}.{} ({:#x})",
+                api_version_major(properties.driver_version),
+                api_version_minor(properties.driver_version),
+                api_version_patch(properties.driver_version),
+                properties.driver_version
+            )?;
+
+            let extension_properties = unsafe {
+                self.instance
+                    .enumerate_device_extension_properties(physical_device)?
+            };
+            let extension_names = extension_properties
+                .iter()
+                .map(|properties| properties.extension_name_as_c_str())
+                .collect::<Result<Vec<_>, _>>()?;
+            let missing_extensions = REQUIRED_DEVICE_EXTENSIONS
+                .iter()
+                .filter(|required| !extension_names.contains(required))
+                .map(|required| required.to_string_lossy())
+                .collect::<Vec<_>>();
+            if missing_extensions.is_empty() {
+                writeln!(report, "  Presentation: supported")?;
+            } else {
+                writeln!(
+                    report,
+                    "  Presentation: unsupported, missing {}",
+                    missing_extensions.join(", ")
+                )?;
+            }
+
+            let queue_family_properties = unsafe {
+                self.instance
+                    .get_physical_device_queue_family_properties(physical_device)
+            };
+            writeln!(report, "  Queue families:")?;
+            for (family_idx, queue_family) in queue_family_properties.iter().enumerate() {
+                writeln!(
+                    report,
+                    "    {family_idx}: {:?} ({} queue(s))",
+                    queue_family.queue_flags, queue_family.queue_count
+                )?;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the needed instance exensions for Vulkan to function correctly.
     /// These always require the extensions necessary to interact with the native
-    /// windowing system, and may include optional validation extensions if validations
-    /// are enabled.
-    fn get_required_instance_extensions(required_extensions: Vec<&str>) -> Result<Vec<&str>> {
+    /// windowing system, and include the DebugUtils and portability enumeration
+    /// extensions if asked for.
+    fn get_required_instance_extensions(
+        required_extensions: Vec<&str>,
+        debug_utils: bool,
+        portability_enumeration: bool,
+    ) -> Result<Vec<&str>> {
         let mut enabled_extension_names = required_extensions.clone();
-        if ENABLE_VALIDATIONS {
+        if debug_utils {
             enabled_extension_names.push(debug_utils::NAME.to_str()?);
         }
+        if portability_enumeration {
+            enabled_extension_names.push(KHR_PORTABILITY_ENUMERATION_NAME.to_str()?);
+        }
         Ok(enabled_extension_names)
     }
 
@@ -94,7 +403,7 @@ impl Instance {
             layer_names = vec!["VK_LAYER_KHRONOS_validation".to_owned()];
         }
         debug!("Layers to enable: {}", layer_names.join(", "));
-        return layer_names;
+        layer_names
     }
 }
 
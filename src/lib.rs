@@ -1,47 +1,168 @@
+mod app;
+mod buffer;
+mod color;
 mod command_pool;
+mod compute_pipeline;
+mod cubemap;
+mod debug_messenger;
+mod descriptor_allocator;
+mod descriptor_pool;
+mod descriptor_set_layout;
+mod draw_command;
+mod error;
+mod fence;
 mod frame;
+mod frame_limiter;
+mod frame_uniforms;
+#[cfg(feature = "gltf")]
+mod gltf_scene;
 mod graphics_pipeline;
+mod image;
+mod image_layout;
 mod image_view;
+mod indirect_draw;
 mod instance;
+mod logging;
 mod logical_device;
+mod mesh;
 mod physical_device_surface;
+mod pipeline_cache;
+mod push_descriptor;
+mod queue;
+mod render_step;
+mod renderer;
+mod resource_manager;
+mod sampler;
+mod scene;
+mod semaphore;
 mod shaders;
+mod shadow_map;
+mod static_commands;
+mod submit_batch;
 mod surface;
 mod swapchain;
+mod text;
+mod texture_array;
+mod timeline_semaphore;
+mod ui_pass;
+mod uniform_ring;
+mod vertex;
+mod viewport;
+mod window_config;
 
 use std::ffi::CStr;
 
-use anyhow::Result;
+pub use app::{App, EventHandler};
 use ash::vk::{
     Bool32, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
     DebugUtilsMessengerCallbackDataEXT, DebugUtilsMessengerCreateInfoEXT, KHR_SWAPCHAIN_NAME,
 };
-pub use command_pool::CommandPool;
-pub use graphics_pipeline::GraphicsPipeline;
-pub use image_view::ImageView;
-pub use instance::Instance;
+pub use buffer::Buffer;
+pub use color::{linear_to_srgb, srgb_to_linear};
+pub use command_pool::{CommandPool, FRAMES_IN_FLIGHT};
+pub use compute_pipeline::ComputePipeline;
+pub use cubemap::{Cubemap, CubemapFace};
+pub use debug_messenger::{DebugMessenger, DebugMessengerData};
+pub use descriptor_allocator::DescriptorAllocator;
+pub use descriptor_pool::DescriptorPool;
+pub use descriptor_set_layout::DescriptorSetLayout;
+pub use draw_command::DrawCommand;
+pub use error::RustyGamesError;
+pub use fence::Fence;
+pub use frame::DEFAULT_FRAME_TIMEOUT;
+pub use frame_limiter::FrameLimiter;
+pub use frame_uniforms::FrameUniforms;
+#[cfg(feature = "gltf")]
+pub use gltf_scene::{GltfScene, GltfVertex, Material};
+pub use graphics_pipeline::{
+    create_shader_module, BlendMode, ConservativeRasterization, DepthBias, DepthConfig,
+    GraphicsPipeline, PipelineConfig, PipelineLayout, RasterizationConfig, RenderPass,
+    RenderPassBuilder, RenderScale, ShaderStages, Specialization, StencilConfig, Subpass,
+    TessellationShaders, Topology,
+};
+pub use image::{Image, ImageDescriptor};
+pub use image_layout::transition_image_layout;
+pub use image_view::{ImageView, ImageViewConfig};
+pub use indirect_draw::{IndirectBuffer, IndirectDraw};
+pub use instance::{Instance, InstanceBuilder, SurfaceSupport};
+pub use logging::{init_logging, init_logging_with_level};
 pub use logical_device::LogicalDevice;
-pub use physical_device_surface::{PhysicalDeviceSurface, SwapChainSupportDetails};
-use simple_logger::{set_up_color_terminal, SimpleLogger};
+pub use mesh::Mesh;
+pub use physical_device_surface::{
+    ExtentClamping, HeapBudget, PhysicalDeviceSurface, RejectionReason, SwapChainSupportDetails,
+};
+pub use pipeline_cache::PipelineCache;
+pub use push_descriptor::PushDescriptor;
+pub use queue::Queue;
+pub use render_step::RenderStep;
+pub use renderer::Renderer;
+pub use resource_manager::{Handle, Pool, Resource, ResourceManager};
+pub use rusty_games_derive::Vertex;
+pub use sampler::Sampler;
+pub use scene::{Mat4, Node};
+pub use semaphore::Semaphore;
+pub use shadow_map::{ShadowMap, ShadowPass, ShadowPipeline};
+pub use static_commands::StaticCommands;
+pub use submit_batch::{Submission, SubmitBatch};
 pub use surface::Surface;
-pub use swapchain::Swapchain;
+pub use swapchain::{CompositeAlphaPreference, Swapchain, SwapchainObserver};
+pub use text::{BitmapFont, TextRenderer, TextVertex};
+pub use texture_array::TextureArray;
+pub use timeline_semaphore::TimelineSemaphore;
 use tracing::{event, Level};
+pub use ui_pass::UiPass;
+pub use uniform_ring::UniformRing;
+pub use vertex::{Vertex, VertexAttribute};
+pub use viewport::{SplitLayout, SplitScreen, ViewportExt};
+pub use window_config::{
+    available_video_modes, leave_disconnected_fullscreen, set_video_mode, set_window_mode,
+    MonitorVideoModes, WindowConfig, WindowIcon, WindowMode,
+};
 
-const REQUIRED_DEVICE_EXTENSIONS: &[&CStr] = &[KHR_SWAPCHAIN_NAME];
-
-pub fn init_logging() -> Result<()> {
-    set_up_color_terminal();
-    let logger = SimpleLogger::new();
-    logger.init()?;
-    Ok(())
+/// Used by the code generated by `#[derive(Vertex)]`, not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::vertex::{attribute_description_for, binding_description_for};
+    pub use ash::vk::{VertexInputAttributeDescription, VertexInputBindingDescription};
 }
 
+/// The pointer the renderer's objects share ownership of each other with, `Rc` by
+/// default. Because of it no object holding a `LogicalDevice` (or anything else shared)
+/// is `Send`, so the whole renderer stays on the thread that created it. Enable the
+/// `thread_safe` feature to make it `Arc` instead; the objects then drop in the same
+/// order, with the device destroyed once the last thing created from it is gone.
+#[cfg(not(feature = "thread_safe"))]
+pub type Shared<T> = std::rc::Rc<T>;
+#[cfg(feature = "thread_safe")]
+pub type Shared<T> = std::sync::Arc<T>;
+
+// the point of the feature, so it's checked when building it
+#[cfg(feature = "thread_safe")]
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Shared<Instance>>();
+    assert_send_sync::<Shared<LogicalDevice>>();
+};
+
+const REQUIRED_DEVICE_EXTENSIONS: &[&CStr] = &[KHR_SWAPCHAIN_NAME];
+
+/// Logs the messages from the DebugUtils extension via tracing. If the messenger was
+/// registered with user data, it's recorded into the [`DebugMessengerData`] as well.
+///
+/// # Safety
+///
+/// Must only be invoked by Vulkan. `p_user_data` must either be null or point to a
+/// [`DebugMessengerData`] that outlives the messenger.
 pub unsafe extern "system" fn vulkan_debug_utils_callback(
     message_severity: DebugUtilsMessageSeverityFlagsEXT,
     message_type: DebugUtilsMessageTypeFlagsEXT,
     p_callback_data: *const DebugUtilsMessengerCallbackDataEXT,
-    _p_user_data: *mut std::ffi::c_void,
+    p_user_data: *mut std::ffi::c_void,
 ) -> Bool32 {
+    if let Some(data) = (p_user_data as *const DebugMessengerData).as_ref() {
+        data.record(message_severity);
+    }
+
     let message = format!(
         "{:?}",
         std::ffi::CStr::from_ptr((*p_callback_data).p_message)
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use simple_logger::set_up_color_terminal;
+use tracing::{event, level_filters::LevelFilter, Level};
+use tracing_subscriber::EnvFilter;
+
+/// Prints the crate's log output to the terminal, at every level. See
+/// [`init_logging_with_level`].
+pub fn init_logging() -> Result<()> {
+    init_logging_with_level(Level::TRACE)
+}
+
+/// Prints the crate's log output at `level` and above to the terminal with a
+/// `tracing_subscriber` fmt subscriber, which also picks up dependencies logging thru
+/// `log`. The `RUST_LOG` environment variable overrides the level with [`EnvFilter`]
+/// directives, e.g. `warn` or `warn,rusty_games::frame=debug`; if they don't parse, a
+/// warning is logged and `level` is used instead. If the application
+/// already installed a subscriber or logger, that's kept and receives the output instead,
+/// so this is fine to call more than once.
+pub fn init_logging_with_level(level: Level) -> Result<()> {
+    set_up_color_terminal();
+    let fallback = || EnvFilter::default().add_directive(LevelFilter::from_level(level).into());
+    // an empty RUST_LOG would turn everything off, so it's treated as unset
+    let is_env_set = std::env::var_os(EnvFilter::DEFAULT_ENV).is_some_and(|env| !env.is_empty());
+    let (filter, env_error) = if is_env_set {
+        match EnvFilter::try_from_default_env() {
+            Ok(filter) => (filter, None),
+            Err(error) => (fallback(), Some(error)),
+        }
+    } else {
+        (fallback(), None)
+    };
+    // only errors if a subscriber or logger is installed already
+    if tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .try_init()
+        .is_err()
+    {
+        event!(
+            Level::DEBUG,
+            "A tracing subscriber is already installed, keeping it"
+        );
+    }
+    // logged once the subscriber is installed, so it's printed
+    if let Some(error) = env_error {
+        event!(
+            Level::WARN,
+            %error,
+            "Ignoring {} since it doesn't parse, logging at {level} instead",
+            EnvFilter::DEFAULT_ENV
+        );
+    }
+    Ok(())
+}
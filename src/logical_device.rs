@@ -1,21 +1,61 @@
-use std::{collections::HashSet, ops::Deref, rc::Rc};
+use std::{collections::HashSet, ffi::CString, ops::Deref};
 
-use anyhow::ensure;
+use anyhow::{anyhow, ensure, Result};
 use ash::{
-    vk::{DeviceCreateInfo, DeviceQueueCreateInfo, PhysicalDeviceFeatures, Queue},
+    ext::debug_utils,
+    khr::push_descriptor,
+    vk::{
+        DebugUtilsObjectNameInfoEXT, DeviceCreateInfo, DeviceQueueCreateInfo, Format,
+        FormatFeatureFlags, Handle, MemoryPropertyFlags, PhysicalDevice, PhysicalDeviceFeatures,
+        PhysicalDeviceLimits, PhysicalDeviceMemoryProperties, PhysicalDeviceProperties,
+        PhysicalDeviceVulkan12Features, EXT_CONSERVATIVE_RASTERIZATION_NAME,
+        KHR_PORTABILITY_SUBSET_NAME,
+    },
     Device,
 };
 
+#[cfg(feature = "dynamic_rendering")]
+use ash::vk::PhysicalDeviceVulkan13Features;
+
 use crate::{
-    physical_device_surface::QueueFamilyIndicies, Instance, PhysicalDeviceSurface, Surface,
-    SwapChainSupportDetails, REQUIRED_DEVICE_EXTENSIONS,
+    error::VkResultExt, physical_device_surface::QueueFamilyIndicies, HeapBudget, Instance,
+    PhysicalDeviceSurface, Queue, RustyGamesError, Shared, Surface, SwapChainSupportDetails,
+    REQUIRED_DEVICE_EXTENSIONS,
 };
 
 pub struct LogicalDevice {
-    _instance: Rc<Instance>,
+    instance: Shared<Instance>,
     device: Device,
     queue_handles: QueueHandles,
     physical_device_surface: PhysicalDeviceSurface,
+    /// The DebugUtils device functions, if the extension is enabled
+    debug_utils: Option<debug_utils::Device>,
+    /// The push descriptor device functions, if the device supports the extension
+    push_descriptor: Option<push_descriptor::Device>,
+    /// True if `VK_KHR_portability_subset` was enabled, as it must be when the device
+    /// implements it
+    portability_subset_enabled: bool,
+    /// How far conservative rasterization can grow primitives beyond the device's own
+    /// overestimation, if `VK_EXT_conservative_rasterization` was enabled
+    max_extra_primitive_overestimation_size: Option<f32>,
+    /// True if the timelineSemaphore feature was enabled
+    timeline_semaphore_enabled: bool,
+    /// True if the descriptor indexing features texture arrays need were enabled
+    descriptor_indexing_enabled: bool,
+    /// True if the multiDrawIndirect feature was enabled
+    multi_draw_indirect_enabled: bool,
+    /// True if the drawIndirectFirstInstance feature was enabled
+    draw_indirect_first_instance_enabled: bool,
+    /// True if the geometryShader feature was enabled
+    geometry_shader_enabled: bool,
+    /// True if the tessellationShader feature was enabled
+    tessellation_shader_enabled: bool,
+    /// True if the depthBiasClamp feature was enabled
+    depth_bias_clamp_enabled: bool,
+    /// The memory types and heaps of the physical device, which never change
+    memory_properties: PhysicalDeviceMemoryProperties,
+    /// The properties of the physical device, including its limits, which never change
+    properties: PhysicalDeviceProperties,
 }
 
 impl LogicalDevice {
@@ -23,17 +63,197 @@ impl LogicalDevice {
         &self.queue_handles
     }
 
-    pub fn get_surface(&self) -> &Rc<Surface> {
+    pub fn graphics_queue(&self) -> &Queue {
+        &self.queue_handles.graphics
+    }
+
+    pub fn present_queue(&self) -> &Queue {
+        &self.queue_handles.present
+    }
+
+    /// The queue from the compute family, if the device has one
+    pub fn compute_queue(&self) -> Option<&Queue> {
+        self.queue_handles.compute.as_ref()
+    }
+
+    /// The queue from the dedicated transfer family, if the device has one
+    pub fn transfer_queue(&self) -> Option<&Queue> {
+        self.queue_handles.transfer.as_ref()
+    }
+
+    pub fn get_surface(&self) -> &Shared<Surface> {
         self.physical_device_surface.get_surface()
     }
 
+    pub fn get_physical_device(&self) -> PhysicalDevice {
+        self.physical_device_surface.get_physical_device()
+    }
+
     pub fn get_queue_family_indicies(&self) -> &QueueFamilyIndicies {
         self.physical_device_surface.get_queue_family_indicies()
     }
 
+    /// See [`PhysicalDeviceSurface::memory_budget`]
+    pub fn memory_budget(&self) -> Result<Vec<HeapBudget>> {
+        self.physical_device_surface.memory_budget()
+    }
+
+    /// The support details captured when the device was picked. Formats and present modes
+    /// don't change, but the capabilities may be out of date.
+    #[deprecated(
+        note = "captured when the device was picked, so the capabilities (such as the current extent) may be stale; the swapchain queries its own"
+    )]
+    #[allow(deprecated)]
     pub fn get_swapchain_support_details(&self) -> &SwapChainSupportDetails {
         self.physical_device_surface.get_swapchain_support_details()
     }
+
+    pub fn is_timeline_semaphore_enabled(&self) -> bool {
+        self.timeline_semaphore_enabled
+    }
+
+    /// True if descriptor indexing was enabled, so [`crate::TextureArray`]s can be created
+    pub fn is_descriptor_indexing_enabled(&self) -> bool {
+        self.descriptor_indexing_enabled
+    }
+
+    /// True if indirect draws can draw more than one command per call. Otherwise
+    /// [`crate::IndirectDraw`]s are recorded as a call per command.
+    pub fn is_multi_draw_indirect_enabled(&self) -> bool {
+        self.multi_draw_indirect_enabled
+    }
+
+    /// True if indirect draw commands can have a nonzero `first_instance`
+    pub fn is_draw_indirect_first_instance_enabled(&self) -> bool {
+        self.draw_indirect_first_instance_enabled
+    }
+
+    /// True if pipelines can run geometry shaders, see [`crate::ShaderStages`]
+    pub fn is_geometry_shader_enabled(&self) -> bool {
+        self.geometry_shader_enabled
+    }
+
+    /// True if pipelines can run tessellation shaders, see [`crate::ShaderStages`]
+    pub fn is_tessellation_shader_enabled(&self) -> bool {
+        self.tessellation_shader_enabled
+    }
+
+    /// True if pipelines can clamp their depth bias, see [`crate::DepthBias`]
+    pub fn is_depth_bias_clamp_enabled(&self) -> bool {
+        self.depth_bias_clamp_enabled
+    }
+
+    /// True if `VK_KHR_push_descriptor` was enabled, so [`crate::PushDescriptor`]s can
+    /// be recorded
+    pub fn is_push_descriptor_enabled(&self) -> bool {
+        self.push_descriptor.is_some()
+    }
+
+    /// True if the device is a portability implementation like MoltenVK, which may not
+    /// support everything core Vulkan does
+    pub fn is_portability_subset_enabled(&self) -> bool {
+        self.portability_subset_enabled
+    }
+
+    /// True if `VK_EXT_conservative_rasterization` was enabled, so pipelines can be
+    /// created with [`crate::ConservativeRasterization`]
+    pub fn is_conservative_rasterization_enabled(&self) -> bool {
+        self.max_extra_primitive_overestimation_size.is_some()
+    }
+
+    /// Returns how far, in pixels, conservative rasterization can be asked to grow
+    /// primitives, if it's enabled
+    pub fn get_max_extra_primitive_overestimation_size(&self) -> Option<f32> {
+        self.max_extra_primitive_overestimation_size
+    }
+
+    /// Returns the push descriptor device functions, if the extension is enabled
+    pub(crate) fn get_push_descriptor(&self) -> Option<&push_descriptor::Device> {
+        self.push_descriptor.as_ref()
+    }
+
+    /// Returns the limits of the physical device, such as the alignment buffer offsets
+    /// need
+    pub fn get_limits(&self) -> &PhysicalDeviceLimits {
+        &self.properties.limits
+    }
+
+    /// Returns the index of the first memory type allowed by `type_filter` (a bitmask of
+    /// memory type indicies, as in `MemoryRequirements::memory_type_bits`) that has all
+    /// of the `properties`. Errors with [`RustyGamesError::NoSuitableMemoryType`] if none do.
+    pub fn find_memory_type(
+        &self,
+        type_filter: u32,
+        properties: MemoryPropertyFlags,
+    ) -> Result<u32> {
+        self.memory_properties
+            .memory_types_as_slice()
+            .iter()
+            .enumerate()
+            .position(|(idx, memory_type)| {
+                type_filter & (1 << idx) != 0 && memory_type.property_flags.contains(properties)
+            })
+            .map(|idx| idx as u32)
+            .ok_or_else(|| RustyGamesError::NoSuitableMemoryType.into())
+    }
+
+    /// Blocks until the device has finished all submitted work, so objects it may still
+    /// be using can be destroyed, e.g. before recreating the swapchain or reloading a
+    /// pipeline. This is a heavy sync point stalling every queue: when only one frame's
+    /// resources are involved, waiting on that frame's fence is much cheaper.
+    pub fn wait_idle(&self) -> Result<()> {
+        unsafe { self.device.device_wait_idle().map_device_lost()? };
+        Ok(())
+    }
+
+    /// Picks a combined depth and stencil format usable as an optimally tiled attachment,
+    /// preferring the smaller `D24_UNORM_S8_UINT`
+    pub fn find_depth_stencil_format(&self) -> Result<Format> {
+        [Format::D24_UNORM_S8_UINT, Format::D32_SFLOAT_S8_UINT]
+            .into_iter()
+            .find(|format| {
+                self.get_format_features(*format)
+                    .contains(FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+            })
+            .ok_or_else(|| anyhow!("Could not find a supported depth stencil format!"))
+    }
+
+    /// Returns the first depth-only format the device can both render to and sample from,
+    /// for shadow maps
+    pub fn find_sampled_depth_format(&self) -> Result<Format> {
+        [Format::D32_SFLOAT, Format::D16_UNORM]
+            .into_iter()
+            .find(|format| {
+                self.get_format_features(*format).contains(
+                    FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                        | FormatFeatureFlags::SAMPLED_IMAGE,
+                )
+            })
+            .ok_or_else(|| anyhow!("Could not find a sampleable depth format!"))
+    }
+
+    /// Returns what images of the format with optimal tiling support
+    pub fn get_format_features(&self, format: Format) -> FormatFeatureFlags {
+        let format_properties = unsafe {
+            self.instance
+                .get_physical_device_format_properties(self.get_physical_device(), format)
+        };
+        format_properties.optimal_tiling_features
+    }
+
+    /// Gives the object a readable name, which is used in validation messages and
+    /// tools like RenderDoc instead of the opaque handle. Does nothing if the
+    /// DebugUtils extension isn't enabled.
+    pub fn set_object_name<T: Handle>(&self, handle: T, name: &str) -> Result<()> {
+        if let Some(debug_utils) = &self.debug_utils {
+            let name = CString::new(name)?;
+            let name_info = DebugUtilsObjectNameInfoEXT::default()
+                .object_handle(handle)
+                .object_name(&name);
+            unsafe { debug_utils.set_debug_utils_object_name(&name_info)? };
+        }
+        Ok(())
+    }
 }
 
 impl TryFrom<PhysicalDeviceSurface> for LogicalDevice {
@@ -43,10 +263,16 @@ impl TryFrom<PhysicalDeviceSurface> for LogicalDevice {
         let indicies = physical_device_surface.get_queue_family_indicies();
         ensure!(indicies.is_complete());
 
-        let unique_queue_family_indicies = HashSet::from([
+        let mut unique_queue_family_indicies = HashSet::from([
             indicies.graphics_family.unwrap() as u32,
             indicies.present_family.unwrap() as u32,
         ]);
+        unique_queue_family_indicies.extend(
+            [indicies.compute_family, indicies.transfer_family]
+                .into_iter()
+                .flatten()
+                .map(|queue_family_index| queue_family_index as u32),
+        );
 
         let queue_priorities = [1.0f32];
         let device_queue_creation_infos = unique_queue_family_indicies
@@ -58,17 +284,76 @@ impl TryFrom<PhysicalDeviceSurface> for LogicalDevice {
             })
             .collect::<Vec<_>>();
 
-        let physical_device_features = PhysicalDeviceFeatures::default();
+        // indirect draws work without these, just with a call per command and no
+        // instancing offsets
+        let multi_draw_indirect_enabled = physical_device_surface.supports_multi_draw_indirect();
+        let draw_indirect_first_instance_enabled =
+            physical_device_surface.supports_draw_indirect_first_instance();
+        // only needed by pipelines with the extra shader stages
+        let geometry_shader_enabled = physical_device_surface.supports_geometry_shaders();
+        let tessellation_shader_enabled = physical_device_surface.supports_tessellation_shaders();
+        // depth bias works without it, just unclamped
+        let depth_bias_clamp_enabled = physical_device_surface.supports_depth_bias_clamp();
+        let physical_device_features = PhysicalDeviceFeatures::default()
+            .multi_draw_indirect(multi_draw_indirect_enabled)
+            .draw_indirect_first_instance(draw_indirect_first_instance_enabled)
+            .geometry_shader(geometry_shader_enabled)
+            .tessellation_shader(tessellation_shader_enabled)
+            .depth_bias_clamp(depth_bias_clamp_enabled);
+        // enable timeline semaphores when available, falling back to binary
+        // semaphores and fences otherwise
+        let timeline_semaphore_enabled = physical_device_surface.supports_timeline_semaphores();
+        // likewise for descriptor indexing, thru the 1.2 features rather than
+        // PhysicalDeviceDescriptorIndexingFeatures, since both can't be chained
+        let descriptor_indexing_enabled = physical_device_surface.supports_descriptor_indexing();
+        let mut vulkan_12_features = PhysicalDeviceVulkan12Features::default()
+            .timeline_semaphore(timeline_semaphore_enabled)
+            .descriptor_indexing(descriptor_indexing_enabled)
+            .descriptor_binding_partially_bound(descriptor_indexing_enabled)
+            .descriptor_binding_variable_descriptor_count(descriptor_indexing_enabled)
+            .runtime_descriptor_array(descriptor_indexing_enabled)
+            .shader_sampled_image_array_non_uniform_indexing(descriptor_indexing_enabled);
 
-        let extension_names = REQUIRED_DEVICE_EXTENSIONS
+        // push descriptors are optional, only enabled when the device has them
+        let push_descriptor_enabled = physical_device_surface.supports_push_descriptors()?;
+        let mut extension_names = REQUIRED_DEVICE_EXTENSIONS
             .iter()
             .map(|extension_name| (**extension_name).as_ptr())
             .collect::<Vec<_>>();
+        if push_descriptor_enabled {
+            extension_names.push(push_descriptor::NAME.as_ptr());
+        }
+        // devices implementing the subset, like MoltenVK, require it to be enabled
+        let portability_subset_enabled = physical_device_surface.supports_portability_subset()?;
+        if portability_subset_enabled {
+            extension_names.push(KHR_PORTABILITY_SUBSET_NAME.as_ptr());
+        }
+        // only needed by pipelines rasterizing conservatively
+        let max_extra_primitive_overestimation_size =
+            if physical_device_surface.supports_conservative_rasterization()? {
+                extension_names.push(EXT_CONSERVATIVE_RASTERIZATION_NAME.as_ptr());
+                Some(physical_device_surface.get_max_extra_primitive_overestimation_size())
+            } else {
+                None
+            };
 
-        let device_create_info = DeviceCreateInfo::default()
+        let mut device_create_info = DeviceCreateInfo::default()
             .queue_create_infos(&device_queue_creation_infos)
             .enabled_features(&physical_device_features)
             .enabled_extension_names(&extension_names);
+        // the struct is only valid on devices supporting Vulkan 1.2, which either feature
+        // being enabled implies
+        if timeline_semaphore_enabled || descriptor_indexing_enabled {
+            device_create_info = device_create_info.push_next(&mut vulkan_12_features);
+        }
+        // required for drawing without render pass objects, and checked for when picking
+        // the device. only chained when needed, since the struct is only valid on devices
+        // supporting Vulkan 1.3
+        #[cfg(feature = "dynamic_rendering")]
+        let mut vulkan_13_features =
+            PhysicalDeviceVulkan13Features::default().dynamic_rendering(true);
+        #[cfg(feature = "dynamic_rendering")]
+        let device_create_info = device_create_info.push_next(&mut vulkan_13_features);
 
         let logical_device = unsafe {
             physical_device_surface.instance.create_device(
@@ -78,23 +363,80 @@ impl TryFrom<PhysicalDeviceSurface> for LogicalDevice {
             )
         }?;
 
-        let graphics_queue_handle =
-            unsafe { logical_device.get_device_queue(indicies.graphics_family.unwrap() as u32, 0) };
-        let present_queue_handle =
-            unsafe { logical_device.get_device_queue(indicies.present_family.unwrap() as u32, 0) };
+        // remember what each queue's family supports, so work can't be sent to the wrong one
+        let queue_family_properties = unsafe {
+            physical_device_surface
+                .instance
+                .get_physical_device_queue_family_properties(
+                    physical_device_surface.get_physical_device(),
+                )
+        };
+        let present_family = indicies.present_family.unwrap();
+        let get_queue = |queue_family_index: usize| {
+            let queue = unsafe { logical_device.get_device_queue(queue_family_index as u32, 0) };
+            Queue::new(
+                queue,
+                queue_family_index as u32,
+                queue_family_properties[queue_family_index].queue_flags,
+                queue_family_index == present_family,
+            )
+        };
         let queue_handles = QueueHandles {
-            graphics: graphics_queue_handle,
-            present: present_queue_handle,
+            graphics: get_queue(indicies.graphics_family.unwrap()),
+            present: get_queue(present_family),
+            compute: indicies.compute_family.map(get_queue),
+            transfer: indicies.transfer_family.map(get_queue),
         };
 
-        let instance = Rc::clone(&physical_device_surface.instance);
+        let instance = Shared::clone(&physical_device_surface.instance);
+        let debug_utils = instance
+            .is_debug_utils_enabled()
+            .then(|| debug_utils::Device::new(&instance, &logical_device));
+        let push_descriptor = push_descriptor_enabled
+            .then(|| push_descriptor::Device::new(&instance, &logical_device));
+
+        let memory_properties = unsafe {
+            instance.get_physical_device_memory_properties(
+                physical_device_surface.get_physical_device(),
+            )
+        };
+        let properties = unsafe {
+            instance.get_physical_device_properties(physical_device_surface.get_physical_device())
+        };
 
-        Ok(Self {
-            _instance: instance,
+        let device = Self {
+            instance,
             device: logical_device,
             queue_handles,
             physical_device_surface,
-        })
+            debug_utils,
+            push_descriptor,
+            portability_subset_enabled,
+            max_extra_primitive_overestimation_size,
+            timeline_semaphore_enabled,
+            descriptor_indexing_enabled,
+            multi_draw_indirect_enabled,
+            draw_indirect_first_instance_enabled,
+            geometry_shader_enabled,
+            tessellation_shader_enabled,
+            depth_bias_clamp_enabled,
+            memory_properties,
+            properties,
+        };
+        device.set_object_name(*device.queue_handles.graphics, "graphics queue")?;
+        if device.queue_handles.present != device.queue_handles.graphics {
+            device.set_object_name(*device.queue_handles.present, "present queue")?;
+        }
+        if let Some(compute) = device.queue_handles.compute {
+            if compute != device.queue_handles.graphics {
+                device.set_object_name(*compute, "compute queue")?;
+            }
+        }
+        if let Some(transfer) = device.queue_handles.transfer {
+            device.set_object_name(*transfer, "transfer queue")?;
+        }
+
+        Ok(device)
     }
 }
 
@@ -115,4 +457,8 @@ impl Deref for LogicalDevice {
 pub struct QueueHandles {
     pub graphics: Queue,
     pub present: Queue,
+    /// Queue from the compute family, if the device has one
+    pub compute: Option<Queue>,
+    /// Queue from the dedicated transfer family, if the device has one
+    pub transfer: Option<Queue>,
 }
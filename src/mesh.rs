@@ -0,0 +1,145 @@
+use std::mem::size_of_val;
+
+use anyhow::{ensure, Result};
+use ash::vk::{
+    BufferUsageFlags, CommandBuffer, DeviceSize, IndexType, MemoryPropertyFlags, PipelineLayout,
+    ShaderStageFlags,
+};
+
+use crate::{Buffer, CommandPool, LogicalDevice, Mat4, Shared, Vertex};
+
+/// Indexed geometry in device local vertex and index buffers, such as one primitive of
+/// a loaded model, along with the index of the material it's drawn with
+pub struct Mesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    /// Which of the model's materials the mesh uses, if any
+    material_index: Option<usize>,
+}
+
+impl Mesh {
+    /// Uploads the vertices and indicies thru staging buffers, blocking until the copies
+    /// have finished. `V` must match the vertex layout of the pipelines drawing the mesh.
+    pub fn new<V: Vertex + Copy>(
+        logical_device: &Shared<LogicalDevice>,
+        command_pool: &CommandPool,
+        vertices: &[V],
+        indices: &[u32],
+        material_index: Option<usize>,
+    ) -> Result<Self> {
+        ensure!(!vertices.is_empty(), "Meshes need at least one vertex!");
+        ensure!(!indices.is_empty(), "Meshes need at least one index!");
+        ensure!(
+            indices
+                .iter()
+                .all(|index| (*index as usize) < vertices.len()),
+            "Mesh indicies refer to vertices past the {} given!",
+            vertices.len()
+        );
+
+        let vertex_buffer = upload(
+            logical_device,
+            command_pool,
+            vertices,
+            BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        let index_buffer = upload(
+            logical_device,
+            command_pool,
+            indices,
+            BufferUsageFlags::INDEX_BUFFER,
+        )?;
+
+        Ok(Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            material_index,
+        })
+    }
+
+    /// Binds the mesh's buffers and records drawing it. A pipeline with a matching
+    /// vertex layout must already be bound.
+    pub fn record_draw(&self, logical_device: &LogicalDevice, command_buffer: CommandBuffer) {
+        unsafe {
+            logical_device.cmd_bind_vertex_buffers(command_buffer, 0, &[*self.vertex_buffer], &[0]);
+            logical_device.cmd_bind_index_buffer(
+                command_buffer,
+                *self.index_buffer,
+                0,
+                IndexType::UINT32,
+            );
+            logical_device.cmd_draw_indexed(command_buffer, self.index_count, 1, 0, 0, 0);
+        }
+    }
+
+    /// Like [`Mesh::record_draw`], first pushing the world transform (for instance from
+    /// [`crate::Node::traverse`]) as a `mat4` push constant at offset 0 of the vertex
+    /// stage. The pipeline layout must have a push constant range covering it.
+    pub fn record_draw_with_transform(
+        &self,
+        logical_device: &LogicalDevice,
+        command_buffer: CommandBuffer,
+        pipeline_layout: PipelineLayout,
+        world_transform: &Mat4,
+    ) {
+        let floats = world_transform.to_cols_array();
+        let bytes = floats
+            .iter()
+            .flat_map(|float| float.to_ne_bytes())
+            .collect::<Vec<_>>();
+        unsafe {
+            logical_device.cmd_push_constants(
+                command_buffer,
+                pipeline_layout,
+                ShaderStageFlags::VERTEX,
+                0,
+                &bytes,
+            )
+        };
+        self.record_draw(logical_device, command_buffer);
+    }
+
+    pub fn get_vertex_buffer(&self) -> &Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn get_index_buffer(&self) -> &Buffer {
+        &self.index_buffer
+    }
+
+    pub fn get_index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    pub fn get_material_index(&self) -> Option<usize> {
+        self.material_index
+    }
+}
+
+/// Creates a device local buffer with the data, copied in from a staging buffer
+fn upload<T: Copy>(
+    logical_device: &Shared<LogicalDevice>,
+    command_pool: &CommandPool,
+    data: &[T],
+    usage: BufferUsageFlags,
+) -> Result<Buffer> {
+    let size = size_of_val(data) as DeviceSize;
+    let staging_buffer = Buffer::new(
+        logical_device,
+        size,
+        BufferUsageFlags::TRANSFER_SRC,
+        MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+    staging_buffer.write_slice(0, data)?;
+
+    let buffer = Buffer::new(
+        logical_device,
+        size,
+        usage | BufferUsageFlags::TRANSFER_DST,
+        MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+    command_pool.copy_buffer(*staging_buffer, *buffer, size)?;
+    Ok(buffer)
+}
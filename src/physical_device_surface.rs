@@ -1,20 +1,72 @@
-use std::{collections::HashSet, ffi::CString, rc::Rc};
+use std::{
+    collections::HashSet,
+    ffi::CString,
+    fmt::{self, Display, Formatter},
+};
 
 use anyhow::Result;
 use ash::vk::{
-    ColorSpaceKHR, Extent2D, Format, PhysicalDevice, PresentModeKHR, QueueFlags,
-    SurfaceCapabilitiesKHR, SurfaceFormatKHR,
+    ColorSpaceKHR, CompositeAlphaFlagsKHR, Extent2D, Format, MemoryHeapFlags, PhysicalDevice,
+    PhysicalDeviceConservativeRasterizationPropertiesEXT, PhysicalDeviceFeatures2,
+    PhysicalDeviceMemoryBudgetPropertiesEXT, PhysicalDeviceMemoryProperties2,
+    PhysicalDeviceProperties2, PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features,
+    PresentModeKHR, QueueFlags, SurfaceCapabilitiesKHR, SurfaceFormatKHR, API_VERSION_1_2,
+    API_VERSION_1_3, EXT_CONSERVATIVE_RASTERIZATION_NAME, EXT_MEMORY_BUDGET_NAME,
+    KHR_PORTABILITY_SUBSET_NAME, KHR_PUSH_DESCRIPTOR_NAME, TRUE,
 };
-use winit::window::Window;
+use tracing::warn;
+use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::{Instance, Surface, REQUIRED_DEVICE_EXTENSIONS};
+use crate::{
+    CompositeAlphaPreference, Instance, RustyGamesError, Shared, Surface,
+    REQUIRED_DEVICE_EXTENSIONS,
+};
+
+/// How a window size outside a surface's supported extents is brought into range, see
+/// [`SwapChainSupportDetails::choose_swap_extent`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExtentClamping {
+    /// Clamps the width and height separately, using as much of the range as possible
+    /// but distorting the image if only one side is clamped
+    #[default]
+    Independent,
+    /// Scales the size down uniformly until it fits under the maximum, so the image keeps
+    /// the window's aspect ratio, then clamps it up to the minimum
+    PreserveAspectRatio,
+}
+
+impl ExtentClamping {
+    /// Clamps `extent` to between `min` and `max`:
+    ///
+    /// - `3000x1000` in `max = 2000x2000` is `2000x1000` clamped independently, and
+    ///   `2000x666` preserving the aspect ratio
+    /// - `1000x500` in `max = 2000x2000` is unchanged either way
+    pub fn clamp(&self, extent: Extent2D, min: Extent2D, max: Extent2D) -> Extent2D {
+        let extent = match self {
+            ExtentClamping::Independent => extent,
+            ExtentClamping::PreserveAspectRatio => {
+                let scale = (max.width as f64 / extent.width.max(1) as f64)
+                    .min(max.height as f64 / extent.height.max(1) as f64)
+                    .min(1.0);
+                Extent2D {
+                    width: (extent.width as f64 * scale) as u32,
+                    height: (extent.height as f64 * scale) as u32,
+                }
+            }
+        };
+        Extent2D {
+            width: extent.width.clamp(min.width, max.width),
+            height: extent.height.clamp(min.height, max.height),
+        }
+    }
+}
 
 /// Struct representing the intersection of a physical device and
 /// presentation surface. There should be one per surface to display
 /// results on, and per physical device.
 pub struct PhysicalDeviceSurface {
-    pub(crate) instance: Rc<Instance>,
-    surface: Rc<Surface>,
+    pub(crate) instance: Shared<Instance>,
+    surface: Shared<Surface>,
     physical_device: PhysicalDevice,
     queue_families: QueueFamilyIndicies,
     swapchain_support_details: SwapChainSupportDetails,
@@ -22,30 +74,52 @@ pub struct PhysicalDeviceSurface {
 
 impl PhysicalDeviceSurface {
     pub fn new(
-        instance: &Rc<Instance>,
-        surface: &Rc<Surface>,
+        instance: &Shared<Instance>,
+        surface: &Shared<Surface>,
         physical_device: PhysicalDevice,
     ) -> Result<Self> {
-        let queue_families = find_queue_families(instance, &physical_device, &surface);
+        let queue_families = find_queue_families(instance, &physical_device, surface);
         let swapchain_support_details = query_swap_chain_support(&physical_device, surface)?;
         Ok(Self {
-            instance: Rc::clone(instance),
-            surface: Rc::clone(surface),
+            instance: Shared::clone(instance),
+            surface: Shared::clone(surface),
             physical_device,
             queue_families,
             swapchain_support_details,
         })
     }
 
-    pub fn is_suitable(&self) -> Result<bool> {
-        let supports_extensions = self.check_device_extensions_supported()?;
-        let mut swap_chain_supported = false;
-        if supports_extensions {
-            swap_chain_supported = !self.swapchain_support_details.formats.is_empty()
-                && !self.swapchain_support_details.present_modes.is_empty();
+    /// Checks whether the device can render to the surface, returning why not if it
+    /// can't. Errors only if querying the device fails.
+    pub fn is_suitable(&self) -> Result<Result<(), RejectionReason>> {
+        if let Some(missing_extension) = self.find_missing_device_extension()? {
+            return Ok(Err(RejectionReason::MissingExtension(missing_extension)));
+        }
+        if self.queue_families.graphics_family.is_none() {
+            return Ok(Err(RejectionReason::NoGraphicsFamily));
+        }
+        if self.queue_families.present_family.is_none() {
+            return Ok(Err(RejectionReason::NoPresentFamily));
+        }
+        if self.swapchain_support_details.formats.is_empty() {
+            return Ok(Err(RejectionReason::NoSurfaceFormats));
         }
+        if self.swapchain_support_details.present_modes.is_empty() {
+            return Ok(Err(RejectionReason::NoPresentModes));
+        }
+        if cfg!(feature = "dynamic_rendering") && !self.supports_dynamic_rendering() {
+            return Ok(Err(RejectionReason::MissingFeature("dynamicRendering")));
+        }
+        Ok(Ok(()))
+    }
 
-        Ok(self.queue_families.is_complete() && supports_extensions && swap_chain_supported)
+    /// Returns the device's name, as reported by the driver
+    pub fn get_device_name(&self) -> Result<String> {
+        let properties = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+        };
+        Ok(properties.device_name_as_c_str()?.to_str()?.to_owned())
     }
 
     pub fn get_queue_family_indicies(&self) -> &QueueFamilyIndicies {
@@ -56,16 +130,214 @@ impl PhysicalDeviceSurface {
         self.physical_device
     }
 
+    /// The support details captured when the device was queried. Formats and present modes
+    /// don't change, but the capabilities may be out of date.
+    #[deprecated(
+        note = "captured when the device was picked, so the capabilities (such as the current extent) may be stale; the swapchain queries its own"
+    )]
     pub fn get_swapchain_support_details(&self) -> &SwapChainSupportDetails {
         &self.swapchain_support_details
     }
 
-    pub fn get_surface(&self) -> &Rc<Surface> {
+    pub fn get_surface(&self) -> &Shared<Surface> {
         &self.surface
     }
 
-    /// Checks to see if the physical device supports all required device extensions
-    fn check_device_extensions_supported(&self) -> Result<bool> {
+    /// Returns the highest Vulkan version the device supports
+    pub fn get_api_version(&self) -> u32 {
+        let properties = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+        };
+        properties.api_version
+    }
+
+    /// True if the device supports timeline semaphores, which are core in Vulkan 1.2
+    /// but still an optional feature. Devices older than 1.2 fall back to binary
+    /// semaphores and fences.
+    pub fn supports_timeline_semaphores(&self) -> bool {
+        // the 1.2 features can't be queried from older devices
+        if self.get_api_version() < API_VERSION_1_2 {
+            return false;
+        }
+        let mut vulkan_12_features = PhysicalDeviceVulkan12Features::default();
+        let mut features = PhysicalDeviceFeatures2::default().push_next(&mut vulkan_12_features);
+        unsafe {
+            self.instance
+                .get_physical_device_features2(self.physical_device, &mut features)
+        };
+        vulkan_12_features.timeline_semaphore == TRUE
+    }
+
+    /// True if the device supports drawing several commands per indirect draw call
+    pub fn supports_multi_draw_indirect(&self) -> bool {
+        let features = unsafe {
+            self.instance
+                .get_physical_device_features(self.physical_device)
+        };
+        features.multi_draw_indirect == TRUE
+    }
+
+    /// True if the device supports geometry shaders
+    pub fn supports_geometry_shaders(&self) -> bool {
+        let features = unsafe {
+            self.instance
+                .get_physical_device_features(self.physical_device)
+        };
+        features.geometry_shader == TRUE
+    }
+
+    /// True if the device supports tessellation control and evaluation shaders
+    pub fn supports_tessellation_shaders(&self) -> bool {
+        let features = unsafe {
+            self.instance
+                .get_physical_device_features(self.physical_device)
+        };
+        features.tessellation_shader == TRUE
+    }
+
+    /// True if the device supports clamping the depth bias pipelines add
+    pub fn supports_depth_bias_clamp(&self) -> bool {
+        let features = unsafe {
+            self.instance
+                .get_physical_device_features(self.physical_device)
+        };
+        features.depth_bias_clamp == TRUE
+    }
+
+    /// True if the device supports indirect draw commands with a nonzero first instance
+    pub fn supports_draw_indirect_first_instance(&self) -> bool {
+        let features = unsafe {
+            self.instance
+                .get_physical_device_features(self.physical_device)
+        };
+        features.draw_indirect_first_instance == TRUE
+    }
+
+    /// True if the device supports the parts of descriptor indexing (core in Vulkan 1.2,
+    /// but optional) needed for [`crate::TextureArray`]: partially bound, variably sized
+    /// arrays of sampled images, indexed non-uniformly from shaders
+    pub fn supports_descriptor_indexing(&self) -> bool {
+        // the 1.2 features can't be queried from older devices
+        if self.get_api_version() < API_VERSION_1_2 {
+            return false;
+        }
+        let mut vulkan_12_features = PhysicalDeviceVulkan12Features::default();
+        let mut features = PhysicalDeviceFeatures2::default().push_next(&mut vulkan_12_features);
+        unsafe {
+            self.instance
+                .get_physical_device_features2(self.physical_device, &mut features)
+        };
+        vulkan_12_features.descriptor_indexing == TRUE
+            && vulkan_12_features.descriptor_binding_partially_bound == TRUE
+            && vulkan_12_features.descriptor_binding_variable_descriptor_count == TRUE
+            && vulkan_12_features.runtime_descriptor_array == TRUE
+            && vulkan_12_features.shader_sampled_image_array_non_uniform_indexing == TRUE
+    }
+
+    /// True if the device supports dynamic rendering, which is core in Vulkan 1.3 but
+    /// only guaranteed on devices supporting 1.3
+    pub fn supports_dynamic_rendering(&self) -> bool {
+        // the 1.3 features can't be queried from older devices
+        if self.get_api_version() < API_VERSION_1_3 {
+            return false;
+        }
+        let mut vulkan_13_features = PhysicalDeviceVulkan13Features::default();
+        let mut features = PhysicalDeviceFeatures2::default().push_next(&mut vulkan_13_features);
+        unsafe {
+            self.instance
+                .get_physical_device_features2(self.physical_device, &mut features)
+        };
+        vulkan_13_features.dynamic_rendering == TRUE
+    }
+
+    /// True if the device supports `VK_KHR_push_descriptor`, for writing descriptors
+    /// straight into command buffers
+    pub fn supports_push_descriptors(&self) -> Result<bool> {
+        Ok(self
+            .available_device_extensions()?
+            .contains(KHR_PUSH_DESCRIPTOR_NAME))
+    }
+
+    /// True if the device implements `VK_KHR_portability_subset`, as MoltenVK does, which
+    /// then has to be enabled on the logical device
+    pub fn supports_portability_subset(&self) -> Result<bool> {
+        Ok(self
+            .available_device_extensions()?
+            .contains(KHR_PORTABILITY_SUBSET_NAME))
+    }
+
+    /// True if the device supports `VK_EXT_conservative_rasterization`, for rasterizing
+    /// every pixel a primitive touches rather than only those whose center it covers
+    pub fn supports_conservative_rasterization(&self) -> Result<bool> {
+        Ok(self
+            .available_device_extensions()?
+            .contains(EXT_CONSERVATIVE_RASTERIZATION_NAME))
+    }
+
+    /// Returns how far, in pixels, conservative rasterization can be asked to grow
+    /// primitives beyond the size the device already grows them by. Only meaningful if
+    /// [`PhysicalDeviceSurface::supports_conservative_rasterization`].
+    pub fn get_max_extra_primitive_overestimation_size(&self) -> f32 {
+        let mut conservative_rasterization_properties =
+            PhysicalDeviceConservativeRasterizationPropertiesEXT::default();
+        let mut properties = PhysicalDeviceProperties2::default()
+            .push_next(&mut conservative_rasterization_properties);
+        unsafe {
+            self.instance
+                .get_physical_device_properties2(self.physical_device, &mut properties)
+        };
+        conservative_rasterization_properties.max_extra_primitive_overestimation_size
+    }
+
+    /// Reports how much memory each heap of the device has, and if `VK_EXT_memory_budget`
+    /// is supported, how much of it is in use and how much the process can use. Budgets
+    /// and usage change over time (and with other processes), so query this as needed.
+    pub fn memory_budget(&self) -> Result<Vec<HeapBudget>> {
+        let supports_memory_budget = self
+            .available_device_extensions()?
+            .contains(EXT_MEMORY_BUDGET_NAME);
+
+        let mut memory_budget_properties = PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut memory_properties = PhysicalDeviceMemoryProperties2::default();
+        if supports_memory_budget {
+            memory_properties = memory_properties.push_next(&mut memory_budget_properties);
+        }
+        unsafe {
+            self.instance.get_physical_device_memory_properties2(
+                self.physical_device,
+                &mut memory_properties,
+            )
+        };
+
+        let memory_heaps = memory_properties.memory_properties.memory_heaps_as_slice();
+        let heap_budgets = memory_heaps
+            .iter()
+            .enumerate()
+            .map(|(idx, memory_heap)| HeapBudget {
+                size: memory_heap.size,
+                flags: memory_heap.flags,
+                budget: supports_memory_budget.then(|| memory_budget_properties.heap_budget[idx]),
+                usage: supports_memory_budget.then(|| memory_budget_properties.heap_usage[idx]),
+            })
+            .collect();
+        Ok(heap_budgets)
+    }
+
+    /// Returns the name of the first required device extension the physical device
+    /// doesn't support, if any
+    fn find_missing_device_extension(&self) -> Result<Option<String>> {
+        let device_extension_names = self.available_device_extensions()?;
+        for required_extension in REQUIRED_DEVICE_EXTENSIONS {
+            if !device_extension_names.contains(*required_extension) {
+                return Ok(Some(required_extension.to_str()?.to_owned()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the names of all the extensions the physical device supports
+    fn available_device_extensions(&self) -> Result<HashSet<CString>> {
         let device_extension_properties = unsafe {
             self.instance
                 .enumerate_device_extension_properties(self.physical_device)?
@@ -76,22 +348,65 @@ impl PhysicalDeviceSurface {
             let extension_name = device_extension.extension_name_as_c_str()?;
             device_extension_names.insert(extension_name.to_owned());
         }
+        Ok(device_extension_names)
+    }
+}
 
-        for required_extension in REQUIRED_DEVICE_EXTENSIONS {
-            let required_extension_name: CString = (*required_extension).to_owned();
-            if !device_extension_names.contains(&required_extension_name) {
-                return Ok(false);
+/// Why a physical device can't be used to render to a surface
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The device doesn't support a required device extension, named here
+    MissingExtension(String),
+    /// The device doesn't support a required device feature, named here
+    MissingFeature(&'static str),
+    /// None of the device's queue families support graphics
+    NoGraphicsFamily,
+    /// None of the device's queue families can present to the surface
+    NoPresentFamily,
+    /// The surface reports no formats for the device
+    NoSurfaceFormats,
+    /// The surface reports no present modes for the device
+    NoPresentModes,
+}
+
+impl Display for RejectionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectionReason::MissingExtension(extension) => {
+                write!(f, "missing device extension {extension}")
             }
+            RejectionReason::MissingFeature(feature) => {
+                write!(f, "missing device feature {feature}")
+            }
+            RejectionReason::NoGraphicsFamily => write!(f, "no queue family supports graphics"),
+            RejectionReason::NoPresentFamily => {
+                write!(f, "no queue family can present to the surface")
+            }
+            RejectionReason::NoSurfaceFormats => write!(f, "the surface has no formats"),
+            RejectionReason::NoPresentModes => write!(f, "the surface has no present modes"),
         }
-        Ok(true)
     }
 }
 
+/// How much memory a heap of the device has
+#[derive(Clone, Copy, Debug)]
+pub struct HeapBudget {
+    /// Total size of the heap in bytes
+    pub size: u64,
+    pub flags: MemoryHeapFlags,
+    /// How many bytes the process can allocate from the heap before allocations may fail
+    /// or perform poorly. None if `VK_EXT_memory_budget` isn't supported.
+    pub budget: Option<u64>,
+    /// How many bytes of the heap the process is currently using. None if
+    /// `VK_EXT_memory_budget` isn't supported.
+    pub usage: Option<u64>,
+}
+
 /// Queries the Queue Families the physica device supports, and records the index of the relevant ones.
 fn find_queue_families(
     instance: &Instance,
     physical_device: &PhysicalDevice,
-    surface: &Rc<Surface>,
+    surface: &Shared<Surface>,
 ) -> QueueFamilyIndicies {
     let queue_family_properties =
         unsafe { instance.get_physical_device_queue_family_properties(*physical_device) };
@@ -107,12 +422,33 @@ fn find_queue_families(
                     .get_physical_device_surface_support(physical_device, idx as u32)
                     .unwrap_or_default()
             }),
+        // prefer a family without graphics support, so compute work can run
+        // asynchronously alongside rendering
+        compute_family: queue_family_properties
+            .iter()
+            .position(|qfp| {
+                qfp.queue_flags.contains(QueueFlags::COMPUTE)
+                    && !qfp.queue_flags.contains(QueueFlags::GRAPHICS)
+            })
+            .or_else(|| {
+                queue_family_properties
+                    .iter()
+                    .position(|qfp| qfp.queue_flags.contains(QueueFlags::COMPUTE))
+            }),
+        // only a dedicated family is useful here, since graphics and compute
+        // families implicitly support transfers anyway
+        transfer_family: queue_family_properties.iter().position(|qfp| {
+            qfp.queue_flags.contains(QueueFlags::TRANSFER)
+                && !qfp
+                    .queue_flags
+                    .intersects(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)
+        }),
     }
 }
 
 /// Queries for the details of what the swap chain supports given
 /// the physical device and surface
-fn query_swap_chain_support(
+pub(crate) fn query_swap_chain_support(
     physical_device: &PhysicalDevice,
     surface: &Surface,
 ) -> Result<SwapChainSupportDetails> {
@@ -131,11 +467,17 @@ pub struct QueueFamilyIndicies {
     /// The graphics queue family index, if one is available
     pub graphics_family: Option<usize>,
     pub present_family: Option<usize>,
+    /// The compute queue family index, preferring one without graphics
+    /// support if available
+    pub compute_family: Option<usize>,
+    /// The index of a queue family supporting only transfers, if one is
+    /// available
+    pub transfer_family: Option<usize>,
 }
 
 impl QueueFamilyIndicies {
-    /// True if all queue families are available for this physical
-    /// device.
+    /// True if all required queue families are available for this physical
+    /// device. The compute and transfer families are optional.
     pub fn is_complete(&self) -> bool {
         self.graphics_family.is_some() && self.present_family.is_some()
     }
@@ -152,8 +494,9 @@ pub struct SwapChainSupportDetails {
 }
 
 impl SwapChainSupportDetails {
-    /// Picks the preferential surface format to use from the available
-    pub fn choose_swap_surface_format(&self) -> &SurfaceFormatKHR {
+    /// Picks the preferential surface format to use from the available. Errors with
+    /// [`RustyGamesError::NoSurfaceFormats`] if there are none.
+    pub fn choose_swap_surface_format(&self) -> Result<&SurfaceFormatKHR> {
         let srgb_color_space_formats = self
             .formats
             .iter()
@@ -163,66 +506,284 @@ impl SwapChainSupportDetails {
             .iter()
             .find(|format| format.format == Format::B8G8R8A8_SRGB)
         {
-            return *b8g8r8a8_format;
+            Ok(b8g8r8a8_format)
         } else if let Some(srbg_format) = srgb_color_space_formats.first() {
-            return *srbg_format;
+            Ok(srbg_format)
         } else {
-            return self.formats.first().unwrap();
+            Ok(self
+                .formats
+                .first()
+                .ok_or(RustyGamesError::NoSurfaceFormats)?)
         }
     }
 
-    /// Picks the preferential swap mode to use based on the available
-    pub fn choose_swap_present_mode(&self) -> PresentModeKHR {
+    /// Picks the preferential swap mode to use based on the available. Errors with
+    /// [`RustyGamesError::NoPresentModes`] if there are none.
+    pub fn choose_swap_present_mode(&self) -> Result<PresentModeKHR> {
+        if self.present_modes.is_empty() {
+            return Err(RustyGamesError::NoPresentModes.into());
+        }
         // prefer mailbox, where if we can render faster than the screen can present
         // and the queue fills up, we'll replace the last image with the most up to
         // date version
         if self.present_modes.contains(&PresentModeKHR::MAILBOX) {
-            return PresentModeKHR::MAILBOX;
+            return Ok(PresentModeKHR::MAILBOX);
         }
         // otherwise, use FIFO - basically vertical sync. This is the only setting
         // guaranteed to be available on all systems
-        return PresentModeKHR::FIFO;
+        Ok(PresentModeKHR::FIFO)
+    }
+
+    /// Picks how the surface's alpha is composited with what's behind the window: the
+    /// preferred mode if it's supported, falling back to opaque, or to whatever the
+    /// surface does support if not even that.
+    pub fn choose_composite_alpha(
+        &self,
+        preference: CompositeAlphaPreference,
+    ) -> CompositeAlphaFlagsKHR {
+        let supported = self.capabilities.supported_composite_alpha;
+        let preferred = preference.get_composite_alpha_flags();
+        if supported.contains(preferred) {
+            return preferred;
+        }
+        if preference != CompositeAlphaPreference::Opaque {
+            warn!(
+                ?preference,
+                "Composite alpha mode not supported, the window will be opaque"
+            );
+        }
+        [
+            CompositeAlphaFlagsKHR::OPAQUE,
+            CompositeAlphaFlagsKHR::INHERIT,
+            CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+            CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+        ]
+        .into_iter()
+        .find(|composite_alpha| supported.contains(*composite_alpha))
+        // every surface supports at least one of them
+        .unwrap_or(CompositeAlphaFlagsKHR::OPAQUE)
     }
 
     /// Returns the "extent" of the images to draw - the resolution to use *in pixels*.
-    pub fn choose_swap_extent(&self, window: &Window) -> Extent2D {
+    ///
+    /// Usually the surface dictates it as its current extent. Where it leaves the choice
+    /// to us (a current extent of `u32::MAX`) the window's size in pixels is used, clamped
+    /// into the surface's supported range as `clamping` says. The presentation engine
+    /// scales the image to fill the window, so clamping each side separately stretches it
+    /// when the window is bigger than the maximum extent in only one direction.
+    pub fn choose_swap_extent(&self, window: &Window, clamping: ExtentClamping) -> Extent2D {
+        self.choose_swap_extent_for_size(window.inner_size(), clamping)
+    }
+
+    /// Like [`SwapChainSupportDetails::choose_swap_extent`], for a window of the given
+    /// size in pixels
+    fn choose_swap_extent_for_size(
+        &self,
+        window_size: PhysicalSize<u32>,
+        clamping: ExtentClamping,
+    ) -> Extent2D {
         match self.capabilities.current_extent.width {
             // in this scenario, we're in a high DPI setting where extent is in screen
             // space, but we need it to be in pixels. set it to the same size as the
             // window
-            u32::MAX => {
-                let window_size = window.inner_size();
+            u32::MAX => clamping.clamp(
                 Extent2D {
-                    width: window_size.width.clamp(
-                        self.capabilities.min_image_extent.width,
-                        self.capabilities.max_image_extent.width,
-                    ),
-                    height: window_size.height.clamp(
-                        self.capabilities.min_image_extent.height,
-                        self.capabilities.max_image_extent.height,
-                    ),
-                }
-            }
+                    width: window_size.width,
+                    height: window_size.height,
+                },
+                self.capabilities.min_image_extent,
+                self.capabilities.max_image_extent,
+            ),
             _ => self.capabilities.current_extent,
         }
     }
 
-    /// Returns how many images the swap chain should use based on its support
+    /// Returns how many images the swap chain should use based on its support. Uses one more
+    /// than the minimum, so we're not stuck waiting on the driver's internal operations before
+    /// acquiring another image, clamped to the maximum. A maximum of zero means there is no
+    /// maximum. For example:
+    ///
+    /// - `min = 2, max = 0` (unbounded) uses 3
+    /// - `min = 2, max = 2` uses 2
+    /// - `min = 3, max = 8` uses 4
     pub fn get_image_count(&self) -> u32 {
-        let max_image_count = self.capabilities.max_image_count;
-        let min_image_count = self.capabilities.min_image_count;
+        self.clamp_image_count(self.capabilities.min_image_count + 1)
+    }
 
-        let image_count = match max_image_count {
-            // zero means there is no max, so just use 1 more than the minimum
-            0 => min_image_count + 1,
-            // in this case, use a few more than the minimum so we're not
-            // stuck waiting on internal details
-            _ => {
-                let delta = (max_image_count - min_image_count) / 2;
-                min_image_count + delta
-            }
-        };
+    /// Returns `desired_image_count` clamped to the supported range if given, for instance
+    /// 3 to force triple buffering, and [`SwapChainSupportDetails::get_image_count`]'s
+    /// pick otherwise
+    pub fn choose_image_count(&self, desired_image_count: Option<u32>) -> u32 {
+        match desired_image_count {
+            Some(desired_image_count) => self.clamp_image_count(desired_image_count),
+            None => self.get_image_count(),
+        }
+    }
 
+    /// Clamps `image_count` to the supported range, where a maximum of zero means there
+    /// is no maximum
+    fn clamp_image_count(&self, image_count: u32) -> u32 {
+        let min_image_count = self.capabilities.min_image_count;
+        let max_image_count = match self.capabilities.max_image_count {
+            0 => u32::MAX,
+            max_image_count => max_image_count,
+        };
         image_count.clamp(min_image_count, max_image_count)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support_details(min_image_count: u32, max_image_count: u32) -> SwapChainSupportDetails {
+        SwapChainSupportDetails {
+            capabilities: SurfaceCapabilitiesKHR {
+                min_image_count,
+                max_image_count,
+                ..Default::default()
+            },
+            formats: vec![],
+            present_modes: vec![],
+        }
+    }
+
+    fn extent(width: u32, height: u32) -> Extent2D {
+        Extent2D { width, height }
+    }
+
+    fn extent_support_details(current_extent: Extent2D) -> SwapChainSupportDetails {
+        SwapChainSupportDetails {
+            capabilities: SurfaceCapabilitiesKHR {
+                current_extent,
+                min_image_extent: extent(100, 100),
+                max_image_extent: extent(2000, 2000),
+                ..Default::default()
+            },
+            formats: vec![],
+            present_modes: vec![],
+        }
+    }
+
+    #[test]
+    fn extents_in_range_are_unchanged() {
+        for clamping in [
+            ExtentClamping::Independent,
+            ExtentClamping::PreserveAspectRatio,
+        ] {
+            assert_eq!(
+                clamping.clamp(extent(1000, 500), extent(100, 100), extent(2000, 2000)),
+                extent(1000, 500)
+            );
+        }
+    }
+
+    #[test]
+    fn independent_clamping_clamps_each_side() {
+        let clamping = ExtentClamping::Independent;
+        assert_eq!(
+            clamping.clamp(extent(3000, 1000), extent(100, 100), extent(2000, 2000)),
+            extent(2000, 1000)
+        );
+        assert_eq!(
+            clamping.clamp(extent(50, 3000), extent(100, 100), extent(2000, 2000)),
+            extent(100, 2000)
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_clamping_scales_down_to_the_maximum() {
+        let clamping = ExtentClamping::PreserveAspectRatio;
+        assert_eq!(
+            clamping.clamp(extent(3000, 1000), extent(100, 100), extent(2000, 2000)),
+            extent(2000, 666)
+        );
+        assert_eq!(
+            clamping.clamp(extent(4000, 8000), extent(100, 100), extent(2000, 2000)),
+            extent(1000, 2000)
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_clamping_clamps_up_to_the_minimum() {
+        let clamping = ExtentClamping::PreserveAspectRatio;
+        assert_eq!(
+            clamping.clamp(extent(50, 20), extent(100, 100), extent(2000, 2000)),
+            extent(100, 100)
+        );
+        // too far off the aspect ratio to keep it within the range
+        assert_eq!(
+            clamping.clamp(extent(8000, 100), extent(100, 100), extent(2000, 2000)),
+            extent(2000, 100)
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_clamping_handles_empty_extents() {
+        let clamping = ExtentClamping::PreserveAspectRatio;
+        assert_eq!(
+            clamping.clamp(extent(0, 0), extent(100, 100), extent(2000, 2000)),
+            extent(100, 100)
+        );
+    }
+
+    #[test]
+    fn swap_extent_follows_the_window_without_a_current_extent() {
+        let details = extent_support_details(extent(u32::MAX, u32::MAX));
+        assert_eq!(
+            details.choose_swap_extent_for_size(
+                PhysicalSize::new(1280, 720),
+                ExtentClamping::Independent
+            ),
+            extent(1280, 720)
+        );
+        assert_eq!(
+            details.choose_swap_extent_for_size(
+                PhysicalSize::new(3000, 1000),
+                ExtentClamping::PreserveAspectRatio
+            ),
+            extent(2000, 666)
+        );
+    }
+
+    #[test]
+    fn swap_extent_is_the_current_extent_if_set() {
+        let details = extent_support_details(extent(800, 600));
+        assert_eq!(
+            details.choose_swap_extent_for_size(
+                PhysicalSize::new(1280, 720),
+                ExtentClamping::Independent
+            ),
+            extent(800, 600)
+        );
+    }
+
+    #[test]
+    fn image_count_is_one_more_than_the_minimum() {
+        assert_eq!(support_details(3, 8).get_image_count(), 4);
+    }
+
+    #[test]
+    fn image_count_is_unbounded_for_a_zero_maximum() {
+        assert_eq!(support_details(2, 0).get_image_count(), 3);
+    }
+
+    #[test]
+    fn image_count_is_the_minimum_when_it_equals_the_maximum() {
+        assert_eq!(support_details(2, 2).get_image_count(), 2);
+    }
+
+    #[test]
+    fn chosen_image_count_is_clamped_to_the_supported_range() {
+        let details = support_details(2, 3);
+        assert_eq!(details.choose_image_count(Some(1)), 2);
+        assert_eq!(details.choose_image_count(Some(3)), 3);
+        assert_eq!(details.choose_image_count(Some(8)), 3);
+        assert_eq!(details.choose_image_count(None), 3);
+    }
+
+    #[test]
+    fn chosen_image_count_is_unbounded_for_a_zero_maximum() {
+        assert_eq!(support_details(2, 0).choose_image_count(Some(8)), 8);
+    }
+}
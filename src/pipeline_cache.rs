@@ -0,0 +1,69 @@
+use std::{fmt, ops::Deref};
+
+use anyhow::Result;
+use ash::vk::{self, PipelineCacheCreateInfo};
+
+use crate::{LogicalDevice, Shared};
+
+/// Caches the results of compiling pipelines, so creating pipelines from the same shaders
+/// and state again is faster. The contents can be saved with [`PipelineCache::get_data`]
+/// and loaded with [`PipelineCache::with_data`] to speed up the next run as well.
+pub struct PipelineCache {
+    logical_device: Shared<LogicalDevice>,
+    pipeline_cache: vk::PipelineCache,
+}
+
+impl PipelineCache {
+    /// Creates an empty cache
+    pub fn new(logical_device: &Shared<LogicalDevice>) -> Result<Self> {
+        Self::with_data(logical_device, &[])
+    }
+
+    /// Creates a cache from the data an earlier cache returned. The driver ignores data
+    /// from other drivers or devices, starting empty instead.
+    pub fn with_data(logical_device: &Shared<LogicalDevice>, data: &[u8]) -> Result<Self> {
+        let pipeline_cache_create_info = PipelineCacheCreateInfo::default().initial_data(data);
+        let pipeline_cache =
+            unsafe { logical_device.create_pipeline_cache(&pipeline_cache_create_info, None)? };
+        logical_device.set_object_name(pipeline_cache, "pipeline cache")?;
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            pipeline_cache,
+        })
+    }
+
+    /// Returns the cache's contents, to be saved and passed to [`PipelineCache::with_data`]
+    pub fn get_data(&self) -> Result<Vec<u8>> {
+        let data = unsafe {
+            self.logical_device
+                .get_pipeline_cache_data(self.pipeline_cache)?
+        };
+        Ok(data)
+    }
+}
+
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device
+                .destroy_pipeline_cache(self.pipeline_cache, None)
+        }
+    }
+}
+
+impl Deref for PipelineCache {
+    type Target = vk::PipelineCache;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pipeline_cache
+    }
+}
+
+impl fmt::Debug for PipelineCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PipelineCache")
+            .field(&self.pipeline_cache)
+            .finish()
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use ash::vk::{
+    Buffer, CommandBuffer, DescriptorBufferInfo, DescriptorImageInfo, DescriptorType, DeviceSize,
+    ImageLayout, ImageView, PipelineBindPoint, PipelineLayout, Sampler, WriteDescriptorSet,
+};
+
+use crate::LogicalDevice;
+
+/// A descriptor write recorded by a [`PushDescriptor`], owning the info it points at
+#[derive(Clone, Copy, Debug)]
+enum PushDescriptorWrite {
+    Buffer {
+        binding: u32,
+        descriptor_type: DescriptorType,
+        buffer_info: DescriptorBufferInfo,
+    },
+    Image {
+        binding: u32,
+        descriptor_type: DescriptorType,
+        image_info: DescriptorImageInfo,
+    },
+}
+
+/// Descriptor writes recorded straight into a command buffer with
+/// `vkCmdPushDescriptorSetKHR`, so small per-draw bindings like a material's texture don't
+/// need sets allocated from a pool. The set layout has to be created with
+/// [`crate::DescriptorSetLayout::for_push_descriptors`], and the device needs
+/// [`LogicalDevice::is_push_descriptor_enabled`].
+#[derive(Clone, Debug, Default)]
+pub struct PushDescriptor {
+    writes: Vec<PushDescriptorWrite>,
+}
+
+impl PushDescriptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `range` bytes of the buffer, from `offset`, as a uniform buffer
+    pub fn uniform_buffer(
+        mut self,
+        binding: u32,
+        buffer: Buffer,
+        offset: DeviceSize,
+        range: DeviceSize,
+    ) -> Self {
+        self.writes.push(PushDescriptorWrite::Buffer {
+            binding,
+            descriptor_type: DescriptorType::UNIFORM_BUFFER,
+            buffer_info: DescriptorBufferInfo::default()
+                .buffer(buffer)
+                .offset(offset)
+                .range(range),
+        });
+        self
+    }
+
+    /// Binds an image view and sampler, with the image in `SHADER_READ_ONLY_OPTIMAL`
+    pub fn combined_image_sampler(
+        mut self,
+        binding: u32,
+        image_view: ImageView,
+        sampler: Sampler,
+    ) -> Self {
+        self.writes.push(PushDescriptorWrite::Image {
+            binding,
+            descriptor_type: DescriptorType::COMBINED_IMAGE_SAMPLER,
+            image_info: DescriptorImageInfo::default()
+                .image_view(image_view)
+                .sampler(sampler)
+                .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        });
+        self
+    }
+
+    /// Records the writes into the command buffer as set `set` of the pipeline layout,
+    /// for the draws or dispatches recorded after it. Errors if push descriptors aren't
+    /// enabled on the device.
+    pub fn record(
+        &self,
+        logical_device: &LogicalDevice,
+        command_buffer: CommandBuffer,
+        pipeline_bind_point: PipelineBindPoint,
+        pipeline_layout: PipelineLayout,
+        set: u32,
+    ) -> Result<()> {
+        let push_descriptor = logical_device
+            .get_push_descriptor()
+            .ok_or_else(|| anyhow!("VK_KHR_push_descriptor isn't enabled on the device!"))?;
+
+        let descriptor_writes = self
+            .writes
+            .iter()
+            .map(|write| match write {
+                PushDescriptorWrite::Buffer {
+                    binding,
+                    descriptor_type,
+                    buffer_info,
+                } => WriteDescriptorSet::default()
+                    .dst_binding(*binding)
+                    .descriptor_type(*descriptor_type)
+                    .buffer_info(std::slice::from_ref(buffer_info)),
+                PushDescriptorWrite::Image {
+                    binding,
+                    descriptor_type,
+                    image_info,
+                } => WriteDescriptorSet::default()
+                    .dst_binding(*binding)
+                    .descriptor_type(*descriptor_type)
+                    .image_info(std::slice::from_ref(image_info)),
+            })
+            .collect::<Vec<_>>();
+
+        unsafe {
+            push_descriptor.cmd_push_descriptor_set(
+                command_buffer,
+                pipeline_bind_point,
+                pipeline_layout,
+                set,
+                &descriptor_writes,
+            )
+        };
+        Ok(())
+    }
+}
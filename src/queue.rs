@@ -0,0 +1,91 @@
+use std::ops::Deref;
+
+use anyhow::{ensure, Result};
+use ash::vk::{self, PresentInfoKHR, QueueFlags};
+
+use crate::{error::VkResultExt, RustyGamesError, Swapchain};
+
+/// A queue of the logical device, which remembers the family it's from and what that
+/// family supports so work can't be sent to a queue that can't run it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Queue {
+    queue: vk::Queue,
+    family_index: u32,
+    /// The operations the queue's family supports
+    flags: QueueFlags,
+    /// Whether the queue's family can present to the logical device's surface
+    supports_present: bool,
+}
+
+impl Queue {
+    pub(crate) fn new(
+        queue: vk::Queue,
+        family_index: u32,
+        flags: QueueFlags,
+        supports_present: bool,
+    ) -> Self {
+        Self {
+            queue,
+            family_index,
+            flags,
+            supports_present,
+        }
+    }
+
+    pub fn get_family_index(&self) -> u32 {
+        self.family_index
+    }
+
+    pub fn get_flags(&self) -> QueueFlags {
+        self.flags
+    }
+
+    /// True if the queue's family supports all of the operations in `flags`
+    pub fn supports(&self, flags: QueueFlags) -> bool {
+        self.flags.contains(flags)
+    }
+
+    pub fn supports_present(&self) -> bool {
+        self.supports_present
+    }
+
+    /// Errors if the queue's family doesn't support all of the operations in `flags`
+    pub fn ensure_supports(&self, flags: QueueFlags) -> Result<()> {
+        ensure!(
+            self.supports(flags),
+            "Queue family {} ({:?}) doesn't support {:?}!",
+            self.family_index,
+            self.flags,
+            flags
+        );
+        Ok(())
+    }
+
+    /// Queues the swapchain image for presentation. Errors if the queue can't present, and
+    /// with [`RustyGamesError::SurfaceLost`] if the window's surface went away.
+    /// Errors with [`RustyGamesError::SwapchainOutOfDate`] if the swapchain has to be
+    /// recreated, including when the image was presented but the swapchain is suboptimal.
+    pub fn present(&self, swapchain: &Swapchain, present_info: &PresentInfoKHR) -> Result<()> {
+        ensure!(
+            self.supports_present,
+            "Queue family {} can't present!",
+            self.family_index
+        );
+        match unsafe { swapchain.queue_present(self.queue, present_info) } {
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => Err(RustyGamesError::SurfaceLost.into()),
+            Ok(true) => Err(RustyGamesError::SwapchainOutOfDate.into()),
+            result => {
+                result.map_out_of_date()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Deref for Queue {
+    type Target = vk::Queue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.queue
+    }
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use ash::vk::CommandBuffer;
+
+/// A pass recorded into a frame's command buffer before the main render pass, such as
+/// rendering a shadow map or another offscreen target the main pass then samples. Each
+/// step brings its own render pass, framebuffer and pipelines, and begins and ends the
+/// render pass itself. Steps run in the order given to
+/// [`crate::Renderer::render_frame_with_steps`].
+pub trait RenderStep {
+    /// Records the step's commands into the command buffer, which is outside of any
+    /// render pass. `frame_idx` is the index of the frame within the frames in flight,
+    /// for keeping per frame resources apart.
+    fn record(&mut self, command_buffer: CommandBuffer, frame_idx: usize) -> Result<()>;
+
+    /// Records any barriers later steps (or the main pass) need before reading what this
+    /// step wrote, called right after [`RenderStep::record`]. Does nothing by default,
+    /// for steps whose render pass dependencies already make their output available.
+    fn record_barriers(&mut self, command_buffer: CommandBuffer, frame_idx: usize) -> Result<()> {
+        let _ = (command_buffer, frame_idx);
+        Ok(())
+    }
+}
@@ -0,0 +1,632 @@
+use std::{
+    ffi::CStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, ensure, Result};
+use ash::vk::CommandBuffer;
+use tracing::warn;
+use winit::{
+    monitor::VideoMode,
+    raw_window_handle::HasDisplayHandle,
+    window::{Window, WindowId},
+};
+
+use crate::{
+    color::hue_to_linear_rgb, frame::DEFAULT_FRAME_TIMEOUT, BlendMode, CommandPool,
+    CompositeAlphaPreference, DebugMessenger, DrawCommand, GraphicsPipeline, Instance,
+    LogicalDevice, MonitorVideoModes, PhysicalDeviceSurface, PipelineConfig, RenderScale,
+    RenderStep, Shared, StaticCommands, StencilConfig, Surface, Swapchain, UiPass, WindowMode,
+};
+
+/// Facade tying together everything needed to render to a window: the
+/// instance, surface, device, swapchain, pipeline and command pool. The
+/// lower level types remain available through the getters for more
+/// advanced use.
+///
+/// Additional windows can be rendered to with [`Renderer::add_window`],
+/// sharing the same instance and device.
+///
+/// If recovering from a lost device or failed driver fails, the renderer is left without
+/// a device (or without swapchains), and everything but recovering again errors until
+/// [`Renderer::recover`] or [`Renderer::reinitialize`] succeeds.
+pub struct Renderer {
+    /// The vulkan objects, or `None` if reinitializing failed
+    core: Option<Core>,
+    /// The windows being rendered to. The first is the window the device
+    /// was selected for, and is always present.
+    windows: Vec<Shared<Window>>,
+    /// How long to wait for a frame's previous draw before reporting the GPU as hung
+    frame_timeout: Duration,
+    /// When the renderer was created, which animates the test pattern
+    created_at: Instant,
+}
+
+impl Renderer {
+    pub fn new(window: &Shared<Window>) -> Result<Self> {
+        let windows = vec![Shared::clone(window)];
+        Ok(Self {
+            core: Some(Core::new(&windows)?),
+            windows,
+            frame_timeout: DEFAULT_FRAME_TIMEOUT,
+            created_at: Instant::now(),
+        })
+    }
+
+    /// Renders the next frame to each window and queues them for presentation
+    pub fn render_frame(&mut self) -> Result<()> {
+        self.render_frame_with_steps(&mut [], None)
+    }
+
+    /// Like [`Renderer::render_frame`], but blocks until the GPU has finished every
+    /// window's frame and the device is idle, so nothing is in flight once it returns.
+    /// Meant for tests and tools, e.g. reading back a screenshot without racing the
+    /// present; it throws away the overlap between CPU and GPU that frames in flight give,
+    /// so the render loop should keep using [`Renderer::render_frame`].
+    pub fn render_and_wait(&mut self) -> Result<()> {
+        let frame_timeout = self.frame_timeout;
+        let core = self.core_mut()?;
+        let (primary, others) = core.split_primary()?;
+        primary.render_and_wait(frame_timeout)?;
+        for target in others {
+            target.render_and_wait(frame_timeout)?;
+        }
+        // the frames' fences only cover drawing, not presenting
+        core.logical_device.wait_idle()
+    }
+
+    /// Like [`Renderer::render_frame`], but also has `ui_pass` draw an overlay on top of
+    /// the primary window's scene
+    pub fn render_frame_with_ui(&mut self, ui_pass: &mut dyn UiPass) -> Result<()> {
+        self.render_frame_with_steps(&mut [], Some(ui_pass))
+    }
+
+    /// Like [`Renderer::render_frame`], but first records the `steps` in order ahead of
+    /// the primary window's main render pass, for offscreen passes like shadow maps, and
+    /// has `ui_pass` (if given) draw an overlay on top of its scene
+    pub fn render_frame_with_steps(
+        &mut self,
+        steps: &mut [&mut dyn RenderStep],
+        ui_pass: Option<&mut dyn UiPass>,
+    ) -> Result<()> {
+        let frame_timeout = self.frame_timeout;
+        let (primary, others) = self.core_mut()?.split_primary()?;
+        primary.render(frame_timeout, steps, ui_pass)?;
+        for target in others {
+            target.render(frame_timeout, &mut [], None)?;
+        }
+        Ok(())
+    }
+
+    /// Records the primary window's draws once, see [`CommandPool::record_static`], to be
+    /// submitted with [`Renderer::render_static_frame`]. Has to be called again after
+    /// the window is resized.
+    pub fn record_static<F>(&self, record: F) -> Result<StaticCommands>
+    where
+        F: FnMut(CommandBuffer) -> Result<()>,
+    {
+        let target = self.primary_target()?;
+        target.command_pool.record_static(&target.swapchain, record)
+    }
+
+    /// Like [`Renderer::render_frame`], but submits the commands recorded with
+    /// [`Renderer::record_static`] for the primary window instead of recording them
+    /// again. Errors if the window has been resized since.
+    pub fn render_static_frame(&mut self, static_commands: &StaticCommands) -> Result<()> {
+        let frame_timeout = self.frame_timeout;
+        let (primary, others) = self.core_mut()?.split_primary()?;
+        primary.render_static(frame_timeout, static_commands)?;
+        for target in others {
+            target.render(frame_timeout, &mut [], None)?;
+        }
+        Ok(())
+    }
+
+    /// Renders a frame to each window that's just cleared to a color cycling through the
+    /// hues every few seconds, without drawing anything. As a smoke test this tells
+    /// whether a black screen comes from the present path or the pipeline. The draw
+    /// command and clear color are left as they were.
+    pub fn render_test_pattern(&mut self) -> Result<()> {
+        const CYCLE_SECS: f32 = 6.0;
+        let hue = self.created_at.elapsed().as_secs_f32() / CYCLE_SECS;
+        let [r, g, b] = hue_to_linear_rgb(hue);
+        let frame_timeout = self.frame_timeout;
+        let (primary, others) = self.core_mut()?.split_primary()?;
+        for target in std::iter::once(primary).chain(others) {
+            let draw_command = target.command_pool.get_draw_command();
+            let clear_color = target.command_pool.get_clear_color();
+            target
+                .command_pool
+                .set_draw_command(DrawCommand::vertices(0));
+            target.command_pool.set_clear_color([r, g, b, 1.0]);
+            let result = target.render(frame_timeout, &mut [], None);
+            target.command_pool.set_draw_command(draw_command);
+            target.command_pool.set_clear_color(clear_color);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Recreates the swapchain and everything built from its images, for instance
+    /// after the window has been resized. Waits for the device to go idle first.
+    pub fn resize(&mut self) -> Result<()> {
+        let core = self.core_mut()?;
+        let logical_device = Shared::clone(&core.logical_device);
+        core.split_primary()?.0.resize(&logical_device)
+    }
+
+    /// Lists the video modes of every monitor the primary window could go fullscreen on,
+    /// see [`crate::available_video_modes`]
+    pub fn available_video_modes(&self) -> Vec<MonitorVideoModes> {
+        crate::available_video_modes(&self.windows[0])
+    }
+
+    /// Switches the primary window into exclusive fullscreen at the video mode, falling
+    /// back to windowed if its monitor is gone, see [`crate::set_video_mode`]. The
+    /// swapchain is recreated at the new resolution by [`Renderer::resize`] once the
+    /// window's resize event arrives.
+    pub fn set_video_mode(&self, video_mode: &VideoMode) -> WindowMode {
+        crate::set_video_mode(&self.windows[0], video_mode)
+    }
+
+    /// Same as [`Renderer::resize`], but for the given window
+    pub fn resize_window(&mut self, window_id: WindowId) -> Result<()> {
+        let core = self.core_mut()?;
+        let logical_device = Shared::clone(&core.logical_device);
+        core.get_target_mut(window_id)?.resize(&logical_device)
+    }
+
+    /// Starts rendering to an additional window, with its own surface and swapchain.
+    /// Errors if the device can't present to the window's surface.
+    pub fn add_window(&mut self, window: &Shared<Window>) -> Result<()> {
+        self.core_mut()?.add_window(window)?;
+        self.windows.push(Shared::clone(window));
+        Ok(())
+    }
+
+    /// Stops rendering to a window added via [`Renderer::add_window`], cleaning up its
+    /// resources. The window the renderer was created with can't be removed.
+    pub fn remove_window(&mut self, window_id: WindowId) -> Result<()> {
+        let idx = self
+            .windows
+            .iter()
+            .position(|window| window.id() == window_id)
+            .ok_or_else(|| anyhow!("Window is not being rendered to!"))?;
+        ensure!(idx != 0, "Can't remove the renderer's primary window!");
+
+        if let Some(core) = &mut self.core {
+            core.logical_device.wait_idle()?;
+            core.targets
+                .retain(|target| target.get_window_id() != window_id);
+        }
+        self.windows.remove(idx);
+        Ok(())
+    }
+
+    /// Recovers from a [`crate::RustyGamesError::DeviceLost`] error, by tearing down the logical
+    /// device and everything created from it, then picking a device and recreating them
+    /// for every window. The instance, the primary window's surface and the windows are
+    /// kept. If picking a device fails the old one is kept, and if recreating the
+    /// swapchains fails the renderer has none; in both cases it can be recovered again.
+    /// Falls back to [`Renderer::reinitialize`] if that failed before.
+    pub fn recover(&mut self) -> Result<()> {
+        self.recreate_device(false)
+    }
+
+    /// Recovers from a [`crate::RustyGamesError::SurfaceLost`] error, like
+    /// [`Renderer::recover`] but with a new surface for the primary window as well, which
+    /// the device is then picked for.
+    pub fn recreate_surface(&mut self) -> Result<()> {
+        self.recreate_device(true)
+    }
+
+    /// Implements [`Renderer::recover`], first replacing the primary window's surface if
+    /// `recreate_surface` is set
+    fn recreate_device(&mut self, recreate_surface: bool) -> Result<()> {
+        let Some(core) = &mut self.core else {
+            return self.reinitialize();
+        };
+
+        // the old device is kept until the new one exists, so failing here leaves the
+        // renderer as it was
+        let primary_surface = if recreate_surface {
+            Shared::new(Surface::new(&core.instance, &self.windows[0])?)
+        } else {
+            Shared::clone(core.logical_device.get_surface())
+        };
+        let physical_device_surface = pick_physical_device(&core.instance, &primary_surface)?;
+        let logical_device = Shared::new(LogicalDevice::try_from(physical_device_surface)?);
+
+        // waiting on a lost device fails immediately, so there's nothing to wait for.
+        // everything still referencing the old device must be dropped before it is, and
+        // the windows' old swapchains before new ones can be created for them
+        let _ = core.logical_device.wait_idle();
+        core.targets.clear();
+        core.logical_device = logical_device;
+
+        let mut targets = Vec::with_capacity(self.windows.len());
+        let swapchain = Swapchain::new(
+            &core.instance,
+            &self.windows[0],
+            &core.logical_device,
+            CompositeAlphaPreference::default(),
+            None,
+        )?;
+        targets.push(RenderTarget::new(&core.logical_device, swapchain)?);
+        for window in &self.windows[1..] {
+            // the other windows' surfaces went with their targets
+            targets.push(core.create_target(window)?);
+        }
+        core.targets = targets;
+        Ok(())
+    }
+
+    /// Recovers from a [`crate::RustyGamesError::InitializationFailed`] error, for instance
+    /// after the system switched GPUs, by tearing everything down to the Vulkan entry and
+    /// rebuilding it: the instance, the surfaces, device selection and everything created
+    /// from the device, for every window. Only the windows and the frame timeout are kept.
+    /// Heavier than [`Renderer::recover`], which keeps the instance. If rebuilding fails
+    /// the renderer is left without a device, and can be reinitialized again.
+    pub fn reinitialize(&mut self) -> Result<()> {
+        // the old device, instance and surfaces have to be gone before new ones can be
+        // created for the windows. dropping the core waits for the device (which may not
+        // respond anymore) to go idle first
+        self.core = None;
+        self.core = Some(Core::new(&self.windows)?);
+        Ok(())
+    }
+
+    /// Recreates the primary window's pipeline with a depth stencil buffer, tested and
+    /// updated as `stencil` configures, or without one if `None` (the default). Waits for
+    /// the device to go idle first.
+    pub fn set_depth_stencil(&mut self, stencil: Option<StencilConfig>) -> Result<()> {
+        let core = self.core_mut()?;
+        core.logical_device.wait_idle()?;
+        let logical_device = Shared::clone(&core.logical_device);
+        let target = core.split_primary()?.0;
+        target.stencil = stencil;
+        target.recreate_command_pool(&logical_device)
+    }
+
+    /// Recreates the primary window's pipeline to draw at a fraction of the window's
+    /// resolution, scaling the result up to fill it, or at full resolution with
+    /// [`RenderScale::FULL`] (the default). Waits for the device to go idle first.
+    pub fn set_render_scale(&mut self, render_scale: RenderScale) -> Result<()> {
+        let core = self.core_mut()?;
+        core.logical_device.wait_idle()?;
+        let logical_device = Shared::clone(&core.logical_device);
+        let target = core.split_primary()?.0;
+        target.render_scale = render_scale;
+        target.recreate_command_pool(&logical_device)
+    }
+
+    /// Reads the depth under pixel (`x`, `y`) of the primary window, for mouse picking.
+    /// Lags a frame behind and blocks until the frames in flight finish, see
+    /// [`CommandPool::read_depth_at`]. Errors unless a depth buffer was enabled with
+    /// [`Renderer::set_depth_stencil`].
+    pub fn read_depth_at(&self, x: u32, y: u32) -> Result<f32> {
+        let target = self.primary_target()?;
+        // the depth buffer is at the render scale's resolution, not the window's
+        let scale = target.render_scale.get_scale();
+        let (x, y) = ((x as f32 * scale) as u32, (y as f32 * scale) as u32);
+        target.command_pool.read_depth_at(x, y)
+    }
+
+    /// Sets how long rendering a frame waits for that frame's previous draw to complete,
+    /// before failing with [`crate::RustyGamesError::FenceTimeout`]. Defaults to
+    /// [`DEFAULT_FRAME_TIMEOUT`].
+    pub fn set_frame_timeout(&mut self, frame_timeout: Duration) {
+        self.frame_timeout = frame_timeout;
+    }
+
+    /// Waits for the device to finish all submitted work
+    pub fn wait_idle(&self) -> Result<()> {
+        self.core()?.logical_device.wait_idle()
+    }
+
+    /// Returns how many physical pixels the primary window has per logical pixel, for
+    /// sizing UI and text on high-DPI displays. The swapchain is always sized in physical
+    /// pixels.
+    pub fn get_scale_factor(&self) -> f64 {
+        self.windows[0].scale_factor()
+    }
+
+    /// Errors if the renderer has no device, see [`Renderer::recover`]
+    pub fn get_instance(&self) -> Result<&Shared<Instance>> {
+        Ok(&self.core()?.instance)
+    }
+
+    /// Errors if the renderer has no device, see [`Renderer::recover`]
+    pub fn get_logical_device(&self) -> Result<&Shared<LogicalDevice>> {
+        Ok(&self.core()?.logical_device)
+    }
+
+    /// Returns the swapchain of the primary window. Errors if the renderer has no
+    /// swapchains, see [`Renderer::recover`].
+    pub fn get_swapchain(&self) -> Result<&Swapchain> {
+        Ok(&self.primary_target()?.swapchain)
+    }
+
+    /// Returns the command pool of the primary window. Errors if the renderer has no
+    /// swapchains, see [`Renderer::recover`].
+    pub fn get_command_pool(&mut self) -> Result<&mut CommandPool> {
+        Ok(&mut self.core_mut()?.split_primary()?.0.command_pool)
+    }
+
+    /// Returns the render pass the primary window is drawn with, which pipelines recording
+    /// into a [`UiPass`] must be compatible with. It's recreated when the window is
+    /// resized, so anything built from it has to be rebuilt too. Not available with the
+    /// `dynamic_rendering` feature, where pipelines are created for the swapchain's
+    /// format instead.
+    #[cfg(not(feature = "dynamic_rendering"))]
+    pub fn get_render_pass(&self) -> Result<&crate::RenderPass> {
+        Ok(self
+            .primary_target()?
+            .command_pool
+            .get_graphics_pipeline()
+            .get_render_pass())
+    }
+
+    /// Returns the debug utils messenger, if enabled and the renderer has a device
+    pub fn get_debug_messenger(&self) -> Option<&DebugMessenger> {
+        self.core.as_ref()?.debug_messenger.as_ref()
+    }
+
+    fn core(&self) -> Result<&Core> {
+        self.core.as_ref().ok_or_else(no_device_error)
+    }
+
+    fn core_mut(&mut self) -> Result<&mut Core> {
+        self.core.as_mut().ok_or_else(no_device_error)
+    }
+
+    fn primary_target(&self) -> Result<&RenderTarget> {
+        self.core()?.targets.first().ok_or_else(no_swapchains_error)
+    }
+}
+
+/// The vulkan objects a [`Renderer`] is built from, declared in the order they have to
+/// be dropped in
+struct Core {
+    /// One per window, in the same order as the renderer's windows
+    targets: Vec<RenderTarget>,
+    /// The logical device for interfacing with the
+    /// physical hardware
+    logical_device: Shared<LogicalDevice>,
+    /// The debug utils messenger, if enabled
+    debug_messenger: Option<DebugMessenger>,
+    instance: Shared<Instance>,
+}
+
+impl Core {
+    /// Creates the instance and picks the device for the first window, then renders to
+    /// the others with it as well
+    fn new(windows: &[Shared<Window>]) -> Result<Self> {
+        let window = &windows[0];
+        let required_extensions =
+            ash_window::enumerate_required_extensions(window.display_handle()?.as_raw())?
+                .iter()
+                .map(|extension| unsafe { CStr::from_ptr(*extension) }.to_str())
+                .collect::<Result<Vec<_>, _>>()?;
+
+        // init vulkan
+        let instance = Shared::new(Instance::new(required_extensions)?);
+        let debug_messenger = setup_debug_messenger(&instance)?;
+        let surface = Shared::new(Surface::new(&instance, window)?);
+        let physical_device_surface = pick_physical_device(&instance, &surface)?;
+        let logical_device = Shared::new(LogicalDevice::try_from(physical_device_surface)?);
+        let swapchain = Swapchain::new(
+            &instance,
+            window,
+            &logical_device,
+            CompositeAlphaPreference::default(),
+            None,
+        )?;
+
+        let target = RenderTarget::new(&logical_device, swapchain)?;
+
+        let mut core = Self {
+            targets: vec![target],
+            logical_device,
+            debug_messenger,
+            instance,
+        };
+        for window in &windows[1..] {
+            core.add_window(window)?;
+        }
+        Ok(core)
+    }
+
+    fn add_window(&mut self, window: &Shared<Window>) -> Result<()> {
+        let target = self.create_target(window)?;
+        self.targets.push(target);
+        Ok(())
+    }
+
+    /// Creates a surface and swapchain for a window other than the primary one
+    fn create_target(&self, window: &Shared<Window>) -> Result<RenderTarget> {
+        let surface = Shared::new(Surface::new(&self.instance, window)?);
+        let swapchain = Swapchain::for_surface(
+            &self.instance,
+            window,
+            &self.logical_device,
+            &surface,
+            CompositeAlphaPreference::default(),
+            None,
+        )?;
+        RenderTarget::new(&self.logical_device, swapchain)
+    }
+
+    /// Splits the targets into the primary window's and the others'. Errors if there are
+    /// none, after recreating the swapchains failed.
+    fn split_primary(&mut self) -> Result<(&mut RenderTarget, &mut [RenderTarget])> {
+        self.targets
+            .split_first_mut()
+            .ok_or_else(no_swapchains_error)
+    }
+
+    fn get_target_mut(&mut self, window_id: WindowId) -> Result<&mut RenderTarget> {
+        self.targets
+            .iter_mut()
+            .find(|target| target.get_window_id() == window_id)
+            .ok_or_else(|| anyhow!("Window is not being rendered to!"))
+    }
+}
+
+impl Drop for Core {
+    fn drop(&mut self) {
+        // wait for vulkan to finish up before tearing everything down
+        let _ = self.logical_device.wait_idle();
+    }
+}
+
+fn no_device_error() -> anyhow::Error {
+    anyhow!("The renderer has no device, it has to be recovered first!")
+}
+
+fn no_swapchains_error() -> anyhow::Error {
+    anyhow!("The renderer has no swapchains, it has to be recovered first!")
+}
+
+/// The resources needed to render to a single window
+struct RenderTarget {
+    /// Command pool responsible for managing memory and creating
+    /// command buffers. Owns the graphics pipeline.
+    command_pool: CommandPool,
+    /// See swapchain manager struct docs
+    swapchain: Swapchain,
+    /// How the pipeline uses its depth stencil buffer, if it has one
+    stencil: Option<StencilConfig>,
+    /// The fraction of the window's resolution the pipeline draws at
+    render_scale: RenderScale,
+}
+
+impl RenderTarget {
+    fn new(logical_device: &Shared<LogicalDevice>, swapchain: Swapchain) -> Result<Self> {
+        // configure graphics pipeline
+        let pipeline = create_pipeline(logical_device, &swapchain, None, RenderScale::FULL)?;
+
+        // configure command buffers
+        let command_pool = CommandPool::new(logical_device, pipeline)?;
+
+        Ok(Self {
+            command_pool,
+            swapchain,
+            stencil: None,
+            render_scale: RenderScale::FULL,
+        })
+    }
+
+    fn render_static(
+        &mut self,
+        frame_timeout: Duration,
+        static_commands: &StaticCommands,
+    ) -> Result<()> {
+        let frame = self.command_pool.get_next_frame();
+        frame.render_static(&self.swapchain, frame_timeout, static_commands)
+    }
+
+    fn render(
+        &mut self,
+        frame_timeout: Duration,
+        steps: &mut [&mut dyn RenderStep],
+        ui_pass: Option<&mut dyn UiPass>,
+    ) -> Result<()> {
+        let frame = self.command_pool.get_next_frame();
+        frame.render(&self.swapchain, frame_timeout, steps, ui_pass)
+    }
+
+    /// Renders the next frame, then waits for the GPU to finish drawing it
+    fn render_and_wait(&mut self, frame_timeout: Duration) -> Result<()> {
+        let frame = self.command_pool.get_next_frame();
+        frame.render(&self.swapchain, frame_timeout, &mut [], None)?;
+        frame.wait_until_idle(frame_timeout)
+    }
+
+    /// Recreates the swapchain and the pipeline built from it
+    fn resize(&mut self, logical_device: &Shared<LogicalDevice>) -> Result<()> {
+        // the window gets another resize event once it's windowed, recreating the
+        // swapchain again at the windowed size
+        if crate::leave_disconnected_fullscreen(self.swapchain.get_window()) {
+            warn!("The fullscreen monitor was disconnected, switching to windowed");
+        }
+        // waits for the device to go idle, so the old pipeline and command pool are no
+        // longer in use either
+        self.swapchain.recreate()?;
+        self.recreate_command_pool(logical_device)
+    }
+
+    /// Recreates the pipeline and the command pool drawing with it, which must no longer
+    /// be in use
+    fn recreate_command_pool(&mut self, logical_device: &Shared<LogicalDevice>) -> Result<()> {
+        let pipeline = create_pipeline(
+            logical_device,
+            &self.swapchain,
+            self.stencil,
+            self.render_scale,
+        )?;
+        // keep drawing the same thing with the new command pool
+        let draw_command = self.command_pool.get_draw_command();
+        let clear_color = self.command_pool.get_clear_color();
+        self.command_pool = CommandPool::new(logical_device, pipeline)?;
+        self.command_pool.set_draw_command(draw_command);
+        self.command_pool.set_clear_color(clear_color);
+        Ok(())
+    }
+
+    fn get_window_id(&self) -> WindowId {
+        self.swapchain.get_window().id()
+    }
+}
+
+/// Creates the pipeline a window draws with, with a depth stencil buffer if `stencil` is
+/// given, at the render scale's fraction of the window's resolution
+fn create_pipeline(
+    logical_device: &Shared<LogicalDevice>,
+    swapchain: &Swapchain,
+    stencil: Option<StencilConfig>,
+    render_scale: RenderScale,
+) -> Result<GraphicsPipeline> {
+    let config = PipelineConfig {
+        blend_mode: BlendMode::Opaque,
+        stencil,
+        render_scale,
+        ..Default::default()
+    };
+    GraphicsPipeline::new::<()>(logical_device, swapchain, config)
+}
+
+/// Queries the system for the available physical devices, and picks the most appropriate one for use.
+fn pick_physical_device(
+    instance: &Shared<Instance>,
+    surface: &Shared<Surface>,
+) -> Result<PhysicalDeviceSurface> {
+    let physical_devices = unsafe { instance.enumerate_physical_devices()? };
+    let mut rejections = vec![];
+    for pd in physical_devices {
+        let pds = PhysicalDeviceSurface::new(instance, surface, pd)?;
+        match pds.is_suitable()? {
+            Ok(()) => return Ok(pds),
+            Err(reason) => rejections.push(format!("{}: {reason}", pds.get_device_name()?)),
+        }
+    }
+    if rejections.is_empty() {
+        return Err(anyhow!(
+            "Could not find a suitable physical device! No devices were found."
+        ));
+    }
+    Err(anyhow!(
+        "Could not find a suitable physical device!\n  {}",
+        rejections.join("\n  ")
+    ))
+}
+
+/// If validations are enabled, creates and registers the DebugUtils extension which prints
+/// logs to the console.
+fn setup_debug_messenger(instance: &Shared<Instance>) -> Result<Option<DebugMessenger>> {
+    // with only debug names there's no layer to report anything
+    if instance.is_validation_enabled() {
+        return Ok(Some(DebugMessenger::new(instance)?));
+    }
+    Ok(None)
+}
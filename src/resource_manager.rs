@@ -0,0 +1,268 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use crate::{GraphicsPipeline, Image, Mesh};
+
+/// A lightweight, copyable reference to a resource owned by a [`ResourceManager`]. Holds
+/// the slot the resource lives in and the generation of that slot, so a handle to a
+/// removed resource doesn't resolve to whatever reuses its slot later.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _resource: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// implemented by hand so handles are copyable whatever the resource type is
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    resource: Option<T>,
+}
+
+/// Resources of one type, stored in slots reused once freed
+pub struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    free_indices: Vec<u32>,
+}
+
+impl<T> Pool<T> {
+    fn new() -> Self {
+        Self {
+            slots: vec![],
+            free_indices: vec![],
+        }
+    }
+
+    fn insert(&mut self, resource: T) -> Handle<T> {
+        let index = match self.free_indices.pop() {
+            Some(index) => {
+                self.slots[index as usize].resource = Some(resource);
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    resource: Some(resource),
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        Handle {
+            index,
+            generation: self.slots[index as usize].generation,
+            _resource: PhantomData,
+        }
+    }
+
+    fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.resource.as_ref())
+    }
+
+    fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.resource.as_mut())
+    }
+
+    fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let resource = slot.resource.take()?;
+        // outstanding handles to the slot go stale
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_indices.push(handle.index);
+        Some(resource)
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len() - self.free_indices.len()
+    }
+}
+
+/// Resource types a [`ResourceManager`] can own, picking the pool they're stored in
+pub trait Resource: Sized {
+    fn pool(resource_manager: &ResourceManager) -> &Pool<Self>;
+
+    fn pool_mut(resource_manager: &mut ResourceManager) -> &mut Pool<Self>;
+}
+
+macro_rules! impl_resource {
+    ($($ty:ty => $pool:ident),* $(,)?) => {
+        $(
+            impl Resource for $ty {
+                fn pool(resource_manager: &ResourceManager) -> &Pool<Self> {
+                    &resource_manager.$pool
+                }
+
+                fn pool_mut(resource_manager: &mut ResourceManager) -> &mut Pool<Self> {
+                    &mut resource_manager.$pool
+                }
+            }
+        )*
+    };
+}
+
+/// Owns an app's meshes, textures and pipelines, handing out [`Handle`]s to them so
+/// they can be passed around and stored without sharing ownership. Resources are
+/// destroyed when removed, or along with the manager, which therefore has to be dropped
+/// before the logical device is waited on and torn down.
+pub struct ResourceManager {
+    meshes: Pool<Mesh>,
+    textures: Pool<Image>,
+    pipelines: Pool<GraphicsPipeline>,
+}
+
+impl_resource! {
+    Mesh => meshes,
+    Image => textures,
+    GraphicsPipeline => pipelines,
+}
+
+impl ResourceManager {
+    pub fn new() -> Self {
+        Self {
+            meshes: Pool::new(),
+            textures: Pool::new(),
+            pipelines: Pool::new(),
+        }
+    }
+
+    /// Takes ownership of the resource, returning a handle to it
+    pub fn insert<T: Resource>(&mut self, resource: T) -> Handle<T> {
+        T::pool_mut(self).insert(resource)
+    }
+
+    /// Resolves the handle, returning `None` if its resource has been removed
+    pub fn get<T: Resource>(&self, handle: Handle<T>) -> Option<&T> {
+        T::pool(self).get(handle)
+    }
+
+    pub fn get_mut<T: Resource>(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        T::pool_mut(self).get_mut(handle)
+    }
+
+    /// Removes the resource, giving it back to be dropped (destroying it) or kept. The
+    /// caller has to make sure the GPU is done with it first. Handles to it go stale.
+    pub fn remove<T: Resource>(&mut self, handle: Handle<T>) -> Option<T> {
+        T::pool_mut(self).remove(handle)
+    }
+
+    /// Returns how many resources of the type are held
+    pub fn count<T: Resource>(&self) -> usize {
+        T::pool(self).len()
+    }
+}
+
+impl Default for ResourceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_handles_resolve_to_nothing() {
+        let mut pool = Pool::new();
+        let handle = pool.insert(1);
+        assert_eq!(pool.remove(handle), Some(1));
+        assert_eq!(pool.get(handle), None);
+        assert_eq!(pool.get_mut(handle), None);
+        assert_eq!(pool.remove(handle), None);
+    }
+
+    #[test]
+    fn reused_slots_get_a_new_generation() {
+        let mut pool = Pool::new();
+        let old_handle = pool.insert(1);
+        pool.remove(old_handle);
+        let new_handle = pool.insert(2);
+        assert_eq!(new_handle.get_index(), old_handle.get_index());
+        assert_ne!(new_handle.get_generation(), old_handle.get_generation());
+        assert_eq!(pool.get(old_handle), None);
+        assert_eq!(pool.remove(old_handle), None);
+        assert_eq!(pool.get(new_handle), Some(&2));
+    }
+
+    #[test]
+    fn len_counts_held_resources() {
+        let mut pool = Pool::new();
+        let handles = [pool.insert(1), pool.insert(2), pool.insert(3)];
+        assert_eq!(pool.len(), 3);
+        pool.remove(handles[1]);
+        assert_eq!(pool.len(), 2);
+        // removing a stale handle again doesn't free the slot twice
+        pool.remove(handles[1]);
+        assert_eq!(pool.len(), 2);
+        pool.insert(4);
+        assert_eq!(pool.len(), 3);
+        pool.insert(5);
+        assert_eq!(pool.len(), 4);
+    }
+
+    #[test]
+    fn pools_for_different_types_dont_alias() {
+        let mut numbers = Pool::new();
+        let mut names = Pool::new();
+        let number = numbers.insert(1);
+        let name = names.insert("one");
+        // both are the first slot of their pool, but only resolve in their own
+        assert_eq!(number.get_index(), name.get_index());
+        assert_eq!(number.get_generation(), name.get_generation());
+        names.remove(name);
+        assert_eq!(numbers.get(number), Some(&1));
+        assert_eq!(names.get(name), None);
+    }
+}
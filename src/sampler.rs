@@ -0,0 +1,90 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use ash::vk::{
+    self, BorderColor, CompareOp, Filter, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode,
+    LOD_CLAMP_NONE,
+};
+
+use crate::{LogicalDevice, Shared};
+
+/// How shaders read from a sampled image
+pub struct Sampler {
+    logical_device: Shared<LogicalDevice>,
+    sampler: vk::Sampler,
+}
+
+impl Sampler {
+    /// Creates a sampler filtering linearly between texels and mip levels, addressing
+    /// coordinates outside the image with `address_mode`
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        address_mode: SamplerAddressMode,
+    ) -> Result<Self> {
+        let sampler_create_info = SamplerCreateInfo::default()
+            .mag_filter(Filter::LINEAR)
+            .min_filter(Filter::LINEAR)
+            .mipmap_mode(SamplerMipmapMode::LINEAR)
+            .address_mode_u(address_mode)
+            .address_mode_v(address_mode)
+            .address_mode_w(address_mode)
+            // the samplerAnisotropy feature isn't enabled
+            .anisotropy_enable(false)
+            .compare_enable(false)
+            .compare_op(CompareOp::ALWAYS)
+            .min_lod(0.0)
+            // sample from every mip level the view has
+            .max_lod(LOD_CLAMP_NONE)
+            .border_color(BorderColor::INT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false);
+        Self::from_create_info(logical_device, &sampler_create_info)
+    }
+
+    /// Creates a sampler comparing depth images against a reference value instead of
+    /// returning their depth, as `sampler2DShadow`, for shadow maps. Returns 1.0 where the
+    /// reference is closer than or as close as the stored depth. Reads outside the
+    /// image count as lit.
+    pub fn comparison(logical_device: &Shared<LogicalDevice>, filter: Filter) -> Result<Self> {
+        let sampler_create_info = SamplerCreateInfo::default()
+            .mag_filter(filter)
+            .min_filter(filter)
+            .mipmap_mode(SamplerMipmapMode::NEAREST)
+            .address_mode_u(SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_v(SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_w(SamplerAddressMode::CLAMP_TO_BORDER)
+            .anisotropy_enable(false)
+            .compare_enable(true)
+            .compare_op(CompareOp::LESS_OR_EQUAL)
+            .min_lod(0.0)
+            .max_lod(LOD_CLAMP_NONE)
+            .border_color(BorderColor::FLOAT_OPAQUE_WHITE)
+            .unnormalized_coordinates(false);
+        Self::from_create_info(logical_device, &sampler_create_info)
+    }
+
+    fn from_create_info(
+        logical_device: &Shared<LogicalDevice>,
+        sampler_create_info: &SamplerCreateInfo,
+    ) -> Result<Self> {
+        let sampler = unsafe { logical_device.create_sampler(sampler_create_info, None)? };
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            sampler,
+        })
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe { self.logical_device.destroy_sampler(self.sampler, None) }
+    }
+}
+
+impl Deref for Sampler {
+    type Target = vk::Sampler;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sampler
+    }
+}
@@ -0,0 +1,236 @@
+use std::ops::Mul;
+
+/// A 4x4 column-major matrix of `f32`s, matching GLSL's `mat4`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    /// The matrix's columns, each of 4 rows
+    pub cols: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self {
+        cols: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn from_translation(translation: [f32; 3]) -> Self {
+        let [x, y, z] = translation;
+        let mut matrix = Self::IDENTITY;
+        matrix.cols[3] = [x, y, z, 1.0];
+        matrix
+    }
+
+    pub fn from_scale(scale: [f32; 3]) -> Self {
+        let [x, y, z] = scale;
+        let mut matrix = Self::IDENTITY;
+        matrix.cols[0][0] = x;
+        matrix.cols[1][1] = y;
+        matrix.cols[2][2] = z;
+        matrix
+    }
+
+    /// Rotates `angle` radians around the x axis
+    pub fn from_rotation_x(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut matrix = Self::IDENTITY;
+        matrix.cols[1] = [0.0, cos, sin, 0.0];
+        matrix.cols[2] = [0.0, -sin, cos, 0.0];
+        matrix
+    }
+
+    /// Rotates `angle` radians around the y axis
+    pub fn from_rotation_y(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut matrix = Self::IDENTITY;
+        matrix.cols[0] = [cos, 0.0, -sin, 0.0];
+        matrix.cols[2] = [sin, 0.0, cos, 0.0];
+        matrix
+    }
+
+    /// Rotates `angle` radians around the z axis
+    pub fn from_rotation_z(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        let mut matrix = Self::IDENTITY;
+        matrix.cols[0] = [cos, sin, 0.0, 0.0];
+        matrix.cols[1] = [-sin, cos, 0.0, 0.0];
+        matrix
+    }
+
+    /// A right-handed perspective projection, for a camera looking down -z with a
+    /// vertical field of view of `fov_y` radians. Depth goes from 0.0 at the `near` plane
+    /// to 1.0 at the `far` plane, and y is flipped to point down like Vulkan's clip space.
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let focal_length = 1.0 / (fov_y / 2.0).tan();
+        Self {
+            cols: [
+                [focal_length / aspect, 0.0, 0.0, 0.0],
+                [0.0, -focal_length, 0.0, 0.0],
+                [0.0, 0.0, far / (near - far), -1.0],
+                [0.0, 0.0, near * far / (near - far), 0.0],
+            ],
+        }
+    }
+
+    /// Like [`Mat4::perspective`], but with depth going from 1.0 at the `near` plane to
+    /// 0.0 at the `far` plane, for reverse-Z. See [`crate::DepthConfig::reverse_z`].
+    pub fn perspective_reverse_z(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        // the same projection with the planes swapped
+        Self::perspective(fov_y, aspect, far, near)
+    }
+
+    /// Returns the matrix as 16 floats, column after column, for uploading to shaders
+    pub fn to_cols_array(&self) -> [f32; 16] {
+        let mut array = [0.0; 16];
+        for (col_idx, col) in self.cols.iter().enumerate() {
+            array[col_idx * 4..col_idx * 4 + 4].copy_from_slice(col);
+        }
+        array
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    /// Combines the transforms, applying `rhs` first and then `self`
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut cols = [[0.0; 4]; 4];
+        for (col_idx, col) in cols.iter_mut().enumerate() {
+            for (row_idx, value) in col.iter_mut().enumerate() {
+                *value = (0..4)
+                    .map(|idx| self.cols[idx][row_idx] * rhs.cols[col_idx][idx])
+                    .sum();
+            }
+        }
+        Self { cols }
+    }
+}
+
+/// A node of a scene graph, positioning a mesh (and its children) relative to its
+/// parent. Meshes are referred to by their index into whatever list the scene keeps
+/// them in, such as the meshes of a loaded model, so nodes don't depend on the device.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Node {
+    /// Transform from this node's space into its parent's
+    pub transform: Mat4,
+    pub children: Vec<Node>,
+    /// Index of the mesh drawn at this node, if any
+    pub mesh: Option<usize>,
+}
+
+impl Node {
+    pub fn new(transform: Mat4) -> Self {
+        Self {
+            transform,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_mesh(mut self, mesh: usize) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    pub fn add_child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Walks the tree depth first, calling `visit` with the world transform (the
+    /// transforms of every ancestor, then this node's, combined) and mesh of each node
+    /// that has one
+    pub fn traverse<F>(&self, mut visit: F)
+    where
+        F: FnMut(&Mat4, usize),
+    {
+        self.traverse_from(&Mat4::IDENTITY, &mut visit);
+    }
+
+    /// Returns the world transform and mesh of every node with one, in traversal order
+    pub fn collect_draws(&self) -> Vec<(Mat4, usize)> {
+        let mut draws = vec![];
+        self.traverse(|world_transform, mesh| draws.push((*world_transform, mesh)));
+        draws
+    }
+
+    fn traverse_from<F>(&self, parent_transform: &Mat4, visit: &mut F)
+    where
+        F: FnMut(&Mat4, usize),
+    {
+        let world_transform = *parent_transform * self.transform;
+        if let Some(mesh) = self.mesh {
+            visit(&world_transform, mesh);
+        }
+        for child in &self.children {
+            child.traverse_from(&world_transform, visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_identity() {
+        let matrices = [
+            Mat4::from_translation([1.0, -2.0, 3.0]),
+            Mat4::from_scale([2.0, 3.0, 4.0]),
+            Mat4::from_rotation_y(0.5),
+            Mat4::perspective(1.0, 16.0 / 9.0, 0.1, 100.0),
+        ];
+        for matrix in matrices {
+            assert_eq!(Mat4::default() * matrix, matrix);
+            assert_eq!(matrix * Mat4::default(), matrix);
+        }
+    }
+
+    #[test]
+    fn translation_after_scale() {
+        let matrix = Mat4::from_translation([1.0, 2.0, 3.0]) * Mat4::from_scale([2.0, 3.0, 4.0]);
+        assert_eq!(
+            matrix.to_cols_array(),
+            [
+                2.0, 0.0, 0.0, 0.0, //
+                0.0, 3.0, 0.0, 0.0, //
+                0.0, 0.0, 4.0, 0.0, //
+                1.0, 2.0, 3.0, 1.0,
+            ]
+        );
+    }
+
+    #[test]
+    fn children_are_drawn_relative_to_their_parent() {
+        let parent_transform = Mat4::from_translation([1.0, 0.0, 0.0]);
+        let child_transform = Mat4::from_scale([2.0, 2.0, 2.0]);
+        let node = Node::new(parent_transform)
+            .with_mesh(0)
+            .add_child(Node::new(child_transform).with_mesh(1));
+        assert_eq!(
+            node.collect_draws(),
+            [
+                (parent_transform, 0),
+                (parent_transform * child_transform, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn nodes_without_a_mesh_are_not_drawn() {
+        let transform = Mat4::from_translation([0.0, 1.0, 0.0]);
+        let node = Node::new(transform)
+            .add_child(Node::default())
+            .add_child(Node::new(transform).add_child(Node::default().with_mesh(2)));
+        assert_eq!(node.collect_draws(), [(transform * transform, 2)]);
+        assert!(Node::default().collect_draws().is_empty());
+    }
+}
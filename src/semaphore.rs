@@ -0,0 +1,39 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use ash::vk::{self, SemaphoreCreateInfo};
+
+use crate::{LogicalDevice, Shared};
+
+/// A binary semaphore, for ordering work on the GPU such as waiting for a
+/// swapchain image before rendering to it
+pub struct Semaphore {
+    logical_device: Shared<LogicalDevice>,
+    semaphore: vk::Semaphore,
+}
+
+impl Semaphore {
+    pub fn new(logical_device: &Shared<LogicalDevice>) -> Result<Self> {
+        let semaphore_create_info = SemaphoreCreateInfo::default();
+        let semaphore = unsafe { logical_device.create_semaphore(&semaphore_create_info, None)? };
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            semaphore,
+        })
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe { self.logical_device.destroy_semaphore(self.semaphore, None) }
+    }
+}
+
+impl Deref for Semaphore {
+    type Target = vk::Semaphore;
+
+    fn deref(&self) -> &Self::Target {
+        &self.semaphore
+    }
+}
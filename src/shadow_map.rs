@@ -0,0 +1,300 @@
+use std::ops::Deref;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use ash::vk::{
+    self, ClearDepthStencilValue, ClearValue, CommandBuffer, CompareOp, CullModeFlags, Extent2D,
+    Filter, FormatFeatureFlags, FramebufferCreateInfo, FrontFace, GraphicsPipelineCreateInfo,
+    ImageAspectFlags, ImageUsageFlags, PipelineBindPoint, PipelineCache,
+    PipelineDepthStencilStateCreateInfo, PipelineInputAssemblyStateCreateInfo,
+    PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
+    PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
+    PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, Rect2D, RenderPassBeginInfo,
+    SampleCountFlags, ShaderStageFlags, SubpassContents, Viewport,
+};
+
+use crate::{
+    create_shader_module, Image, ImageDescriptor, LogicalDevice, PipelineLayout, RenderPass,
+    RenderPassBuilder, RenderStep, Sampler, Shared, Vertex,
+};
+
+/// An offscreen depth image the scene is rendered into from a light's point of view, then
+/// sampled by the main pass with a comparison sampler to tell which fragments the light
+/// can't see. Draw into it with a [`ShadowPipeline`] thru a [`ShadowPass`] step.
+pub struct ShadowMap {
+    logical_device: Shared<LogicalDevice>,
+    framebuffer: vk::Framebuffer,
+    image: Image,
+    render_pass: RenderPass,
+    sampler: Sampler,
+}
+
+impl ShadowMap {
+    /// Creates a square shadow map `size` texels across
+    pub fn new(logical_device: &Shared<LogicalDevice>, size: u32) -> Result<Self> {
+        ensure!(size > 0, "Shadow maps must be at least one texel across!");
+        let depth_format = logical_device.find_sampled_depth_format()?;
+        let extent = Extent2D {
+            width: size,
+            height: size,
+        };
+        let image = Image::new(
+            logical_device,
+            &ImageDescriptor {
+                format: depth_format,
+                extent,
+                usage: ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | ImageUsageFlags::SAMPLED,
+                aspect: ImageAspectFlags::DEPTH,
+                ..Default::default()
+            },
+        )?;
+        logical_device.set_object_name(*image, "shadow map")?;
+        let render_pass = RenderPassBuilder::depth_only(depth_format).build(logical_device)?;
+
+        let attachments = [**image
+            .get_view()
+            .context("Framebuffer attachments need an image view")?];
+        let framebuffer_create_info = FramebufferCreateInfo::default()
+            .render_pass(*render_pass)
+            .attachments(&attachments)
+            .width(size)
+            .height(size)
+            .layers(1);
+        let framebuffer =
+            unsafe { logical_device.create_framebuffer(&framebuffer_create_info, None)? };
+
+        // filter the comparisons for softer edges where the format allows it
+        let filter = if logical_device
+            .get_format_features(depth_format)
+            .contains(FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+        {
+            Filter::LINEAR
+        } else {
+            Filter::NEAREST
+        };
+        let sampler = match Sampler::comparison(logical_device, filter) {
+            Ok(sampler) => sampler,
+            Err(err) => {
+                unsafe { logical_device.destroy_framebuffer(framebuffer, None) };
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            framebuffer,
+            image,
+            render_pass,
+            sampler,
+        })
+    }
+
+    /// Returns the depth image, in `DEPTH_STENCIL_READ_ONLY_OPTIMAL` once rendered to, for
+    /// binding with the comparison sampler
+    pub fn get_image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Returns the comparison sampler to read the shadow map with
+    pub fn get_sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    pub fn get_render_pass(&self) -> &RenderPass {
+        &self.render_pass
+    }
+
+    pub fn get_extent(&self) -> Extent2D {
+        self.image.get_extent()
+    }
+
+    /// Records the shadow map's render pass with the pipeline bound, having `draw` record
+    /// the shadow casters inside it
+    pub fn record<F>(
+        &self,
+        command_buffer: CommandBuffer,
+        pipeline: &ShadowPipeline,
+        draw: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(CommandBuffer) -> Result<()>,
+    {
+        let mut clear_value = ClearValue::default();
+        clear_value.depth_stencil = ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        };
+        let clear_values = [clear_value];
+        let render_pass_begin_info = RenderPassBeginInfo::default()
+            .render_pass(*self.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(Rect2D::default().extent(self.get_extent()))
+            .clear_values(&clear_values);
+        unsafe {
+            self.logical_device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_begin_info,
+                SubpassContents::INLINE,
+            );
+            self.logical_device.cmd_bind_pipeline(
+                command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                **pipeline,
+            );
+        }
+        // end the render pass even if drawing fails, so the command buffer stays valid
+        let result = draw(command_buffer);
+        unsafe { self.logical_device.cmd_end_render_pass(command_buffer) };
+        result
+    }
+}
+
+impl Drop for ShadowMap {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device
+                .destroy_framebuffer(self.framebuffer, None)
+        }
+    }
+}
+
+/// A pipeline rendering only depth into a [`ShadowMap`], running a vertex shader that
+/// transforms vertices laid out as `V` into the light's clip space, and no fragment shader
+pub struct ShadowPipeline {
+    logical_device: Shared<LogicalDevice>,
+    pipeline: vk::Pipeline,
+    pipeline_layout: PipelineLayout,
+}
+
+impl ShadowPipeline {
+    /// Creates the pipeline from the vertex shader's SPIR-V, with the layout of the
+    /// descriptors and push constants it reads, such as the light's matrix
+    pub fn new<V: Vertex>(
+        logical_device: &Shared<LogicalDevice>,
+        shadow_map: &ShadowMap,
+        vertex_shader_code: &[u8],
+        pipeline_layout: PipelineLayout,
+    ) -> Result<Self> {
+        ensure!(
+            vertex_shader_code.len().is_multiple_of(4),
+            "Invalid vertex shader code read!"
+        );
+        let vertex_shader_module = create_shader_module(logical_device, vertex_shader_code)?;
+        let shader_stage_create_infos = [PipelineShaderStageCreateInfo::default()
+            .stage(ShaderStageFlags::VERTEX)
+            .module(vertex_shader_module)
+            .name(c"main")];
+
+        let vertex_binding_descriptions = [V::binding_description()];
+        let vertex_attribute_descriptions = V::attribute_descriptions();
+        let mut vertex_input_state_create_info = PipelineVertexInputStateCreateInfo::default();
+        if !vertex_attribute_descriptions.is_empty() {
+            vertex_input_state_create_info = vertex_input_state_create_info
+                .vertex_binding_descriptions(&vertex_binding_descriptions)
+                .vertex_attribute_descriptions(&vertex_attribute_descriptions);
+        }
+        let input_assembly_state_create_info = PipelineInputAssemblyStateCreateInfo::default()
+            .topology(PrimitiveTopology::TRIANGLE_LIST)
+            .primitive_restart_enable(false);
+
+        let extent = shadow_map.get_extent();
+        let viewports = [Viewport::default()
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0)];
+        let scissors = [Rect2D::default().extent(extent)];
+        let viewport_state_create_info = PipelineViewportStateCreateInfo::default()
+            .viewports(&viewports)
+            .scissors(&scissors);
+
+        let rasterization_state_create_info = PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(PolygonMode::FILL)
+            .line_width(1.0)
+            // only the back faces' depth is kept, and surfaces are pushed away from the
+            // light a little, both to stop them shadowing themselves ("shadow acne")
+            .cull_mode(CullModeFlags::FRONT)
+            .front_face(FrontFace::CLOCKWISE)
+            .depth_bias_enable(true)
+            .depth_bias_constant_factor(1.25)
+            .depth_bias_slope_factor(1.75);
+        let multisample_state_create_info = PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(SampleCountFlags::TYPE_1);
+        let depth_stencil_state_create_info = PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(true)
+            .depth_write_enable(true)
+            .depth_compare_op(CompareOp::LESS_OR_EQUAL);
+
+        let graphics_pipeline_create_info = GraphicsPipelineCreateInfo::default()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_create_info)
+            .input_assembly_state(&input_assembly_state_create_info)
+            .viewport_state(&viewport_state_create_info)
+            .rasterization_state(&rasterization_state_create_info)
+            .multisample_state(&multisample_state_create_info)
+            .depth_stencil_state(&depth_stencil_state_create_info)
+            .layout(*pipeline_layout)
+            .render_pass(**shadow_map.get_render_pass());
+        let result = unsafe {
+            logical_device.create_graphics_pipelines(
+                PipelineCache::null(),
+                &[graphics_pipeline_create_info],
+                None,
+            )
+        };
+        unsafe { logical_device.destroy_shader_module(vertex_shader_module, None) };
+        let pipeline = result
+            .map_err(|(_, r)| r)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No shadow pipeline was created!"))?;
+        logical_device.set_object_name(pipeline, "shadow pipeline")?;
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            pipeline,
+            pipeline_layout,
+        })
+    }
+
+    pub fn get_pipeline_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+}
+
+impl Drop for ShadowPipeline {
+    fn drop(&mut self) {
+        unsafe { self.logical_device.destroy_pipeline(self.pipeline, None) }
+    }
+}
+
+impl Deref for ShadowPipeline {
+    type Target = vk::Pipeline;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pipeline
+    }
+}
+
+/// A [`RenderStep`] rendering the shadow casters into a shadow map ahead of the main pass,
+/// with `draw` recording them (given the command buffer and frame index)
+pub struct ShadowPass<'a, F>
+where
+    F: FnMut(CommandBuffer, usize) -> Result<()>,
+{
+    pub shadow_map: &'a ShadowMap,
+    pub pipeline: &'a ShadowPipeline,
+    pub draw: F,
+}
+
+impl<F> RenderStep for ShadowPass<'_, F>
+where
+    F: FnMut(CommandBuffer, usize) -> Result<()>,
+{
+    fn record(&mut self, command_buffer: CommandBuffer, frame_idx: usize) -> Result<()> {
+        let draw = &mut self.draw;
+        self.shadow_map
+            .record(command_buffer, self.pipeline, |command_buffer| {
+                draw(command_buffer, frame_idx)
+            })
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use ash::vk::{
+    self, CommandBuffer, CommandBufferAllocateInfo, CommandBufferLevel, CommandPoolCreateInfo,
+};
+
+use crate::{GraphicsPipeline, LogicalDevice, Shared};
+
+/// Command buffers recorded once, one per swapchain image, and submitted again every
+/// frame instead of re-recording the frame's own, for scenes that don't change. Made
+/// with [`crate::CommandPool::record_static`], and only valid for the pipeline they were
+/// recorded with, so they have to be recorded again once the swapchain is recreated.
+pub struct StaticCommands {
+    logical_device: Shared<LogicalDevice>,
+    /// Owned by us, so the command buffers can outlive the frames' pool
+    command_pool: vk::CommandPool,
+    command_buffers: Vec<CommandBuffer>,
+    /// The pipeline the command buffers draw with, and whose framebuffers they draw to
+    graphics_pipeline: Shared<GraphicsPipeline>,
+}
+
+impl StaticCommands {
+    /// Allocates a command buffer per swapchain image, left for the caller to record
+    pub(crate) fn new(
+        logical_device: &Shared<LogicalDevice>,
+        graphics_pipeline: &Shared<GraphicsPipeline>,
+        image_count: u32,
+    ) -> Result<Self> {
+        let create_command_pool = CommandPoolCreateInfo::default()
+            .queue_family_index(logical_device.graphics_queue().get_family_index());
+        let command_pool =
+            unsafe { logical_device.create_command_pool(&create_command_pool, None)? };
+        // build up the struct first so the pool is destroyed if allocating fails
+        let mut this = Self {
+            logical_device: Shared::clone(logical_device),
+            command_pool,
+            command_buffers: vec![],
+            graphics_pipeline: Shared::clone(graphics_pipeline),
+        };
+        logical_device.set_object_name(command_pool, "static command pool")?;
+
+        let allocate_info = CommandBufferAllocateInfo::default()
+            .command_pool(command_pool)
+            .level(CommandBufferLevel::PRIMARY)
+            .command_buffer_count(image_count);
+        this.command_buffers = unsafe { logical_device.allocate_command_buffers(&allocate_info)? };
+        for (idx, command_buffer) in this.command_buffers.iter().enumerate() {
+            logical_device
+                .set_object_name(*command_buffer, &format!("static command buffer {idx}"))?;
+        }
+        Ok(this)
+    }
+
+    pub(crate) fn get_command_buffers(&self) -> &[CommandBuffer] {
+        &self.command_buffers
+    }
+
+    /// Returns the command buffer drawing to the swapchain image with the given index
+    pub(crate) fn get_command_buffer(&self, image_index: usize) -> Result<CommandBuffer> {
+        self.command_buffers
+            .get(image_index)
+            .copied()
+            .context("No static commands were recorded for the swapchain image!")
+    }
+
+    /// Whether the commands were recorded with the pipeline, so can be submitted in its
+    /// place. False once the pipeline has been recreated, for instance after a resize.
+    pub fn is_recorded_for(&self, graphics_pipeline: &Shared<GraphicsPipeline>) -> bool {
+        Shared::ptr_eq(&self.graphics_pipeline, graphics_pipeline)
+    }
+}
+
+impl Drop for StaticCommands {
+    fn drop(&mut self) {
+        // the command buffers are freed with the pool
+        unsafe {
+            self.logical_device
+                .destroy_command_pool(self.command_pool, None)
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use ash::vk::{
+    CommandBuffer, Fence, PipelineStageFlags, QueueFlags, Semaphore, SubmitInfo,
+    TimelineSemaphoreSubmitInfo,
+};
+
+use crate::{error::VkResultExt, LogicalDevice, Queue};
+
+/// A single submission within a [`SubmitBatch`]: the command buffers to run, and the
+/// semaphores to wait on before and signal after running them
+#[derive(Clone, Debug, Default)]
+pub struct Submission {
+    command_buffers: Vec<CommandBuffer>,
+    wait_semaphores: Vec<Semaphore>,
+    wait_dst_stage_masks: Vec<PipelineStageFlags>,
+    /// Values to wait for, per wait semaphore. Ignored for binary semaphores.
+    wait_values: Vec<u64>,
+    signal_semaphores: Vec<Semaphore>,
+    /// Values to signal, per signal semaphore. Ignored for binary semaphores.
+    signal_values: Vec<u64>,
+    /// Whether any of the semaphores are timeline semaphores, needing their values passed
+    uses_timeline_semaphores: bool,
+}
+
+impl Submission {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn command_buffer(mut self, command_buffer: CommandBuffer) -> Self {
+        self.command_buffers.push(command_buffer);
+        self
+    }
+
+    /// Waits for the binary semaphore to be signaled before running the given stages
+    pub fn wait_semaphore(mut self, semaphore: Semaphore, stage: PipelineStageFlags) -> Self {
+        self.wait_semaphores.push(semaphore);
+        self.wait_dst_stage_masks.push(stage);
+        self.wait_values.push(0);
+        self
+    }
+
+    /// Waits for the timeline semaphore to reach `value` before running the given stages
+    pub fn wait_timeline_semaphore(
+        mut self,
+        semaphore: Semaphore,
+        value: u64,
+        stage: PipelineStageFlags,
+    ) -> Self {
+        self.wait_semaphores.push(semaphore);
+        self.wait_dst_stage_masks.push(stage);
+        self.wait_values.push(value);
+        self.uses_timeline_semaphores = true;
+        self
+    }
+
+    /// Signals the binary semaphore once the command buffers have finished
+    pub fn signal_semaphore(mut self, semaphore: Semaphore) -> Self {
+        self.signal_semaphores.push(semaphore);
+        self.signal_values.push(0);
+        self
+    }
+
+    /// Sets the timeline semaphore to `value` once the command buffers have finished
+    pub fn signal_timeline_semaphore(mut self, semaphore: Semaphore, value: u64) -> Self {
+        self.signal_semaphores.push(semaphore);
+        self.signal_values.push(value);
+        self.uses_timeline_semaphores = true;
+        self
+    }
+}
+
+/// Accumulates several submissions, for instance a shadow pass and the main pass, to
+/// hand them to the queue in a single `queue_submit` call
+#[derive(Clone, Debug, Default)]
+pub struct SubmitBatch {
+    submissions: Vec<Submission>,
+    /// The operations the queue has to support to run the submissions
+    required_queue_flags: QueueFlags,
+}
+
+impl SubmitBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_submission(mut self, submission: Submission) -> Self {
+        self.submissions.push(submission);
+        self
+    }
+
+    /// Requires the queue the batch is submitted to to support the operations, for
+    /// instance `GRAPHICS` when the command buffers contain draws
+    pub fn requires(mut self, queue_flags: QueueFlags) -> Self {
+        self.required_queue_flags |= queue_flags;
+        self
+    }
+
+    /// Submits everything to the queue, signaling the fence (if not null) once all of
+    /// it has finished. Errors if the queue doesn't support the required operations.
+    pub fn submit(
+        &self,
+        logical_device: &LogicalDevice,
+        queue: &Queue,
+        fence: Fence,
+    ) -> Result<()> {
+        queue.ensure_supports(self.required_queue_flags)?;
+
+        let mut timeline_submit_infos = self
+            .submissions
+            .iter()
+            .map(|submission| {
+                TimelineSemaphoreSubmitInfo::default()
+                    .wait_semaphore_values(&submission.wait_values)
+                    .signal_semaphore_values(&submission.signal_values)
+            })
+            .collect::<Vec<_>>();
+
+        let submit_infos = self
+            .submissions
+            .iter()
+            .zip(timeline_submit_infos.iter_mut())
+            .map(|(submission, timeline_submit_info)| {
+                let submit_info = SubmitInfo::default()
+                    .command_buffers(&submission.command_buffers)
+                    .wait_semaphores(&submission.wait_semaphores)
+                    .wait_dst_stage_mask(&submission.wait_dst_stage_masks)
+                    .signal_semaphores(&submission.signal_semaphores);
+                if submission.uses_timeline_semaphores {
+                    submit_info.push_next(timeline_submit_info)
+                } else {
+                    submit_info
+                }
+            })
+            .collect::<Vec<_>>();
+
+        unsafe {
+            logical_device
+                .queue_submit(**queue, &submit_infos, fence)
+                .map_device_lost()?
+        };
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ops::Deref, rc::Rc};
+use std::ops::Deref;
 
 use anyhow::Result;
 use ash::{
@@ -10,17 +10,17 @@ use winit::{
     window::Window,
 };
 
-use crate::Instance;
+use crate::{Instance, Shared};
 
 pub struct Surface {
     surface_fn: surface::Instance,
     surface_ptr: SurfaceKHR,
     // references to make sure we are dropped before these
-    _instance: Rc<Instance>,
+    _instance: Shared<Instance>,
 }
 
 impl Surface {
-    pub fn new(instance: &Rc<Instance>, window: &Window) -> Result<Self> {
+    pub fn new(instance: &Shared<Instance>, window: &Window) -> Result<Self> {
         let surface_fn = surface::Instance::new(instance.get_entry(), instance);
         let surface_ptr = unsafe {
             ash_window::create_surface(
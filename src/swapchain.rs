@@ -1,107 +1,263 @@
-use std::{collections::HashSet, ops::Deref, rc::Rc};
+use std::ops::Deref;
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ash::{
     khr::swapchain,
     vk::{
-        CompositeAlphaFlagsKHR, Extent2D, Fence, Image, ImageUsageFlags, Semaphore, SharingMode,
+        CompositeAlphaFlagsKHR, Extent2D, Fence, Format, ImageUsageFlags, Semaphore, SharingMode,
         SurfaceFormatKHR, SwapchainCreateInfoKHR, SwapchainKHR,
     },
 };
+use tracing::info;
 use winit::window::Window;
 
-use crate::{ImageView, Instance, LogicalDevice};
+use crate::{
+    error::VkResultExt, physical_device_surface::query_swap_chain_support, ExtentClamping, Image,
+    ImageView, Instance, LogicalDevice, Shared, Surface, SwapChainSupportDetails,
+};
+
+/// How the swapchain's alpha channel is composited with whatever is behind the window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompositeAlphaPreference {
+    /// Alpha is ignored and the window is fully opaque
+    #[default]
+    Opaque,
+    /// The window is see-thru where alpha is below 1, with colors already multiplied
+    /// by alpha, as blending with [`crate::BlendMode::AlphaBlend`] over a transparent
+    /// clear color produces
+    PreMultiplied,
+    /// The window is see-thru where alpha is below 1, with the compositor multiplying
+    /// colors by alpha
+    PostMultiplied,
+}
+
+impl CompositeAlphaPreference {
+    pub fn get_composite_alpha_flags(&self) -> CompositeAlphaFlagsKHR {
+        match self {
+            CompositeAlphaPreference::Opaque => CompositeAlphaFlagsKHR::OPAQUE,
+            CompositeAlphaPreference::PreMultiplied => CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+            CompositeAlphaPreference::PostMultiplied => CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+        }
+    }
+}
+
+/// Something built from the swapchain's images or sized to them, like framebuffers, depth
+/// buffers or offscreen targets, which has to be rebuilt when the swapchain is recreated.
+/// Passed to [`Swapchain::recreate_and_notify`].
+pub trait SwapchainObserver {
+    /// Called once the swapchain has been recreated, with the device idle so nothing
+    /// built from the old images is still in use. The new extent and image views are read
+    /// from `swapchain`.
+    fn on_swapchain_recreated(&mut self, swapchain: &Swapchain) -> Result<()>;
+}
+
+/// How the swapchain was asked to be configured, as far as the surface allows
+#[derive(Clone, Copy, Debug)]
+struct SwapchainPreferences {
+    composite_alpha: CompositeAlphaPreference,
+    /// Overrides the automatically picked number of images, if set
+    image_count: Option<u32>,
+    extent_clamping: ExtentClamping,
+}
 
 pub struct Swapchain {
     swapchain_fn: swapchain::Device,
     swapchain_ptr: SwapchainKHR,
+    /// The swapchain's images, each with a view onto it. Rebuilt along with the swapchain.
+    images: Vec<Shared<Image>>,
     extent: Extent2D,
     surface_format: SurfaceFormatKHR,
+    /// What the images can be used for
+    image_usage: ImageUsageFlags,
+    /// What was asked for, kept to build the swapchain the same way when recreating it
+    preferences: SwapchainPreferences,
+    /// The composite alpha mode picked for the preference from what the surface supports
+    composite_alpha: CompositeAlphaFlagsKHR,
+    /// What the surface supports, used to configure the swapchain. The capabilities are
+    /// refreshed every time the swapchain is built.
+    swapchain_support_details: SwapChainSupportDetails,
+    logical_device: Shared<LogicalDevice>,
+    window: Shared<Window>,
+    surface: Shared<Surface>,
     // references we need to keep to ensure
     // we are cleaned up before they are
-    _instance: Rc<Instance>,
-    _window: Rc<Window>,
+    _instance: Shared<Instance>,
 }
 
 impl Swapchain {
+    /// Creates the swapchain for the surface the logical device was selected for. The
+    /// surface's support details are queried fresh, rather than reusing the ones captured
+    /// when the device was picked. The window is see-thru if `composite_alpha` asks for it
+    /// and the surface supports it, and opaque otherwise. `image_count` forces a number of
+    /// images, clamped to what the surface supports, instead of one more than the minimum.
     pub fn new(
-        instance: &Rc<Instance>,
-        window: &Rc<Window>,
-        logical_device: &Rc<LogicalDevice>,
+        instance: &Shared<Instance>,
+        window: &Shared<Window>,
+        logical_device: &Shared<LogicalDevice>,
+        composite_alpha: CompositeAlphaPreference,
+        image_count: Option<u32>,
     ) -> Result<Self> {
-        let queue_indicies = logical_device.get_queue_family_indicies();
-        let queue_family_indicies = Vec::from_iter(HashSet::from([
-            queue_indicies.graphics_family.unwrap() as u32,
-            queue_indicies.present_family.unwrap() as u32,
-        ]));
-
-        let swap_chain_support = logical_device.get_swapchain_support_details();
-        let surface_format = swap_chain_support.choose_swap_surface_format();
-        let present_mode = swap_chain_support.choose_swap_present_mode();
-        let extent = swap_chain_support.choose_swap_extent(window);
-        let image_count = swap_chain_support.get_image_count();
-
-        let mut swap_chain_creation_info = SwapchainCreateInfoKHR::default()
-            .surface(***logical_device.get_surface())
-            .min_image_count(image_count)
-            .image_format(surface_format.format)
-            .image_color_space(surface_format.color_space)
-            .image_extent(extent)
-            .present_mode(present_mode)
-            // always 1 unless doing sterioscopic 3D
-            .image_array_layers(1)
-            // use images as color attachments for drawing color pictures to
-            .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
-            // no transform
-            .pre_transform(swap_chain_support.capabilities.current_transform)
-            // ignore alpha channel
-            .composite_alpha(CompositeAlphaFlagsKHR::OPAQUE)
-            // enable clipping, to discard pixels that aren't visible
-            .clipped(true)
-            .old_swapchain(SwapchainKHR::null());
-        if queue_family_indicies.len() == 1 {
-            swap_chain_creation_info =
-                swap_chain_creation_info.image_sharing_mode(SharingMode::EXCLUSIVE);
-        } else {
-            swap_chain_creation_info = swap_chain_creation_info
-                .image_sharing_mode(SharingMode::CONCURRENT)
-                .queue_family_indices(&queue_family_indicies);
-        }
+        let swapchain_support_details = query_swap_chain_support(
+            &logical_device.get_physical_device(),
+            logical_device.get_surface(),
+        )?;
+        Self::create(
+            instance,
+            window,
+            logical_device,
+            logical_device.get_surface(),
+            swapchain_support_details,
+            SwapchainPreferences {
+                composite_alpha,
+                image_count,
+                extent_clamping: ExtentClamping::default(),
+            },
+        )
+    }
 
-        let swapchain_device = swapchain::Device::new(instance, &logical_device);
-        let swapchain =
-            unsafe { swapchain_device.create_swapchain(&swap_chain_creation_info, None) }?;
+    /// Creates a swapchain for an additional surface, for instance when rendering to a
+    /// second window. Errors if the logical device's present queue can't present to
+    /// the surface.
+    pub fn for_surface(
+        instance: &Shared<Instance>,
+        window: &Shared<Window>,
+        logical_device: &Shared<LogicalDevice>,
+        surface: &Shared<Surface>,
+        composite_alpha: CompositeAlphaPreference,
+        image_count: Option<u32>,
+    ) -> Result<Self> {
+        let physical_device = logical_device.get_physical_device();
+        let present_family = logical_device.present_queue().get_family_index();
+        ensure!(
+            surface.get_physical_device_surface_support(&physical_device, present_family)?,
+            "The device's present queue can't present to the surface!"
+        );
+
+        let swapchain_support_details = query_swap_chain_support(&physical_device, surface)?;
+        Self::create(
+            instance,
+            window,
+            logical_device,
+            surface,
+            swapchain_support_details,
+            SwapchainPreferences {
+                composite_alpha,
+                image_count,
+                extent_clamping: ExtentClamping::default(),
+            },
+        )
+    }
 
-        let extent = logical_device
-            .get_swapchain_support_details()
-            .choose_swap_extent(window);
-        let surface_format = logical_device
-            .get_swapchain_support_details()
-            .choose_swap_surface_format();
+    fn create(
+        instance: &Shared<Instance>,
+        window: &Shared<Window>,
+        logical_device: &Shared<LogicalDevice>,
+        surface: &Shared<Surface>,
+        mut swapchain_support_details: SwapChainSupportDetails,
+        preferences: SwapchainPreferences,
+    ) -> Result<Self> {
+        let swapchain_device = swapchain::Device::new(instance, logical_device);
+        let (swapchain, extent, surface_format, image_usage, composite_alpha) = create_swapchain(
+            &swapchain_device,
+            window,
+            logical_device,
+            surface,
+            &mut swapchain_support_details,
+            preferences,
+            SwapchainKHR::null(),
+        )?;
 
-        Ok(Self {
-            _instance: Rc::clone(instance),
+        let mut this = Self {
+            _instance: Shared::clone(instance),
             swapchain_fn: swapchain_device,
             swapchain_ptr: swapchain,
+            images: vec![],
             extent,
-            surface_format: *surface_format,
-            _window: Rc::clone(window),
-        })
+            surface_format,
+            image_usage,
+            preferences,
+            composite_alpha,
+            swapchain_support_details,
+            logical_device: Shared::clone(logical_device),
+            window: Shared::clone(window),
+            surface: Shared::clone(surface),
+        };
+        this.images = this.create_images()?;
+        Ok(this)
+    }
+
+    /// Sets how the window's size is clamped to the extents the surface supports, where
+    /// the surface leaves the size up to us. Applies from the next [`Swapchain::recreate`].
+    pub fn set_extent_clamping(&mut self, extent_clamping: ExtentClamping) {
+        self.preferences.extent_clamping = extent_clamping;
     }
 
-    pub fn get_swapchain_images(&self) -> Result<Vec<Image>> {
+    /// Replaces the current swapchain with a new one, for instance after the window has
+    /// been resized. The old swapchain is handed to the driver so it can reuse its
+    /// resources, and is only destroyed once the new one exists. Waits for the device to
+    /// go idle first, since in flight frames may still be using the old images. The
+    /// caller must ensure anything built from them is rebuilt afterwards.
+    pub fn recreate(&mut self) -> Result<()> {
+        self.logical_device.wait_idle()?;
+
+        // on failure the old swapchain is kept, and destroyed on drop as usual
+        let (swapchain, extent, surface_format, image_usage, composite_alpha) = create_swapchain(
+            &self.swapchain_fn,
+            &self.window,
+            &self.logical_device,
+            &self.surface,
+            &mut self.swapchain_support_details,
+            self.preferences,
+            self.swapchain_ptr,
+        )?;
+        // the old swapchain is retired by now, so none of its images can be acquired
+        // anymore and the idle wait above means none are still in use
+        self.images.clear();
+        unsafe {
+            self.swapchain_fn
+                .destroy_swapchain(self.swapchain_ptr, None)
+        };
+        self.swapchain_ptr = swapchain;
+        self.extent = extent;
+        self.surface_format = surface_format;
+        self.image_usage = image_usage;
+        self.composite_alpha = composite_alpha;
+        self.images = self.create_images()?;
+        Ok(())
+    }
+
+    /// Same as [`Swapchain::recreate`], then has each of the observers rebuild what they
+    /// built from the old images, in order. Stops at the first observer that errors.
+    pub fn recreate_and_notify(
+        &mut self,
+        observers: &mut [&mut dyn SwapchainObserver],
+    ) -> Result<()> {
+        self.recreate()?;
+        for observer in observers.iter_mut() {
+            observer.on_swapchain_recreated(self)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_swapchain_images(&self) -> Result<Vec<ash::vk::Image>> {
         let images = unsafe { self.swapchain_fn.get_swapchain_images(self.swapchain_ptr)? };
         Ok(images)
     }
 
+    /// Acquires the next image to draw to, returning its index. Errors with
+    /// [`crate::RustyGamesError::SwapchainOutOfDate`] if the swapchain has to be recreated
+    /// first. A suboptimal swapchain still hands out the image, since the semaphore is
+    /// signaled then; presenting it reports the swapchain as out of date instead.
     pub fn acquire_next_image_index(&self, signal_semaphore: &Semaphore) -> Result<u32> {
-        let (index, _) = unsafe {
-            self.swapchain_fn.acquire_next_image(
-                self.swapchain_ptr,
-                u64::MAX,
-                *signal_semaphore,
-                Fence::null(),
-            )?
+        let (index, _suboptimal) = unsafe {
+            self.swapchain_fn
+                .acquire_next_image(
+                    self.swapchain_ptr,
+                    u64::MAX,
+                    *signal_semaphore,
+                    Fence::null(),
+                )
+                .map_out_of_date()?
         };
         Ok(index)
     }
@@ -110,23 +266,103 @@ impl Swapchain {
         &self.swapchain_ptr
     }
 
+    /// Returns the size of the swapchain's images. This is the live size: it's replaced
+    /// by [`Swapchain::recreate`], so read it again after a resize rather than caching it.
     pub fn get_extent(&self) -> &Extent2D {
         &self.extent
     }
 
+    /// Returns the width over the height of the images, the aspect ratio a projection
+    /// should use to not stretch the scene. Like the extent, it changes on recreate, so
+    /// read it each frame. Returns 1 while the extent is empty, e.g. when minimized.
+    pub fn get_aspect_ratio(&self) -> f32 {
+        if self.extent.width == 0 || self.extent.height == 0 {
+            return 1.0;
+        }
+        self.extent.width as f32 / self.extent.height as f32
+    }
+
     pub fn get_surface_format(&self) -> &SurfaceFormatKHR {
         &self.surface_format
     }
 
-    pub fn create_image_views(&self, logical_device: &Rc<LogicalDevice>) -> Result<Vec<ImageView>> {
-        let images = unsafe { self.swapchain_fn.get_swapchain_images(self.swapchain_ptr)? };
-        let image_views = create_image_views(logical_device, self.surface_format, images)?;
-        Ok(image_views)
+    /// Returns what the images can be used for: always as color attachments, and as blit
+    /// destinations where the surface allows it
+    pub fn get_image_usage(&self) -> ImageUsageFlags {
+        self.image_usage
+    }
+
+    /// Returns how the images' alpha is composited, which is only see-thru if the
+    /// preference the swapchain was created with is supported by the surface
+    pub fn get_composite_alpha(&self) -> CompositeAlphaFlagsKHR {
+        self.composite_alpha
+    }
+
+    /// Whether the images use an `*_SRGB` format, which encodes the linear colors written
+    /// to them. If not, colors pushed via uniforms should be converted with
+    /// [`crate::linear_to_srgb`] first to display the same.
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self.surface_format.format,
+            Format::R8G8B8A8_SRGB
+                | Format::B8G8R8A8_SRGB
+                | Format::R8G8B8_SRGB
+                | Format::B8G8R8_SRGB
+                | Format::A8B8G8R8_SRGB_PACK32
+        )
+    }
+
+    pub fn get_window(&self) -> &Shared<Window> {
+        &self.window
+    }
+
+    pub fn get_surface(&self) -> &Shared<Surface> {
+        &self.surface
+    }
+
+    /// Returns the swapchain's images, each with a view onto it, in the order the image
+    /// indicies returned by [`Swapchain::acquire_next_image_index`] refer to them
+    pub fn get_images(&self) -> &[Shared<Image>] {
+        &self.images
+    }
+
+    /// Returns the view onto each of the swapchain's images
+    pub fn get_image_views(&self) -> Vec<&ImageView> {
+        self.images
+            .iter()
+            .filter_map(|image| image.get_view())
+            .collect()
+    }
+
+    /// Wraps the swapchain's images, with a view onto each
+    fn create_images(&self) -> Result<Vec<Shared<Image>>> {
+        let images = self
+            .get_swapchain_images()?
+            .into_iter()
+            .map(|image| {
+                Image::from_swapchain_image(
+                    &self.logical_device,
+                    image,
+                    self.surface_format.format,
+                    self.extent,
+                )
+                .map(Shared::new)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (idx, image) in images.iter().enumerate() {
+            if let Some(image_view) = image.get_view() {
+                self.logical_device
+                    .set_object_name(**image_view, &format!("swapchain image view {idx}"))?;
+            }
+        }
+        Ok(images)
     }
 }
 
 impl Drop for Swapchain {
     fn drop(&mut self) {
+        // the views have to go before the images they view
+        self.images.clear();
         unsafe {
             self.swapchain_fn
                 .destroy_swapchain(self.swapchain_ptr, None)
@@ -142,15 +378,126 @@ impl Deref for Swapchain {
     }
 }
 
-/// Creates Image views from the provided images
-fn create_image_views(
-    logical_device: &Rc<LogicalDevice>,
-    surface_format: SurfaceFormatKHR,
-    images: Vec<Image>,
-) -> Result<Vec<ImageView>> {
-    let image_views = images
-        .into_iter()
-        .map(|image| ImageView::new(logical_device, surface_format, image))
-        .collect::<Result<Vec<_>, _>>()?;
-    Ok(image_views)
+/// Creates the swapchain for the surface, sized to the window, retiring `old_swapchain` if
+/// it's not null. Returns the swapchain along with the extent, surface format, image usage
+/// and composite alpha chosen for it.
+///
+/// The surface capabilities are re-queried first, since the current extent changes as the
+/// window is resized or moved between monitors.
+fn create_swapchain(
+    swapchain_device: &swapchain::Device,
+    window: &Window,
+    logical_device: &LogicalDevice,
+    surface: &Surface,
+    swap_chain_support: &mut SwapChainSupportDetails,
+    preferences: SwapchainPreferences,
+    old_swapchain: SwapchainKHR,
+) -> Result<(
+    SwapchainKHR,
+    Extent2D,
+    SurfaceFormatKHR,
+    ImageUsageFlags,
+    CompositeAlphaFlagsKHR,
+)> {
+    swap_chain_support.capabilities =
+        surface.get_physical_device_surface_capabilities(&logical_device.get_physical_device())?;
+
+    let (image_sharing_mode, queue_family_indicies) = get_image_sharing(
+        logical_device.graphics_queue().get_family_index(),
+        logical_device.present_queue().get_family_index(),
+    );
+
+    let surface_format = swap_chain_support.choose_swap_surface_format()?;
+    let present_mode = swap_chain_support.choose_swap_present_mode()?;
+    let extent = swap_chain_support.choose_swap_extent(window, preferences.extent_clamping);
+    let image_count = swap_chain_support.choose_image_count(preferences.image_count);
+    let composite_alpha = swap_chain_support.choose_composite_alpha(preferences.composite_alpha);
+    // blitting to the images lets the scene be rendered at a lower resolution
+    let image_usage = ImageUsageFlags::COLOR_ATTACHMENT
+        | (swap_chain_support.capabilities.supported_usage_flags & ImageUsageFlags::TRANSFER_DST);
+
+    let swap_chain_creation_info = SwapchainCreateInfoKHR::default()
+        .surface(**surface)
+        .min_image_count(image_count)
+        .image_format(surface_format.format)
+        .image_color_space(surface_format.color_space)
+        .image_extent(extent)
+        .present_mode(present_mode)
+        // always 1 unless doing sterioscopic 3D
+        .image_array_layers(1)
+        .image_usage(image_usage)
+        // no transform
+        .pre_transform(swap_chain_support.capabilities.current_transform)
+        .composite_alpha(composite_alpha)
+        // enable clipping, to discard pixels that aren't visible
+        .clipped(true)
+        // lets the driver reuse the old swapchain's resources when recreating
+        .old_swapchain(old_swapchain)
+        .image_sharing_mode(image_sharing_mode)
+        .queue_family_indices(&queue_family_indicies);
+
+    let swapchain = unsafe { swapchain_device.create_swapchain(&swap_chain_creation_info, None) }?;
+    logical_device.set_object_name(swapchain, "swapchain")?;
+
+    // the driver may create more images than the minimum asked for. failing to count
+    // them isn't worth failing the swapchain over, the images are queried again anyway
+    let created_image_count = unsafe { swapchain_device.get_swapchain_images(swapchain) }
+        .map(|images| images.len())
+        .ok();
+    info!(
+        format = ?surface_format.format,
+        color_space = ?surface_format.color_space,
+        ?present_mode,
+        width = extent.width,
+        height = extent.height,
+        image_count = ?created_image_count,
+        min_image_count = image_count,
+        requested_image_count = ?preferences.image_count,
+        ?composite_alpha,
+        "Created the swapchain"
+    );
+
+    Ok((
+        swapchain,
+        extent,
+        *surface_format,
+        image_usage,
+        composite_alpha,
+    ))
+}
+
+/// Returns how the swapchain images are shared between the queues that draw to them and
+/// present them. If both are from the same family, the images are owned by that family
+/// exclusively and no indicies are needed. Otherwise they're shared concurrently between
+/// both families, so ownership doesn't have to be transferred explicitly:
+///
+/// - `(0, 0)` is `(EXCLUSIVE, [])`
+/// - `(0, 2)` is `(CONCURRENT, [0, 2])`
+fn get_image_sharing(graphics_family: u32, present_family: u32) -> (SharingMode, Vec<u32>) {
+    if graphics_family == present_family {
+        (SharingMode::EXCLUSIVE, vec![])
+    } else {
+        (
+            SharingMode::CONCURRENT,
+            vec![graphics_family, present_family],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_exclusive_to_a_shared_family() {
+        assert_eq!(get_image_sharing(0, 0), (SharingMode::EXCLUSIVE, vec![]));
+    }
+
+    #[test]
+    fn images_are_concurrent_between_different_families() {
+        assert_eq!(
+            get_image_sharing(0, 2),
+            (SharingMode::CONCURRENT, vec![0, 2])
+        );
+    }
 }
@@ -0,0 +1,222 @@
+use ash::vk::{Extent2D, VertexInputAttributeDescription, VertexInputBindingDescription};
+
+use crate::{
+    vertex::{attribute_description_for, binding_description_for},
+    Vertex,
+};
+
+/// Describes a bitmap font atlas: a texture holding a grid of equally sized glyphs for
+/// consecutive characters, laid out left to right, top to bottom
+#[derive(Clone, Copy, Debug)]
+pub struct BitmapFont {
+    /// Number of glyphs in each row of the atlas
+    pub columns: u32,
+    /// Number of rows of glyphs in the atlas
+    pub rows: u32,
+    /// The character of the glyph in the top left corner
+    pub first_char: char,
+    /// Size of each glyph in pixels, when drawn at a scale of 1.0
+    pub glyph_width: f32,
+    pub glyph_height: f32,
+}
+
+impl BitmapFont {
+    /// A 16x6 atlas of the printable ASCII characters, from space to `~`
+    pub fn ascii(glyph_width: f32, glyph_height: f32) -> Self {
+        Self {
+            columns: 16,
+            rows: 6,
+            first_char: ' ',
+            glyph_width,
+            glyph_height,
+        }
+    }
+
+    /// Returns the top left and bottom right texture coordinates of the character's
+    /// glyph, falling back to `?` for characters the atlas doesn't have
+    fn get_glyph_uvs(&self, c: char) -> ([f32; 2], [f32; 2]) {
+        let glyph_count = self.columns * self.rows;
+        let glyph_idx = (c as u32)
+            .checked_sub(self.first_char as u32)
+            .filter(|idx| *idx < glyph_count)
+            .or_else(|| ('?' as u32).checked_sub(self.first_char as u32))
+            .unwrap_or(0)
+            % glyph_count;
+        let column = (glyph_idx % self.columns) as f32;
+        let row = (glyph_idx / self.columns) as f32;
+        let glyph_u = 1.0 / self.columns as f32;
+        let glyph_v = 1.0 / self.rows as f32;
+        (
+            [column * glyph_u, row * glyph_v],
+            [(column + 1.0) * glyph_u, (row + 1.0) * glyph_v],
+        )
+    }
+}
+
+/// A corner of a glyph quad, in normalized device coordinates, with the texture
+/// coordinate in the font atlas to sample
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextVertex {
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+}
+
+impl Vertex for TextVertex {
+    fn binding_description() -> VertexInputBindingDescription {
+        binding_description_for::<Self>()
+    }
+
+    fn attribute_descriptions() -> Vec<VertexInputAttributeDescription> {
+        vec![
+            attribute_description_for::<[f32; 2]>(0, std::mem::offset_of!(TextVertex, pos)),
+            attribute_description_for::<[f32; 2]>(1, std::mem::offset_of!(TextVertex, uv)),
+        ]
+    }
+}
+
+/// Lays out strings as textured quads, one per glyph of a [`BitmapFont`], for HUDs and
+/// debug overlays. Text is positioned in pixels from the top left of the screen and
+/// projected orthographically, so the vertices can be drawn as a triangle list with an
+/// alpha blended pipeline sampling the font atlas.
+pub struct TextRenderer {
+    font: BitmapFont,
+    screen_extent: Extent2D,
+    vertices: Vec<TextVertex>,
+}
+
+impl TextRenderer {
+    pub fn new(font: BitmapFont, screen_extent: Extent2D) -> Self {
+        Self {
+            font,
+            screen_extent,
+            vertices: vec![],
+        }
+    }
+
+    /// Updates the size of the screen the text is projected onto, e.g. after a resize.
+    /// Only affects text drawn afterwards.
+    pub fn set_screen_extent(&mut self, screen_extent: Extent2D) {
+        self.screen_extent = screen_extent;
+    }
+
+    /// Adds the quads for `text`, with the top left of its first glyph at (`x`, `y`)
+    /// pixels and glyphs sized `scale` times the font's. Newlines start a new line below
+    /// the first.
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, scale: f32) {
+        let glyph_width = self.font.glyph_width * scale;
+        let glyph_height = self.font.glyph_height * scale;
+        let (mut cursor_x, mut cursor_y) = (x, y);
+        for c in text.chars() {
+            if c == '\n' {
+                cursor_x = x;
+                cursor_y += glyph_height;
+                continue;
+            }
+
+            let (uv_min, uv_max) = self.font.get_glyph_uvs(c);
+            let top_left = self.to_ndc(cursor_x, cursor_y);
+            let bottom_right = self.to_ndc(cursor_x + glyph_width, cursor_y + glyph_height);
+            let top_right = TextVertex {
+                pos: [bottom_right[0], top_left[1]],
+                uv: [uv_max[0], uv_min[1]],
+            };
+            let bottom_left = TextVertex {
+                pos: [top_left[0], bottom_right[1]],
+                uv: [uv_min[0], uv_max[1]],
+            };
+            let top_left = TextVertex {
+                pos: top_left,
+                uv: uv_min,
+            };
+            let bottom_right = TextVertex {
+                pos: bottom_right,
+                uv: uv_max,
+            };
+            // two clockwise triangles, matching the pipeline's front face
+            self.vertices.extend_from_slice(&[
+                top_left,
+                top_right,
+                bottom_right,
+                bottom_right,
+                bottom_left,
+                top_left,
+            ]);
+
+            cursor_x += glyph_width;
+        }
+    }
+
+    /// Returns the vertices of everything drawn since the last clear, to upload to the
+    /// vertex buffer
+    pub fn get_vertices(&self) -> &[TextVertex] {
+        &self.vertices
+    }
+
+    /// Removes all the text, typically at the start of every frame
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Projects a position in pixels onto normalized device coordinates, where Vulkan's
+    /// y axis points down like the screen's
+    fn to_ndc(&self, x: f32, y: f32) -> [f32; 2] {
+        [
+            x / self.screen_extent.width as f32 * 2.0 - 1.0,
+            y / self.screen_extent.height as f32 * 2.0 - 1.0,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_renderer() -> TextRenderer {
+        let screen_extent = Extent2D {
+            width: 128,
+            height: 128,
+        };
+        TextRenderer::new(BitmapFont::ascii(10.0, 20.0), screen_extent)
+    }
+
+    #[test]
+    fn empty_text_has_no_quads() {
+        let mut text_renderer = text_renderer();
+        text_renderer.draw_text("", 10.0, 10.0, 1.0);
+        assert!(text_renderer.get_vertices().is_empty());
+    }
+
+    #[test]
+    fn glyphs_are_placed_in_a_row() {
+        let mut text_renderer = text_renderer();
+        text_renderer.draw_text("ab", 0.0, 0.0, 1.0);
+        let vertices = text_renderer.get_vertices();
+        assert_eq!(vertices.len(), 12);
+        assert_eq!(vertices[0].pos, [-1.0, -1.0]);
+        assert_eq!(vertices[2].pos, [-0.843_75, -0.687_5]);
+        // the second glyph starts where the first ends
+        assert_eq!(vertices[6].pos, [-0.843_75, -1.0]);
+    }
+
+    #[test]
+    fn newlines_start_a_line_below() {
+        let mut text_renderer = text_renderer();
+        text_renderer.draw_text("a\nb", 64.0, 0.0, 2.0);
+        let vertices = text_renderer.get_vertices();
+        // the newline itself has no quad
+        assert_eq!(vertices.len(), 12);
+        // back at the starting x, a scaled glyph height down
+        assert_eq!(vertices[6].pos, [0.0, -0.375]);
+    }
+
+    #[test]
+    fn missing_glyphs_fall_back_to_question_marks() {
+        let font = BitmapFont::ascii(10.0, 20.0);
+        assert_eq!(font.get_glyph_uvs('é'), font.get_glyph_uvs('?'));
+        assert_eq!(
+            font.get_glyph_uvs(' '),
+            ([0.0, 0.0], [1.0 / 16.0, 1.0 / 6.0])
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{ensure, Result};
+use ash::vk::{
+    DescriptorBindingFlags, DescriptorImageInfo, DescriptorPoolSize, DescriptorSet,
+    DescriptorSetLayoutBinding, DescriptorSetLayoutBindingFlagsCreateInfo,
+    DescriptorSetLayoutCreateInfo, DescriptorType, ImageLayout, ShaderStageFlags,
+    WriteDescriptorSet,
+};
+
+use crate::{DescriptorPool, DescriptorSetLayout, ImageView, LogicalDevice, Sampler, Shared};
+
+/// A descriptor set holding an array of up to `capacity` textures in a single binding,
+/// which shaders index into (`sampler2D textures[]`), for instance with a per-material
+/// texture index. Slots that were never set are left unbound, so shaders must only
+/// index textures that have been set. Requires
+/// [`LogicalDevice::is_descriptor_indexing_enabled`].
+///
+/// Setting a texture writes the descriptor set immediately, so only do it while no
+/// frame using the set is in flight.
+pub struct TextureArray {
+    logical_device: Shared<LogicalDevice>,
+    layout: DescriptorSetLayout,
+    descriptor_set: DescriptorSet,
+    binding: u32,
+    capacity: u32,
+    // the set is freed with the pool, which has to outlive it
+    _descriptor_pool: DescriptorPool,
+}
+
+impl TextureArray {
+    /// Creates the layout and set for an array of `capacity` combined image samplers at
+    /// `binding`, read by the given stages
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        binding: u32,
+        capacity: u32,
+        stages: ShaderStageFlags,
+    ) -> Result<Self> {
+        ensure!(
+            logical_device.is_descriptor_indexing_enabled(),
+            "Descriptor indexing isn't enabled on the device!"
+        );
+        ensure!(
+            capacity > 0,
+            "Texture arrays must hold at least one texture!"
+        );
+
+        let bindings = [DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)
+            .stage_flags(stages)];
+        // partially bound lets slots stay empty, and the variable count lets the set be
+        // allocated with only as many slots as needed
+        let binding_flags = [DescriptorBindingFlags::PARTIALLY_BOUND
+            | DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT];
+        let mut binding_flags_create_info =
+            DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(&binding_flags);
+        let layout_create_info = DescriptorSetLayoutCreateInfo::default()
+            .bindings(&bindings)
+            .push_next(&mut binding_flags_create_info);
+        let layout = DescriptorSetLayout::from_create_info(logical_device, &layout_create_info)?;
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)];
+        let descriptor_pool = DescriptorPool::new(logical_device, 1, &pool_sizes)?;
+        let descriptor_set = descriptor_pool.allocate_variable(*layout, capacity)?;
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            layout,
+            descriptor_set,
+            binding,
+            capacity,
+            _descriptor_pool: descriptor_pool,
+        })
+    }
+
+    /// Puts the texture in slot `index`, with the image in `SHADER_READ_ONLY_OPTIMAL`
+    pub fn set_texture(&self, index: u32, image_view: &ImageView, sampler: &Sampler) -> Result<()> {
+        ensure!(
+            index < self.capacity,
+            "Texture index {index} is out of bounds for an array of {} textures!",
+            self.capacity
+        );
+        let image_infos = [DescriptorImageInfo::default()
+            .image_view(**image_view)
+            .sampler(**sampler)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+        let descriptor_writes = [WriteDescriptorSet::default()
+            .dst_set(self.descriptor_set)
+            .dst_binding(self.binding)
+            .dst_array_element(index)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos)];
+        unsafe {
+            self.logical_device
+                .update_descriptor_sets(&descriptor_writes, &[])
+        };
+        Ok(())
+    }
+
+    /// Returns the layout, for building pipeline layouts that use the array
+    pub fn get_layout(&self) -> &DescriptorSetLayout {
+        &self.layout
+    }
+
+    pub fn get_descriptor_set(&self) -> DescriptorSet {
+        self.descriptor_set
+    }
+
+    pub fn get_capacity(&self) -> u32 {
+        self.capacity
+    }
+}
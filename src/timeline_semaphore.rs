@@ -0,0 +1,146 @@
+use std::{
+    ops::Deref,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::Result;
+use ash::vk::{
+    Semaphore, SemaphoreCreateInfo, SemaphoreType, SemaphoreTypeCreateInfo, SemaphoreWaitInfo,
+};
+
+use crate::{error::VkResultExt, LogicalDevice, Shared};
+
+/// Returns the values the `submission`th submission (counting from 0) signals and
+/// waits for first. The wait value is the one signaled `frames_in_flight` submissions
+/// earlier, or 0 for the first ones, which the counter starts at.
+fn next_timeline_values(submission: u64, frames_in_flight: u64) -> (u64, u64) {
+    let signal_value = submission + 1;
+    (signal_value, signal_value.saturating_sub(frames_in_flight))
+}
+
+/// A semaphore holding a monotonically increasing counter, which the GPU
+/// increments when a submission signals it and the CPU can wait on. Requires
+/// the timelineSemaphore device feature.
+pub struct TimelineSemaphore {
+    logical_device: Shared<LogicalDevice>,
+    semaphore: Semaphore,
+    /// How many submissions have been handed a value to signal
+    submission_count: AtomicU64,
+}
+
+impl TimelineSemaphore {
+    pub fn new(logical_device: &Shared<LogicalDevice>) -> Result<Self> {
+        let mut semaphore_type_create_info = SemaphoreTypeCreateInfo::default()
+            .semaphore_type(SemaphoreType::TIMELINE)
+            .initial_value(0);
+        let semaphore_create_info =
+            SemaphoreCreateInfo::default().push_next(&mut semaphore_type_create_info);
+        let semaphore = unsafe { logical_device.create_semaphore(&semaphore_create_info, None)? };
+
+        Ok(Self {
+            logical_device: Shared::clone(logical_device),
+            semaphore,
+            submission_count: AtomicU64::new(0),
+        })
+    }
+
+    /// Returns the value the next submission should signal, one past the last value
+    /// handed out
+    pub fn next_signal_value(&self) -> u64 {
+        let submission = self.submission_count.fetch_add(1, Ordering::Relaxed);
+        next_timeline_values(submission, 0).0
+    }
+
+    /// Returns the value to wait for before the next submission, the one signaled
+    /// `frames_in_flight` submissions before it
+    pub fn get_wait_value(&self, frames_in_flight: u64) -> u64 {
+        let submission = self.submission_count.load(Ordering::Relaxed);
+        next_timeline_values(submission, frames_in_flight).1
+    }
+
+    /// Returns the current value of the counter on the GPU
+    pub fn get_value(&self) -> Result<u64> {
+        let value = unsafe {
+            self.logical_device
+                .get_semaphore_counter_value(self.semaphore)?
+        };
+        Ok(value)
+    }
+
+    /// Blocks until the counter reaches at least `value`, or the timeout (in nanoseconds)
+    /// elapses
+    pub fn wait(&self, value: u64, timeout: u64) -> Result<()> {
+        let semaphores = [self.semaphore];
+        let values = [value];
+        let semaphore_wait_info = SemaphoreWaitInfo::default()
+            .semaphores(&semaphores)
+            .values(&values);
+        unsafe {
+            self.logical_device
+                .wait_semaphores(&semaphore_wait_info, timeout)
+                .map_device_lost()?
+        };
+        Ok(())
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    fn drop(&mut self) {
+        unsafe { self.logical_device.destroy_semaphore(self.semaphore, None) }
+    }
+}
+
+impl Deref for TimelineSemaphore {
+    type Target = Semaphore;
+
+    fn deref(&self) -> &Self::Target {
+        &self.semaphore
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_strictly_increase() {
+        for frames_in_flight in 1..4 {
+            let mut last = next_timeline_values(0, frames_in_flight);
+            for submission in 1..16 {
+                let values = next_timeline_values(submission, frames_in_flight);
+                assert!(values.0 > last.0);
+                assert!(values.1 >= last.1);
+                if submission >= frames_in_flight {
+                    assert!(values.1 > last.1);
+                }
+                last = values;
+            }
+        }
+    }
+
+    #[test]
+    fn wait_value_trails_by_frames_in_flight() {
+        for frames_in_flight in 1..4 {
+            for submission in 0..16 {
+                let (signal_value, wait_value) = next_timeline_values(submission, frames_in_flight);
+                assert!(wait_value < signal_value);
+                if signal_value >= frames_in_flight {
+                    assert_eq!(signal_value - wait_value, frames_in_flight);
+                } else {
+                    // nothing was signaled that long ago, so there's nothing to wait for
+                    assert_eq!(wait_value, 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn waits_on_the_same_frames_last_submission() {
+        // with frames taking turns, submission n reuses the resources of submission
+        // n - frames_in_flight, which signaled the wait value
+        let frames_in_flight = 2;
+        let (first_signal_value, _) = next_timeline_values(0, frames_in_flight);
+        let (_, wait_value) = next_timeline_values(frames_in_flight, frames_in_flight);
+        assert_eq!(wait_value, first_signal_value);
+    }
+}
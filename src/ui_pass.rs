@@ -0,0 +1,23 @@
+use anyhow::Result;
+use ash::vk::{CommandBuffer, Extent2D};
+
+/// An integration point for immediate mode GUI libraries like egui, drawing an overlay on
+/// top of the scene. Passed to [`crate::Renderer::render_frame_with_ui`], it's called
+/// while recording each frame, inside the main render pass after the scene has been drawn,
+/// so it draws into the same swapchain framebuffer.
+///
+/// Pipelines recording into the command buffer must be created for the render pass
+/// returned by `Renderer::get_render_pass`, e.g. when creating an `egui-ash-renderer`
+/// `Renderer`. With the `dynamic_rendering` feature, they're created with dynamic
+/// rendering for the swapchain's format instead.
+pub trait UiPass {
+    /// Records the UI's draws into the command buffer, which is mid render pass.
+    /// `frame_idx` is the index of the frame within the frames in flight, for keeping
+    /// per frame resources like vertex buffers apart.
+    fn record(
+        &mut self,
+        command_buffer: CommandBuffer,
+        extent: Extent2D,
+        frame_idx: usize,
+    ) -> Result<()>;
+}
@@ -0,0 +1,220 @@
+use anyhow::{bail, ensure, Result};
+use ash::vk::{
+    BufferUsageFlags, DescriptorBufferInfo, DescriptorPoolSize, DescriptorSet, DescriptorType,
+    DeviceSize, MemoryPropertyFlags, WriteDescriptorSet,
+};
+
+use crate::{Buffer, DescriptorPool, DescriptorSetLayout, LogicalDevice, Shared, FRAMES_IN_FLIGHT};
+
+/// One large uniform buffer that per-object uniforms are sub-allocated from, bound
+/// per draw with a dynamic offset instead of a descriptor set per object. The buffer is
+/// split into a segment per frame in flight; each frame starts over at the beginning of
+/// its own segment, so only do that once the frame's previous draw has completed (after
+/// its `wait_until_idle`).
+pub struct UniformRing {
+    buffer: Buffer,
+    descriptor_set: DescriptorSet,
+    /// Bytes in each frame's segment
+    segment_size: DeviceSize,
+    /// The largest uniform block a single push can hold, the range of the binding
+    max_block_size: DeviceSize,
+    /// The alignment dynamic offsets need, `minUniformBufferOffsetAlignment`
+    alignment: DeviceSize,
+    /// Where the current frame's segment starts
+    segment_start: DeviceSize,
+    /// Offset of the next push within the current segment
+    cursor: DeviceSize,
+    // the set is freed with the pool, which has to outlive it
+    _descriptor_pool: DescriptorPool,
+}
+
+impl UniformRing {
+    /// Creates a ring with `segment_size` bytes for each frame in flight, and a descriptor
+    /// set with `layout` (see [`DescriptorSetLayout::dynamic_uniform_buffer`]) pointing
+    /// `binding` at it. Each push can be up to `max_block_size` bytes.
+    pub fn new(
+        logical_device: &Shared<LogicalDevice>,
+        layout: &DescriptorSetLayout,
+        binding: u32,
+        segment_size: DeviceSize,
+        max_block_size: DeviceSize,
+    ) -> Result<Self> {
+        ensure!(
+            max_block_size > 0 && max_block_size <= segment_size,
+            "Uniform blocks must be between 1 and {segment_size} bytes, not {max_block_size}!"
+        );
+        let alignment = logical_device
+            .get_limits()
+            .min_uniform_buffer_offset_alignment
+            .max(1);
+        // keep every segment aligned, so offsets within them are too
+        let segment_size = align_up(segment_size, alignment);
+
+        let buffer = Buffer::new(
+            logical_device,
+            segment_size * FRAMES_IN_FLIGHT as DeviceSize,
+            BufferUsageFlags::UNIFORM_BUFFER,
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        logical_device.set_object_name(*buffer, "uniform ring buffer")?;
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .descriptor_count(1)];
+        let descriptor_pool = DescriptorPool::new(logical_device, 1, &pool_sizes)?;
+        let descriptor_set = descriptor_pool.allocate(&[**layout])?[0];
+        let buffer_infos = [DescriptorBufferInfo::default()
+            .buffer(*buffer)
+            .range(max_block_size)];
+        let descriptor_writes = [WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(binding)
+            .descriptor_type(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .buffer_info(&buffer_infos)];
+        unsafe { logical_device.update_descriptor_sets(&descriptor_writes, &[]) };
+
+        Ok(Self {
+            buffer,
+            descriptor_set,
+            segment_size,
+            max_block_size,
+            alignment,
+            segment_start: 0,
+            cursor: 0,
+            _descriptor_pool: descriptor_pool,
+        })
+    }
+
+    /// Starts pushing into the frame's segment from the beginning, discarding what the
+    /// frame pushed last time. The frame's previous draw must have completed.
+    pub fn begin_frame(&mut self, frame_idx: usize) -> Result<()> {
+        ensure!(
+            frame_idx < FRAMES_IN_FLIGHT as usize,
+            "There are only {FRAMES_IN_FLIGHT} frames in flight, not {}!",
+            frame_idx + 1
+        );
+        self.segment_start = frame_idx as DeviceSize * self.segment_size;
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// Copies a uniform block into the current frame's segment, returning the dynamic
+    /// offset to bind the descriptor set with for the draw reading it. Errors if the
+    /// block is too big, or the segment is full.
+    pub fn push(&mut self, data: &[u8]) -> Result<u32> {
+        let len = data.len() as DeviceSize;
+        ensure!(
+            len <= self.max_block_size,
+            "The {len} byte uniform block is bigger than the {} bytes bound!",
+            self.max_block_size
+        );
+        let Some((offset, cursor)) = place_block(
+            self.cursor,
+            len,
+            self.max_block_size,
+            self.segment_size,
+            self.alignment,
+        ) else {
+            bail!(
+                "The frame's {} byte uniform segment is full!",
+                self.segment_size
+            );
+        };
+        let offset = self.segment_start + offset;
+        self.buffer.write(offset, data)?;
+        self.cursor = cursor;
+        Ok(offset as u32)
+    }
+
+    /// Returns the descriptor set to bind, with the offset of the block the draw reads
+    pub fn get_descriptor_set(&self) -> DescriptorSet {
+        self.descriptor_set
+    }
+
+    pub fn get_buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+}
+
+/// Returns where in a segment a `len` byte block pushed at `cursor` goes, and the cursor
+/// for the next push, which stays aligned. `None` if the segment is full: the binding
+/// always covers `max_block_size` bytes, which have to fit in it too.
+fn place_block(
+    cursor: DeviceSize,
+    len: DeviceSize,
+    max_block_size: DeviceSize,
+    segment_size: DeviceSize,
+    alignment: DeviceSize,
+) -> Option<(DeviceSize, DeviceSize)> {
+    if cursor + max_block_size > segment_size {
+        return None;
+    }
+    Some((cursor, align_up(cursor + len, alignment)))
+}
+
+/// Rounds `value` up to a multiple of `alignment`, which must be a power of two
+fn align_up(value: DeviceSize, alignment: DeviceSize) -> DeviceSize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pushes `len` byte blocks into a segment until it's full, returning their offsets
+    fn fill(
+        len: DeviceSize,
+        max_block_size: DeviceSize,
+        segment_size: DeviceSize,
+        alignment: DeviceSize,
+    ) -> Vec<DeviceSize> {
+        let mut offsets = vec![];
+        let mut cursor = 0;
+        while let Some((offset, next_cursor)) =
+            place_block(cursor, len, max_block_size, segment_size, alignment)
+        {
+            offsets.push(offset);
+            cursor = next_cursor;
+        }
+        offsets
+    }
+
+    #[test]
+    fn offsets_are_aligned() {
+        for alignment in [1, 64, 256] {
+            for len in [1, 12, 100, 257] {
+                let offsets = fill(len, 512, 4096, alignment);
+                assert!(!offsets.is_empty());
+                for pair in offsets.windows(2) {
+                    assert_eq!(pair[1] % alignment, 0, "{len} bytes at {alignment}");
+                    assert_eq!(pair[1] - pair[0], align_up(len, alignment));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unaligned_blocks_are_packed() {
+        assert_eq!(fill(12, 12, 48, 1), [0, 12, 24, 36]);
+        assert_eq!(fill(12, 12, 48, 4), [0, 12, 24, 36]);
+        assert_eq!(fill(12, 12, 64, 16), [0, 16, 32, 48]);
+    }
+
+    #[test]
+    fn full_segments_start_over() {
+        let offsets = fill(100, 100, 1024, 256);
+        assert_eq!(offsets, [0, 256, 512, 768]);
+        // the last push still has room for the whole binding
+        assert!(offsets.last().unwrap() + 100 <= 1024);
+        assert_eq!(place_block(1024, 100, 100, 1024, 256), None);
+        // beginning the frame again resets the cursor to the segment's start
+        assert_eq!(place_block(0, 100, 100, 1024, 256), Some((0, 256)));
+    }
+
+    #[test]
+    fn binding_range_has_to_fit() {
+        // a small block near the end doesn't fit if the bound range doesn't
+        assert_eq!(place_block(960, 16, 128, 1024, 64), None);
+        assert_eq!(place_block(896, 16, 128, 1024, 64), Some((896, 960)));
+    }
+}
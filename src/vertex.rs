@@ -0,0 +1,97 @@
+use ash::vk::{
+    Format, VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate,
+};
+
+/// Describes the layout of a vertex struct, so the graphics pipeline knows how to read
+/// vertices out of a vertex buffer. Usually derived with `#[derive(Vertex)]`, marking
+/// each field with the shader location it's read from:
+///
+/// ```ignore
+/// #[derive(Vertex)]
+/// struct ColoredVertex {
+///     #[location(0)]
+///     pos: [f32; 2],
+///     #[location(1)]
+///     color: [f32; 3],
+/// }
+/// ```
+///
+/// `()` describes having no vertex input at all, for shaders that generate their own
+/// vertices.
+pub trait Vertex {
+    /// How vertices are laid out in the buffer bound to binding 0
+    fn binding_description() -> VertexInputBindingDescription;
+
+    /// The format and offset of each field, and the shader location it's read from
+    fn attribute_descriptions() -> Vec<VertexInputAttributeDescription>;
+}
+
+impl Vertex for () {
+    fn binding_description() -> VertexInputBindingDescription {
+        VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(0)
+            .input_rate(VertexInputRate::VERTEX)
+    }
+
+    fn attribute_descriptions() -> Vec<VertexInputAttributeDescription> {
+        vec![]
+    }
+}
+
+/// Types that can be used as a field of a derived [`Vertex`], mapping them to the
+/// format the shader reads them as
+pub trait VertexAttribute {
+    const FORMAT: Format;
+}
+
+macro_rules! impl_vertex_attribute {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl VertexAttribute for $ty {
+                const FORMAT: Format = Format::$format;
+            }
+        )*
+    };
+}
+
+impl_vertex_attribute! {
+    f32 => R32_SFLOAT,
+    [f32; 1] => R32_SFLOAT,
+    [f32; 2] => R32G32_SFLOAT,
+    [f32; 3] => R32G32B32_SFLOAT,
+    [f32; 4] => R32G32B32A32_SFLOAT,
+    u32 => R32_UINT,
+    [u32; 1] => R32_UINT,
+    [u32; 2] => R32G32_UINT,
+    [u32; 3] => R32G32B32_UINT,
+    [u32; 4] => R32G32B32A32_UINT,
+    i32 => R32_SINT,
+    [i32; 1] => R32_SINT,
+    [i32; 2] => R32G32_SINT,
+    [i32; 3] => R32G32B32_SINT,
+    [i32; 4] => R32G32B32A32_SINT,
+    [u8; 4] => R8G8B8A8_UNORM,
+}
+
+/// Builds the binding description for a vertex type, used by `#[derive(Vertex)]`
+#[doc(hidden)]
+pub fn binding_description_for<V>() -> VertexInputBindingDescription {
+    VertexInputBindingDescription::default()
+        .binding(0)
+        .stride(std::mem::size_of::<V>() as u32)
+        .input_rate(VertexInputRate::VERTEX)
+}
+
+/// Builds the attribute description for a single field, used by `#[derive(Vertex)]`
+#[doc(hidden)]
+pub fn attribute_description_for<A: VertexAttribute>(
+    location: u32,
+    offset: usize,
+) -> VertexInputAttributeDescription {
+    VertexInputAttributeDescription::default()
+        .binding(0)
+        .location(location)
+        .format(A::FORMAT)
+        .offset(offset as u32)
+}
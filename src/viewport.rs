@@ -0,0 +1,228 @@
+use anyhow::{ensure, Result};
+use ash::vk::{CommandBuffer, Extent2D, Offset2D, Rect2D, Viewport};
+
+use crate::LogicalDevice;
+
+/// Viewport helpers, implemented for [`Viewport`] so they read as constructors
+pub trait ViewportExt {
+    /// Returns the largest viewport with the target aspect ratio (width over height) that
+    /// fits in the extent, centered with bars along the sides or top and bottom, so 2D
+    /// content isn't stretched to the window's shape. Set on pipelines with a dynamic
+    /// viewport via `cmd_set_viewport`, each frame or after resizing.
+    fn letterboxed(extent: Extent2D, target_aspect: f32) -> Self;
+}
+
+impl ViewportExt for Viewport {
+    fn letterboxed(extent: Extent2D, target_aspect: f32) -> Self {
+        let (extent_width, extent_height) = (extent.width as f32, extent.height as f32);
+        let (width, height) = if extent_width / extent_height > target_aspect {
+            // wider than the target, so bars on the sides
+            (extent_height * target_aspect, extent_height)
+        } else {
+            // taller than the target, so bars on the top and bottom
+            (extent_width, extent_width / target_aspect)
+        };
+        Viewport::default()
+            .x((extent_width - width) / 2.0)
+            .y((extent_height - height) / 2.0)
+            .width(width)
+            .height(height)
+            .min_depth(0.0)
+            .max_depth(1.0)
+    }
+}
+
+/// How a [`SplitScreen`] divides the image between its views
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplitLayout {
+    /// Side by side, each view as tall as the image
+    #[default]
+    Columns,
+    /// Stacked top to bottom, each view as wide as the image
+    Rows,
+    /// As square a grid as fits the views, filled row by row, e.g. 2x2 for 4 players
+    Grid,
+}
+
+/// Divides the image being rendered to into regions, for local multiplayer where each
+/// player sees the scene from their own camera. The scene is recorded once per view
+/// within the same render pass, with the viewport and scissor set to the view's region,
+/// so the pipeline needs a dynamic viewport and scissor (as [`crate::GraphicsPipeline`]s
+/// have).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitScreen {
+    view_count: u32,
+    layout: SplitLayout,
+}
+
+impl SplitScreen {
+    /// Errors unless there's at least one view
+    pub fn new(view_count: u32, layout: SplitLayout) -> Result<Self> {
+        ensure!(view_count > 0, "A split screen needs at least one view!");
+        Ok(Self { view_count, layout })
+    }
+
+    pub fn get_view_count(&self) -> u32 {
+        self.view_count
+    }
+
+    /// Returns the region of the extent each view covers, in order. Views split the
+    /// extent evenly, with any leftover pixels going to the last column and row so the
+    /// regions cover it all.
+    pub fn get_regions(&self, extent: Extent2D) -> Vec<Rect2D> {
+        let (columns, rows) = match self.layout {
+            SplitLayout::Columns => (self.view_count, 1),
+            SplitLayout::Rows => (1, self.view_count),
+            SplitLayout::Grid => {
+                let columns = (self.view_count as f32).sqrt().ceil() as u32;
+                (columns, self.view_count.div_ceil(columns))
+            }
+        };
+        let (width, height) = (extent.width / columns, extent.height / rows);
+        (0..self.view_count)
+            .map(|view_idx| {
+                let (column, row) = (view_idx % columns, view_idx / columns);
+                let region_width = if column == columns - 1 {
+                    extent.width - width * column
+                } else {
+                    width
+                };
+                let region_height = if row == rows - 1 {
+                    extent.height - height * row
+                } else {
+                    height
+                };
+                Rect2D::default()
+                    .offset(Offset2D {
+                        x: (width * column) as i32,
+                        y: (height * row) as i32,
+                    })
+                    .extent(Extent2D {
+                        width: region_width,
+                        height: region_height,
+                    })
+            })
+            .collect()
+    }
+
+    /// Records the scene once per view into the command buffer, which has to be inside a
+    /// render pass (or dynamic rendering) with a pipeline bound. For each view the
+    /// viewport and scissor are set to its region, then `record_view` is called with the
+    /// view's index and viewport, whose width over height is the aspect ratio the view's
+    /// camera should project with. The last view's viewport stays set afterwards.
+    pub fn record<F>(
+        &self,
+        logical_device: &LogicalDevice,
+        command_buffer: CommandBuffer,
+        extent: Extent2D,
+        mut record_view: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, &Viewport) -> Result<()>,
+    {
+        for (view_idx, region) in self.get_regions(extent).into_iter().enumerate() {
+            let viewport = Viewport::default()
+                .x(region.offset.x as f32)
+                .y(region.offset.y as f32)
+                .width(region.extent.width as f32)
+                .height(region.extent.height as f32)
+                .min_depth(0.0)
+                .max_depth(1.0);
+            unsafe {
+                logical_device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+                logical_device.cmd_set_scissor(command_buffer, 0, &[region]);
+            }
+            record_view(view_idx, &viewport)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> Extent2D {
+        Extent2D { width, height }
+    }
+
+    #[test]
+    fn matching_aspect_fills_the_extent() {
+        let viewport = Viewport::letterboxed(extent(1600, 900), 16.0 / 9.0);
+        assert_eq!(
+            (viewport.x, viewport.y, viewport.width, viewport.height),
+            (0.0, 0.0, 1600.0, 900.0)
+        );
+    }
+
+    #[test]
+    fn wide_extents_get_bars_on_the_sides() {
+        let viewport = Viewport::letterboxed(extent(4000, 100), 1.0);
+        assert_eq!(
+            (viewport.x, viewport.y, viewport.width, viewport.height),
+            (1950.0, 0.0, 100.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn tall_extents_get_bars_on_the_top_and_bottom() {
+        let viewport = Viewport::letterboxed(extent(100, 4000), 1.0);
+        assert_eq!(
+            (viewport.x, viewport.y, viewport.width, viewport.height),
+            (0.0, 1950.0, 100.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn extreme_target_aspects_stay_inside_the_extent() {
+        for target_aspect in [1e-4, 1e4] {
+            let viewport = Viewport::letterboxed(extent(1920, 1080), target_aspect);
+            assert!(viewport.x >= 0.0 && viewport.y >= 0.0);
+            assert!(viewport.x + viewport.width <= 1920.0);
+            assert!(viewport.y + viewport.height <= 1080.0);
+            let aspect = viewport.width / viewport.height;
+            assert!((aspect / target_aspect - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn split_screens_need_a_view() {
+        assert!(SplitScreen::new(0, SplitLayout::default()).is_err());
+    }
+
+    #[test]
+    fn leftover_pixels_go_to_the_last_view() {
+        let split_screen = SplitScreen::new(3, SplitLayout::Columns).unwrap();
+        let regions = split_screen.get_regions(extent(100, 50));
+        let widths = regions
+            .iter()
+            .map(|region| region.extent.width)
+            .collect::<Vec<_>>();
+        assert_eq!(widths, [33, 33, 34]);
+        assert_eq!(regions[2].offset.x, 66);
+        assert!(regions.iter().all(|region| region.extent.height == 50));
+    }
+
+    #[test]
+    fn grids_are_filled_row_by_row() {
+        let split_screen = SplitScreen::new(3, SplitLayout::Grid).unwrap();
+        let offsets = split_screen
+            .get_regions(extent(200, 100))
+            .iter()
+            .map(|region| (region.offset.x, region.offset.y))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [(0, 0), (100, 0), (0, 50)]);
+    }
+
+    #[test]
+    fn more_views_than_pixels_still_cover_the_extent() {
+        // the first views are left empty, with the last one covering the rest
+        let split_screen = SplitScreen::new(4, SplitLayout::Rows).unwrap();
+        let regions = split_screen.get_regions(extent(1920, 2));
+        let heights = regions
+            .iter()
+            .map(|region| region.extent.height)
+            .collect::<Vec<_>>();
+        assert_eq!(heights, [0, 0, 0, 2]);
+    }
+}
@@ -0,0 +1,249 @@
+use std::cmp::Reverse;
+
+use anyhow::{anyhow, Result};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoopWindowTarget,
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Icon, Window, WindowBuilder, WindowButtons},
+};
+
+/// How the window is presented on screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    /// A regular window with decorations
+    #[default]
+    Windowed,
+    /// Exclusive fullscreen, switching the monitor to the video mode closest to
+    /// the configured size
+    Fullscreen,
+    /// A borderless window covering the whole monitor, keeping the monitor's
+    /// current video mode
+    Borderless,
+}
+
+impl WindowMode {
+    /// Returns the winit fullscreen setting for this mode on the given monitor. `size` is
+    /// used to select the video mode for exclusive fullscreen.
+    pub fn get_fullscreen(
+        &self,
+        monitor: Option<MonitorHandle>,
+        size: PhysicalSize<u32>,
+    ) -> Option<Fullscreen> {
+        match self {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            // without a monitor there are no video modes to pick from, so fall back
+            // to borderless on whichever monitor the window ends up on
+            WindowMode::Fullscreen => match monitor {
+                Some(monitor) => select_video_mode(&monitor, size)
+                    .map(Fullscreen::Exclusive)
+                    .or(Some(Fullscreen::Borderless(Some(monitor)))),
+                None => Some(Fullscreen::Borderless(None)),
+            },
+        }
+    }
+}
+
+/// An image shown for the window by the OS, e.g. in its title bar and the taskbar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowIcon {
+    /// Pixels as 8 bit RGBA, row by row from the top left
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Settings used to create the window
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    /// Width of the window in pixels
+    pub width: u32,
+    /// Height of the window in pixels
+    pub height: u32,
+    pub title: String,
+    pub mode: WindowMode,
+    pub icon: Option<WindowIcon>,
+    /// Index of the monitor to open on, into the event loop's available monitors. The
+    /// primary monitor is used when unset.
+    pub monitor: Option<usize>,
+    /// Position of the window's top left corner relative to the monitor's, in pixels.
+    /// When unset the window is centered on the configured monitor, or placed by the OS
+    /// if no monitor was configured either.
+    pub position: Option<PhysicalPosition<i32>>,
+    /// Whether the user can resize the window. The sizes below only apply if so.
+    pub resizable: bool,
+    pub min_inner_size: Option<PhysicalSize<u32>>,
+    pub max_inner_size: Option<PhysicalSize<u32>>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            title: "Hello, Triangle".to_owned(),
+            mode: WindowMode::Windowed,
+            icon: None,
+            monitor: None,
+            position: None,
+            resizable: false,
+            min_inner_size: None,
+            max_inner_size: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    /// Creates the window that will interact with the OS to draw the results on the screen
+    pub fn build<T>(&self, event_loop: &EventLoopWindowTarget<T>) -> Result<Window> {
+        let size = self.get_size();
+        let monitor = match self.monitor {
+            Some(idx) => Some(
+                event_loop
+                    .available_monitors()
+                    .nth(idx)
+                    .ok_or_else(|| anyhow!("There's no monitor {idx} to open the window on!"))?,
+            ),
+            None => event_loop.primary_monitor(),
+        };
+        let icon = self
+            .icon
+            .as_ref()
+            .map(|icon| Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height))
+            .transpose()?;
+        let enabled_buttons = if self.resizable {
+            WindowButtons::all()
+        } else {
+            WindowButtons::CLOSE
+        };
+
+        let mut window_builder = WindowBuilder::new()
+            .with_inner_size(size)
+            .with_resizable(self.resizable)
+            .with_enabled_buttons(enabled_buttons)
+            .with_active(true)
+            .with_title(&self.title)
+            .with_window_icon(icon)
+            .with_fullscreen(self.mode.get_fullscreen(monitor.clone(), size));
+        if let Some(min_inner_size) = self.min_inner_size {
+            window_builder = window_builder.with_min_inner_size(min_inner_size);
+        }
+        if let Some(max_inner_size) = self.max_inner_size {
+            window_builder = window_builder.with_max_inner_size(max_inner_size);
+        }
+        if let Some(position) = self.get_position(monitor.as_ref()) {
+            window_builder = window_builder.with_position(position);
+        }
+        let window = window_builder.build(event_loop)?;
+        Ok(window)
+    }
+
+    /// Returns where the window's top left corner goes on the desktop, if anywhere in
+    /// particular
+    fn get_position(&self, monitor: Option<&MonitorHandle>) -> Option<PhysicalPosition<i32>> {
+        let origin = monitor.map(MonitorHandle::position).unwrap_or_default();
+        match (self.position, self.monitor, monitor) {
+            (Some(position), _, _) => Some(PhysicalPosition::new(
+                origin.x + position.x,
+                origin.y + position.y,
+            )),
+            (None, Some(_), Some(monitor)) => {
+                let monitor_size = monitor.size();
+                Some(PhysicalPosition::new(
+                    origin.x + (monitor_size.width as i32 - self.width as i32) / 2,
+                    origin.y + (monitor_size.height as i32 - self.height as i32) / 2,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width, self.height)
+    }
+}
+
+/// Switches the window into the given mode. The window will receive a resize event
+/// once the switch has happened, which should be used to recreate the swapchain at
+/// the new resolution.
+pub fn set_window_mode(window: &Window, mode: WindowMode, size: PhysicalSize<u32>) {
+    window.set_fullscreen(mode.get_fullscreen(window.current_monitor(), size));
+}
+
+/// A monitor, and the video modes exclusive fullscreen can switch it to
+#[derive(Clone, Debug)]
+pub struct MonitorVideoModes {
+    pub monitor: MonitorHandle,
+    /// Every resolution, bit depth and refresh rate the monitor supports, as listed by
+    /// the OS
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl MonitorVideoModes {
+    pub fn get_name(&self) -> Option<String> {
+        self.monitor.name()
+    }
+}
+
+/// Lists the video modes of every monitor the window could go fullscreen on, e.g. for a
+/// graphics settings menu to pick from
+pub fn available_video_modes(window: &Window) -> Vec<MonitorVideoModes> {
+    window
+        .available_monitors()
+        .map(|monitor| MonitorVideoModes {
+            video_modes: monitor.video_modes().collect(),
+            monitor,
+        })
+        .collect()
+}
+
+/// Switches the window into exclusive fullscreen at exactly the given video mode, on the
+/// video mode's monitor. If that monitor has been disconnected since the mode was listed,
+/// the window is switched to windowed instead. Returns the mode the window ends up in. As
+/// with [`set_window_mode`], the swapchain should be recreated on the resize event.
+pub fn set_video_mode(window: &Window, video_mode: &VideoMode) -> WindowMode {
+    if is_monitor_connected(window, &video_mode.monitor()) {
+        window.set_fullscreen(Some(Fullscreen::Exclusive(video_mode.clone())));
+        WindowMode::Fullscreen
+    } else {
+        window.set_fullscreen(None);
+        WindowMode::Windowed
+    }
+}
+
+/// Switches the window back to windowed if it's in exclusive fullscreen on a monitor that
+/// has been disconnected, returning whether it had to. Checked when resizing, since the
+/// OS moves (and so resizes) windows off monitors that go away.
+pub fn leave_disconnected_fullscreen(window: &Window) -> bool {
+    match window.fullscreen() {
+        Some(Fullscreen::Exclusive(video_mode))
+            if !is_monitor_connected(window, &video_mode.monitor()) =>
+        {
+            window.set_fullscreen(None);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn is_monitor_connected(window: &Window, monitor: &MonitorHandle) -> bool {
+    window
+        .available_monitors()
+        .any(|available_monitor| available_monitor == *monitor)
+}
+
+/// Picks the monitor's video mode to use for exclusive fullscreen: the one closest to
+/// the requested size, preferring the highest bit depth and refresh rate.
+fn select_video_mode(monitor: &MonitorHandle, size: PhysicalSize<u32>) -> Option<VideoMode> {
+    monitor.video_modes().min_by_key(|video_mode| {
+        let mode_size = video_mode.size();
+        let size_delta = mode_size.width.abs_diff(size.width) as u64
+            + mode_size.height.abs_diff(size.height) as u64;
+        (
+            size_delta,
+            Reverse(video_mode.bit_depth()),
+            Reverse(video_mode.refresh_rate_millihertz()),
+        )
+    })
+}
@@ -0,0 +1,131 @@
+//! Creates the Vulkan object graph and tears it down in different orders, failing if the
+//! validation layer reports any errors, like destroying an object that's still in use or
+//! was already destroyed. Each scenario counts errors with its own debug messenger, which
+//! outlives everything the scenario creates. Needs a display to open a window on and a
+//! Vulkan device with the validation layer installed; run with
+//! `cargo test --features enable_validations --test drop_order`.
+
+use std::ffi::CStr;
+
+use anyhow::{anyhow, ensure, Result};
+use rusty_games::{
+    CommandPool, CompositeAlphaPreference, DebugMessenger, GraphicsPipeline, Instance,
+    LogicalDevice, PhysicalDeviceSurface, PipelineConfig, RenderScale, Renderer, Shared,
+    StencilConfig, Surface, Swapchain, WindowConfig,
+};
+use winit::{event_loop::EventLoop, raw_window_handle::HasDisplayHandle, window::Window};
+
+/// A way of creating and dropping objects, returning how many validation errors fired
+type Scenario = fn(&Shared<Window>) -> Result<u32>;
+
+/// Drops the renderer after drawing, tearing down everything it owns at once
+fn renderer(window: &Shared<Window>) -> Result<u32> {
+    let mut renderer = Renderer::new(window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+    renderer.render_and_wait()?;
+    renderer.render_frame()?;
+    drop(renderer);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+/// Replaces the device and everything created from it while keeping the instance, as
+/// recovering from a lost device does
+fn recovered_renderer(window: &Shared<Window>) -> Result<u32> {
+    let mut renderer = Renderer::new(window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+    renderer.render_frame()?;
+    renderer.recover()?;
+    renderer.render_and_wait()?;
+    drop(renderer);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+/// Recreates the primary window's pipeline and command pool with frames still in flight
+fn recreated_pipeline(window: &Shared<Window>) -> Result<u32> {
+    let mut renderer = Renderer::new(window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+    renderer.render_frame()?;
+    renderer.set_depth_stencil(Some(StencilConfig::default()))?;
+    renderer.render_frame()?;
+    renderer.set_render_scale(RenderScale::new(0.5)?)?;
+    renderer.render_frame()?;
+    renderer.resize()?;
+    renderer.render_and_wait()?;
+    drop(renderer);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+/// Builds the graph by hand, then drops the handles to the instance, surface and device
+/// first, leaving the objects created from them to keep them alive
+fn handles_dropped_first(window: &Shared<Window>) -> Result<u32> {
+    let required_extensions =
+        ash_window::enumerate_required_extensions(window.display_handle()?.as_raw())?
+            .iter()
+            .map(|extension| unsafe { CStr::from_ptr(*extension) }.to_str())
+            .collect::<Result<Vec<_>, _>>()?;
+    let instance = Shared::new(Instance::new(required_extensions)?);
+    let debug_messenger = DebugMessenger::new(&instance)?;
+    let surface = Shared::new(Surface::new(&instance, window)?);
+    let physical_device_surface = pick_physical_device(&instance, &surface)?;
+    let logical_device = Shared::new(LogicalDevice::try_from(physical_device_surface)?);
+    let swapchain = Swapchain::new(
+        &instance,
+        window,
+        &logical_device,
+        CompositeAlphaPreference::default(),
+        None,
+    )?;
+    let pipeline =
+        GraphicsPipeline::new::<()>(&logical_device, &swapchain, PipelineConfig::default())?;
+    let command_pool = CommandPool::new(&logical_device, pipeline)?;
+
+    drop(logical_device);
+    drop(surface);
+    drop(instance);
+    // the pipeline's framebuffers point at the swapchain's image views
+    drop(command_pool);
+    drop(swapchain);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+fn pick_physical_device(
+    instance: &Shared<Instance>,
+    surface: &Shared<Surface>,
+) -> Result<PhysicalDeviceSurface> {
+    for physical_device in unsafe { instance.enumerate_physical_devices()? } {
+        let physical_device_surface =
+            PhysicalDeviceSurface::new(instance, surface, physical_device)?;
+        if physical_device_surface.is_suitable()?.is_ok() {
+            return Ok(physical_device_surface);
+        }
+    }
+    Err(anyhow!("Could not find a suitable physical device!"))
+}
+
+fn main() -> Result<()> {
+    // the event loop can only be created once, and only on the main thread on some
+    // platforms, so the scenarios share it instead of running as separate tests
+    let event_loop = EventLoop::new()?;
+    let window = Shared::new(WindowConfig::default().build(&event_loop)?);
+
+    let scenarios: [(&str, Scenario); 4] = [
+        ("renderer", renderer),
+        ("recovered renderer", recovered_renderer),
+        ("recreated pipeline", recreated_pipeline),
+        ("handles dropped first", handles_dropped_first),
+    ];
+    let mut failures = vec![];
+    for (name, scenario) in scenarios {
+        let error_count = scenario(&window)?;
+        println!("{name}: {error_count} validation error(s)");
+        if error_count > 0 {
+            failures.push(name);
+        }
+    }
+    ensure!(
+        failures.is_empty(),
+        "Validation errors fired in: {}",
+        failures.join(", ")
+    );
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Signals, resets and waits on fences on a real device, failing if one doesn't behave
+//! as documented or the validation layer reports any errors. Each scenario counts errors
+//! with its own debug messenger, which outlives everything the scenario creates. Needs a
+//! display to open a window on and a Vulkan device with the validation layer installed;
+//! run with `cargo test --features enable_validations --test fence`.
+
+use anyhow::{bail, ensure, Result};
+use ash::vk::{self, QueueFlags};
+use rusty_games::{DebugMessenger, Fence, Renderer, Shared, Submission, SubmitBatch, WindowConfig};
+use winit::{event_loop::EventLoop, window::Window};
+
+/// A way of using a fence, returning how many validation errors fired
+type Scenario = fn(&Shared<Window>) -> Result<u32>;
+
+/// Resets a fence created signaled
+fn reset_unsignals(window: &Shared<Window>) -> Result<u32> {
+    let renderer = Renderer::new(window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+    let fence = Fence::new(renderer.get_logical_device()?, true)?;
+    ensure!(fence.is_signaled()?, "The fence wasn't created signaled");
+    fence.reset()?;
+    ensure!(
+        !fence.is_signaled()?,
+        "The fence is still signaled after reset"
+    );
+    drop(fence);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+/// Waits on a fence nothing will signal, without blocking
+fn wait_times_out(window: &Shared<Window>) -> Result<u32> {
+    let renderer = Renderer::new(window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+    let fence = Fence::new(renderer.get_logical_device()?, false)?;
+    // Frame::wait_until_idle reports this as RustyGamesError::FenceTimeout
+    match fence.wait(0) {
+        Ok(()) => bail!("Waiting on an unsignaled fence succeeded"),
+        Err(error) => ensure!(
+            error.downcast_ref() == Some(&vk::Result::TIMEOUT),
+            "Waiting on an unsignaled fence failed with {error} instead of timing out"
+        ),
+    }
+    drop(fence);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+/// Reuses a signaled fence for a submission, as the frames in flight do
+fn reset_submit_wait(window: &Shared<Window>) -> Result<u32> {
+    let renderer = Renderer::new(window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+    let logical_device = renderer.get_logical_device()?;
+    let fence = Fence::new(logical_device, true)?;
+    fence.reset()?;
+    // an empty submission still signals the fence once the queue reaches it
+    SubmitBatch::new()
+        .add_submission(Submission::new())
+        .requires(QueueFlags::GRAPHICS)
+        .submit(logical_device, logical_device.graphics_queue(), *fence)?;
+    fence.wait_forever()?;
+    ensure!(
+        fence.is_signaled()?,
+        "The fence isn't signaled after waiting"
+    );
+    drop(fence);
+    Ok(debug_messenger.get_data().get_error_count())
+}
+
+fn main() -> Result<()> {
+    // the event loop can only be created once, and only on the main thread on some
+    // platforms, so the scenarios share it instead of running as separate tests
+    let event_loop = EventLoop::new()?;
+    let window = Shared::new(WindowConfig::default().build(&event_loop)?);
+
+    let scenarios: [(&str, Scenario); 3] = [
+        ("reset unsignals", reset_unsignals),
+        ("wait times out", wait_times_out),
+        ("reset, submit, wait", reset_submit_wait),
+    ];
+    let mut failures = vec![];
+    for (name, scenario) in scenarios {
+        let error_count = scenario(&window)?;
+        println!("{name}: {error_count} validation error(s)");
+        if error_count > 0 {
+            failures.push(name);
+        }
+    }
+    ensure!(
+        failures.is_empty(),
+        "Validation errors fired in: {}",
+        failures.join(", ")
+    );
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Renders a frame with the validation layer on, failing if it reported any errors. Needs
+//! a display to open a window on and a Vulkan device with the validation layer installed;
+//! run with `cargo test --features enable_validations --test validation`.
+
+use anyhow::{ensure, Result};
+use rusty_games::{DebugMessenger, Renderer, Shared};
+use winit::{event_loop::EventLoop, window::WindowBuilder};
+
+fn main() -> Result<()> {
+    // the event loop can only be created on the main thread on some platforms, which the
+    // default test harness doesn't run tests on
+    let event_loop = EventLoop::new()?;
+    let window = Shared::new(WindowBuilder::new().build(&event_loop)?);
+    let mut renderer = Renderer::new(&window)?;
+    let debug_messenger = DebugMessenger::new(renderer.get_instance()?)?;
+
+    renderer.render_frame()?;
+    renderer.wait_idle()?;
+
+    let error_count = debug_messenger.get_data().get_error_count();
+    ensure!(
+        error_count == 0,
+        "{error_count} validation error(s) fired while rendering a frame"
+    );
+    Ok(())
+}